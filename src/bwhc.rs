@@ -0,0 +1,300 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, FusionRecord, SimpleVariantRecord, ValueFormat, decimal,
+};
+use crate::mhguide::General;
+use crate::provenance::Provenance;
+use serde_json::{Value, json};
+
+fn integer(value: &str) -> Option<u64> {
+    value.parse::<u64>().ok()
+}
+
+fn gene(symbol: &str, hgnc_id: &str, ensembl_id: &str) -> Value {
+    let mut gene = json!({ "symbol": symbol });
+    if !hgnc_id.is_empty() {
+        gene["hgncId"] = json!(hgnc_id);
+    }
+    if !ensembl_id.is_empty() {
+        gene["ensemblId"] = json!(ensembl_id);
+    }
+    gene
+}
+
+/// Maps the classification to the `ClinVar` code used by bwHC
+fn clinvar_code(classification: &str) -> Option<&'static str> {
+    match classification.to_ascii_lowercase().as_str() {
+        "benign" => Some("1"),
+        "likely benign" => Some("2"),
+        "uncertain significance" | "vus" => Some("3"),
+        "likely pathogenic" => Some("4"),
+        "pathogenic" => Some("5"),
+        _ => None,
+    }
+}
+
+fn simple_variant(record: &SimpleVariantRecord, index: usize) -> Value {
+    let mut simple_variant = json!({
+        "id": format!("{}-snv-{index}", record.h_nummer),
+        "chromosome": record.chromosome,
+        "gene": gene(&record.gene, &record.hgnc_id, &record.ensembl_id),
+        "refAllele": record.ref_allele,
+        "altAllele": record.alt_allele,
+    });
+
+    if let Some(start) = integer(&record.start) {
+        simple_variant["startEnd"] = json!({
+            "start": start,
+            "end": integer(&record.end).unwrap_or(start)
+        });
+    }
+    if !record.cdna.is_empty() {
+        simple_variant["dnaChange"] = json!({ "code": record.cdna, "system": "HGVS" });
+    }
+    if !record.protein.is_empty() {
+        simple_variant["aminoAcidChange"] = json!({ "code": record.protein, "system": "HGVS" });
+    }
+    if let Some(read_depth) = record.read_depth {
+        simple_variant["readDepth"] = json!(read_depth);
+    }
+    if let Some(allelic_frequency) = decimal(record.allelic_frequency) {
+        simple_variant["allelicFrequency"] = json!(allelic_frequency);
+    }
+    if !record.dbsnp.is_empty() {
+        simple_variant["dbSNPId"] = json!(record.dbsnp);
+    }
+    if let Some(code) = clinvar_code(&record.classification) {
+        simple_variant["interpretation"] = json!({ "code": code, "system": "ClinVar" });
+    }
+    simple_variant
+}
+
+fn copy_number_variant(record: &CopyNumberRecord, index: usize) -> Value {
+    let mut copy_number_variant = json!({
+        "id": format!("{}-cnv-{index}", record.h_nummer),
+        "chromosome": record.chromosome,
+        "reportedAffectedGenes": [gene(&record.gene, &record.hgnc_id, &record.ensembl_id)],
+        "type": record.cnv_type.replace(' ', "-"),
+    });
+    if let Some(total_copy_number) = decimal(record.total_copy_number) {
+        copy_number_variant["totalCopyNumber"] = json!(total_copy_number.round());
+    }
+    copy_number_variant
+}
+
+fn rna_fusion(record: &FusionRecord, index: usize) -> Value {
+    fn partner(
+        symbol: &str,
+        hgnc_id: &str,
+        transcript_id: &str,
+        exon: &str,
+        position: &str,
+        strand: &str,
+    ) -> Value {
+        let mut partner = json!({
+            "gene": gene(symbol, hgnc_id, ""),
+            "transcriptId": transcript_id,
+            "exon": exon,
+            "strand": strand,
+        });
+        if let Some(position) = integer(position) {
+            partner["position"] = json!(position);
+        }
+        partner
+    }
+
+    let mut rna_fusion = json!({
+        "id": format!("{}-rna-fusion-{index}", record.h_nummer),
+        "fusionPartner5prime": partner(
+            &record.gene,
+            &record.hgnc_id_5,
+            &record.transcript_id_5,
+            &record.exon_id_5,
+            &record.transcript_position_5,
+            &record.strand_5,
+        ),
+        "fusionPartner3prime": partner(
+            &record.fusion_gene,
+            &record.hgnc_id_3,
+            &record.transcript_id_3,
+            &record.exon_id_3,
+            &record.transcript_position_3,
+            &record.strand_3,
+        ),
+    });
+    if let Some(number_reported_reads) = integer(&record.number_reported_reads) {
+        rna_fusion["reportedNumReads"] = json!(number_reported_reads);
+    }
+    rna_fusion
+}
+
+/// Creates a bwHC (DNPM 1.x) `MTBFile` containing the somatic NGS report of the case.
+///
+/// Patient, consent and episode only contain the required fields, the gender of the patient is
+/// not contained in MH Guide files and exported as `unknown`. DNA fusions are not part of MH Guide
+/// reports and are exported as empty list. The NGS report has no field for the HRD score, which is
+/// therefore exported as molecular pathology finding. The provenance is added to the metadata of
+/// the NGS report.
+pub(crate) fn mtb_file(
+    general: &General,
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    fusion_records: &[FusionRecord],
+    biomarker_records: &[BiomarkerRecord],
+    provenance: Option<&Provenance>,
+) -> Value {
+    let patient_id = &general.patient_identifier.pid;
+    let h_number = &general.patient_identifier.h_number;
+
+    let mut ngs_report = json!({
+        "id": h_number,
+        "patient": patient_id,
+        "specimen": h_number,
+        "issueDate": general.order_date,
+        "sequencingType": "tNGS",
+        "metadata": [{
+            "kitType": "MH Guide",
+            "kitManufacturer": "Molecular Health",
+            "sequencer": "unknown",
+            "referenceGenome": general.ref_genome_version.to_string(),
+            "pipeline": "MH Guide"
+        }],
+        "simpleVariants": simple_variant_records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| simple_variant(record, index + 1))
+            .collect::<Vec<_>>(),
+        "copyNumberVariants": copy_number_records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| copy_number_variant(record, index + 1))
+            .collect::<Vec<_>>(),
+        "dnaFusions": [],
+        "rnaFusions": fusion_records
+            .iter()
+            .enumerate()
+            .map(|(index, record)| rna_fusion(record, index + 1))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(provenance) = provenance {
+        let metadata = &mut ngs_report["metadata"][0];
+        metadata["pipeline"] = json!(format!("MH Guide, mhguide-to-csv {}", provenance.version));
+        for (name, value) in provenance.entries() {
+            metadata[name] = json!(value);
+        }
+    }
+
+    let mut molecular_pathology_findings = vec![];
+    for record in biomarker_records {
+        if let Some(tmb) = decimal(record.tmb) {
+            ngs_report["tmb"] = json!(tmb);
+        }
+        if let Some(msi) = decimal(record.msi) {
+            ngs_report["msi"] = json!(msi);
+        }
+        if let Some(hrd) = record.hrd {
+            molecular_pathology_findings.push(json!({
+                "id": format!("{h_number}-hrd"),
+                "patient": patient_id,
+                "specimen": h_number,
+                "issuedOn": general.order_date,
+                "note": format!("HRD-Score: {}", ValueFormat::default().decimal(hrd))
+            }));
+        }
+    }
+
+    json!({
+        "patient": { "id": patient_id, "gender": "unknown" },
+        "consent": {
+            "id": format!("{patient_id}-consent"),
+            "patient": patient_id,
+            "status": "active"
+        },
+        "episode": {
+            "id": format!("{patient_id}-episode"),
+            "patient": patient_id,
+            "period": { "start": general.order_date }
+        },
+        "molecularPathologyFindings": molecular_pathology_findings,
+        "ngsReports": [ngs_report]
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::bwhc::mtb_file;
+    use crate::export_record::{BiomarkerRecord, SimpleVariantRecord};
+    use crate::mhguide::MhGuide;
+
+    #[test]
+    fn test_should_create_mtb_file() {
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        let general = &mhguide.general;
+
+        let simple_variant_records = mhguide
+            .all_variants()
+            .iter()
+            .map(|variant| {
+                SimpleVariantRecord::from_variant(
+                    &general.patient_identifier.h_number,
+                    &general.ref_genome_version,
+                    variant,
+                )
+            })
+            .collect::<Vec<_>>();
+        let biomarker_records = vec![
+            BiomarkerRecord::from_tmb(
+                &general.patient_identifier.h_number,
+                &general.ref_genome_version,
+                5.2,
+            ),
+            BiomarkerRecord::from_hrd(
+                &general.patient_identifier.h_number,
+                &general.ref_genome_version,
+                12.0,
+            ),
+        ];
+
+        let actual = mtb_file(
+            general,
+            &simple_variant_records,
+            &[],
+            &[],
+            &biomarker_records,
+            None,
+        );
+
+        assert_eq!(actual["patient"]["id"], "PID0123456");
+        assert_eq!(actual["patient"]["gender"], "unknown");
+        assert_eq!(
+            actual["molecularPathologyFindings"][0]["note"],
+            "HRD-Score: 12,00"
+        );
+        let ngs_report = &actual["ngsReports"][0];
+        assert_eq!(ngs_report["issueDate"], "2026-02-11");
+        assert_eq!(ngs_report["tmb"], 5.2);
+        assert_eq!(ngs_report["simpleVariants"][0]["chromosome"], "chr1");
+        assert_eq!(
+            ngs_report["simpleVariants"][0]["interpretation"]["code"],
+            "2"
+        );
+    }
+    #[test]
+    fn test_should_write_provenance_into_ngs_report_metadata() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = crate::read(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual = crate::bwhc_json(&mhguide.general, &records).unwrap();
+        let actual = serde_json::from_str::<serde_json::Value>(&actual).unwrap();
+
+        let metadata = &actual["ngsReports"][0]["metadata"][0];
+        assert_eq!(metadata["kitType"], "MH Guide");
+        assert_eq!(
+            metadata["pipeline"],
+            format!("MH Guide, mhguide-to-csv {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(metadata["inputSha256"], crate::provenance::sha256(&content));
+    }
+}
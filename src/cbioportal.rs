@@ -0,0 +1,431 @@
+use crate::export_record::{CopyNumberRecord, FusionRecord, MafRecord};
+use crate::input::InputSource;
+use crate::mhguide::Variant;
+use itertools::Itertools;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// A single case (sample) of a cBioPortal study, built from one MH Guide file.
+pub(crate) struct Case {
+    patient_id: String,
+    sample_id: String,
+    diagnosis: String,
+    order_date: String,
+    report_version: Option<u32>,
+    ref_genome: String,
+    maf_records: Vec<MafRecord>,
+    copy_number_records: Vec<CopyNumberRecord>,
+    fusion_records: Vec<FusionRecord>,
+}
+
+impl Case {
+    /// Constructs a `Case` from the given input source and its already selected variants.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The input source providing general case data and fusions.
+    /// * `maf_records` - The MAF records of the selected simple variants to be exported as mutations.
+    /// * `copy_number_variants` - The selected copy number variants to be exported as discrete CNA.
+    #[must_use]
+    pub(crate) fn new(
+        source: &dyn InputSource,
+        maf_records: Vec<MafRecord>,
+        copy_number_variants: &[&Variant],
+    ) -> Case {
+        let general = source.general();
+        let h_number = &general.patient_identifier.h_number;
+
+        Case {
+            patient_id: general.patient_identifier.pid.clone(),
+            sample_id: h_number.clone(),
+            diagnosis: general.disease_name.clone().unwrap_or_default(),
+            order_date: general.order_date.clone(),
+            report_version: general.report_version,
+            ref_genome: general.ref_genome_version.to_string(),
+            maf_records,
+            copy_number_records: copy_number_variants
+                .iter()
+                .map(|variant| {
+                    CopyNumberRecord::from_variant(h_number, &general.ref_genome_version, variant)
+                })
+                .collect(),
+            fusion_records: source
+                .fusions()
+                .iter()
+                .map(|fusion| {
+                    FusionRecord::from_fusion(h_number, &general.ref_genome_version, fusion)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClinicalSampleRecord<'a> {
+    #[serde(rename = "PATIENT_ID")]
+    patient_id: &'a str,
+    #[serde(rename = "SAMPLE_ID")]
+    sample_id: &'a str,
+    #[serde(rename = "DIAGNOSIS")]
+    diagnosis: &'a str,
+    #[serde(rename = "ORDER_DATE")]
+    order_date: &'a str,
+    #[serde(rename = "REF_GENOME")]
+    ref_genome: &'a str,
+}
+
+#[derive(Serialize)]
+struct StructuralVariantRecord<'a> {
+    #[serde(rename = "Sample_Id")]
+    sample_id: &'a str,
+    #[serde(rename = "SV_Status")]
+    sv_status: &'a str,
+    #[serde(rename = "Site1_Hugo_Symbol")]
+    site1_hugo_symbol: &'a str,
+    #[serde(rename = "Site1_Region_Number")]
+    site1_region_number: &'a str,
+    #[serde(rename = "Site1_Position")]
+    site1_position: &'a str,
+    #[serde(rename = "Site2_Hugo_Symbol")]
+    site2_hugo_symbol: &'a str,
+    #[serde(rename = "Site2_Region_Number")]
+    site2_region_number: &'a str,
+    #[serde(rename = "Site2_Position")]
+    site2_position: &'a str,
+    #[serde(rename = "Event_Info")]
+    event_info: String,
+    #[serde(rename = "RNA_Support")]
+    rna_support: &'a str,
+    #[serde(rename = "Tumor_Paired_End_Read_Count")]
+    tumor_paired_end_read_count: &'a str,
+}
+
+/// Total copy number below which a loss is exported as deep deletion. MH Guide reports
+/// the total copy number as a decimal, a value below 0.5 rounds to no remaining copy,
+/// i.e. a homozygous deletion.
+const DEEP_DELETION_COPY_NUMBER: f32 = 0.5;
+
+/// Maps a `CopyNumberRecord` to a discrete cBioPortal copy number value.
+///
+/// * `-2` - Deep deletion, a loss with a total copy number below `DEEP_DELETION_COPY_NUMBER`
+/// * `-1` - Shallow deletion, any other loss
+/// * `1` - Low level gain
+/// * `2` - High level gain (amplification)
+/// * `0` - Anything else
+fn discrete_copy_number(record: &CopyNumberRecord) -> i8 {
+    match record.cnv_type.as_str() {
+        "loss" => match record.total_copy_number {
+            Some(value) if value < DEEP_DELETION_COPY_NUMBER => -2,
+            _ => -1,
+        },
+        "low level gain" => 1,
+        "high level gain" => 2,
+        _ => 0,
+    }
+}
+
+fn write_tsv<T: Serialize>(
+    path: &Path,
+    prefix: &str,
+    records: &[T],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(prefix.as_bytes().to_vec());
+
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    fs::write(path, writer.into_inner()?).map_err(Into::into)
+}
+
+fn write_meta_file(
+    path: &Path,
+    entries: &[(&str, &str)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = entries
+        .iter()
+        .map(|(key, value)| format!("{key}: {value}\n"))
+        .join("");
+    fs::write(path, content).map_err(Into::into)
+}
+
+/// All cases of one sample, cBioPortal rejects studies with duplicate samples
+struct Sample<'a> {
+    /// Case with the latest order date and report version, used for clinical data
+    latest: &'a Case,
+    maf_records: Vec<&'a MafRecord>,
+    copy_number_records: Vec<&'a CopyNumberRecord>,
+    fusion_records: Vec<&'a FusionRecord>,
+}
+
+/// Merges the cases of each sample, e.g. of a DNA and an RNA report.
+///
+/// Records of the same variant, gene or fusion contained in several cases are taken from the
+/// case with the latest order date and report version.
+fn samples(cases: &[Case]) -> Vec<Sample<'_>> {
+    cases
+        .iter()
+        .map(|case| case.sample_id.as_str())
+        .unique()
+        .filter_map(|sample_id| {
+            let cases = cases
+                .iter()
+                .filter(|case| case.sample_id == sample_id)
+                .sorted_by_key(|case| (case.order_date.as_str(), case.report_version))
+                .rev()
+                .collect_vec();
+            Some(Sample {
+                latest: cases.first()?,
+                maf_records: cases
+                    .iter()
+                    .flat_map(|case| case.maf_records.iter())
+                    .unique_by(|record| record.variant_key())
+                    .collect(),
+                copy_number_records: cases
+                    .iter()
+                    .flat_map(|case| case.copy_number_records.iter())
+                    .unique_by(|record| record.gene.as_str())
+                    .collect(),
+                fusion_records: cases
+                    .iter()
+                    .flat_map(|case| case.fusion_records.iter())
+                    .unique_by(|record| (record.gene.as_str(), record.fusion_gene.as_str()))
+                    .collect(),
+            })
+        })
+        .collect_vec()
+}
+
+/// Writes a complete cBioPortal study into the given directory.
+///
+/// The directory is created if it does not exist. The study identifier is taken from
+/// the name of the directory. Cases of the same sample are merged into one sample.
+/// The study contains
+///
+/// * `data_clinical_sample.txt` with diagnosis and order date of each case,
+/// * `data_mutations.txt` in MAF format,
+/// * `data_cna.txt` with discrete copy number values per gene and sample,
+/// * `data_sv.txt` with RNA fusions as structural variants,
+///
+/// and the required meta files.
+///
+/// # Errors
+///
+/// Returns an error if the directory or one of the files cannot be written.
+pub(crate) fn write_study(path: &Path, cases: &[Case]) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(path)?;
+    let samples = samples(cases);
+
+    let study_id = path.file_name().map_or_else(
+        || "mhguide".to_string(),
+        |name| name.to_string_lossy().to_lowercase().replace(' ', "_"),
+    );
+
+    write_meta_file(
+        &path.join("meta_study.txt"),
+        &[
+            ("type_of_cancer", "mixed"),
+            ("cancer_study_identifier", &study_id),
+            ("name", &format!("MH Guide ({study_id})")),
+            ("description", "Export von MH Guide Fällen"),
+            ("add_global_case_list", "true"),
+        ],
+    )?;
+
+    write_meta_file(
+        &path.join("meta_clinical_sample.txt"),
+        &[
+            ("cancer_study_identifier", &study_id),
+            ("genetic_alteration_type", "CLINICAL"),
+            ("datatype", "SAMPLE_ATTRIBUTES"),
+            ("data_filename", "data_clinical_sample.txt"),
+        ],
+    )?;
+    write_clinical_sample_file(&path.join("data_clinical_sample.txt"), &samples)?;
+
+    write_meta_file(
+        &path.join("meta_mutations.txt"),
+        &[
+            ("cancer_study_identifier", &study_id),
+            ("genetic_alteration_type", "MUTATION_EXTENDED"),
+            ("datatype", "MAF"),
+            ("stable_id", "mutations"),
+            ("show_profile_in_analysis_tab", "true"),
+            ("profile_name", "Mutations"),
+            ("profile_description", "Einfache Varianten aus MH Guide"),
+            ("data_filename", "data_mutations.txt"),
+        ],
+    )?;
+    write_tsv(
+        &path.join("data_mutations.txt"),
+        "#version 2.4\n",
+        &samples
+            .iter()
+            .flat_map(|sample| sample.maf_records.iter())
+            .collect_vec(),
+    )?;
+
+    write_meta_file(
+        &path.join("meta_cna.txt"),
+        &[
+            ("cancer_study_identifier", &study_id),
+            ("genetic_alteration_type", "COPY_NUMBER_ALTERATION"),
+            ("datatype", "DISCRETE"),
+            ("stable_id", "cna"),
+            ("show_profile_in_analysis_tab", "true"),
+            ("profile_name", "Copy Number Variants"),
+            ("profile_description", "Copy Number Varianten aus MH Guide"),
+            ("data_filename", "data_cna.txt"),
+        ],
+    )?;
+    write_cna_file(&path.join("data_cna.txt"), &samples)?;
+
+    write_meta_file(
+        &path.join("meta_sv.txt"),
+        &[
+            ("cancer_study_identifier", &study_id),
+            ("genetic_alteration_type", "STRUCTURAL_VARIANT"),
+            ("datatype", "SV"),
+            ("stable_id", "structural_variants"),
+            ("show_profile_in_analysis_tab", "true"),
+            ("profile_name", "Structural Variants"),
+            ("profile_description", "RNA Fusionen aus MH Guide"),
+            ("data_filename", "data_sv.txt"),
+        ],
+    )?;
+    write_sv_file(&path.join("data_sv.txt"), &samples)
+}
+
+fn write_clinical_sample_file(
+    path: &Path,
+    samples: &[Sample],
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_tsv(
+        path,
+        "#Patient Identifier\tSample Identifier\tDiagnosis\tOrder Date\tReference Genome\n\
+         #Patient Identifier\tSample Identifier\tDiagnosis (PT_DISEASE_NAME)\tOrder date\tReference genome version\n\
+         #STRING\tSTRING\tSTRING\tSTRING\tSTRING\n\
+         #1\t1\t1\t1\t1\n",
+        &samples
+            .iter()
+            .map(|sample| ClinicalSampleRecord {
+                patient_id: &sample.latest.patient_id,
+                sample_id: &sample.latest.sample_id,
+                diagnosis: &sample.latest.diagnosis,
+                order_date: &sample.latest.order_date,
+                ref_genome: &sample.latest.ref_genome,
+            })
+            .collect_vec(),
+    )
+}
+
+fn write_sv_file(path: &Path, samples: &[Sample]) -> Result<(), Box<dyn std::error::Error>> {
+    write_tsv(
+        path,
+        "",
+        &samples
+            .iter()
+            .flat_map(|sample| sample.fusion_records.iter())
+            .map(|record| StructuralVariantRecord {
+                sample_id: &record.h_nummer,
+                sv_status: "SOMATIC",
+                site1_hugo_symbol: &record.gene,
+                site1_region_number: record.exon_id_5.trim_start_matches("Exon"),
+                site1_position: &record.transcript_position_5,
+                site2_hugo_symbol: &record.fusion_gene,
+                site2_region_number: record.exon_id_3.trim_start_matches("Exon"),
+                site2_position: &record.transcript_position_3,
+                event_info: format!(
+                    "{}-{} Fusion ({}/{})",
+                    record.gene, record.fusion_gene, record.transcript_id_5, record.transcript_id_3
+                ),
+                rna_support: "Yes",
+                tumor_paired_end_read_count: &record.number_reported_reads,
+            })
+            .collect_vec(),
+    )
+}
+
+fn write_cna_file(path: &Path, samples: &[Sample]) -> Result<(), Box<dyn std::error::Error>> {
+    let genes = samples
+        .iter()
+        .flat_map(|sample| sample.copy_number_records.iter())
+        .map(|record| record.gene.as_str())
+        .unique()
+        .sorted()
+        .collect_vec();
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(vec![]);
+
+    let mut headlines = vec!["Hugo_Symbol"];
+    headlines.extend(
+        samples
+            .iter()
+            .map(|sample| sample.latest.sample_id.as_str()),
+    );
+    writer.write_record(headlines)?;
+
+    for gene in genes {
+        let mut row = vec![gene.to_string()];
+        row.extend(samples.iter().map(|sample| {
+            sample
+                .copy_number_records
+                .iter()
+                .find(|record| record.gene == gene)
+                .map_or(0, |record| discrete_copy_number(record))
+                .to_string()
+        }));
+        writer.write_record(row)?;
+    }
+
+    fs::write(path, writer.into_inner()?).map_err(Into::into)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::cbioportal::{Case, write_study};
+    use crate::mhguide::MhGuide;
+    use std::fs;
+
+    #[test]
+    fn test_should_write_study() {
+        static CNV_MHGUIDE: &str = include_str!("../testfiles/cnv-mhguide.json");
+        static RNA_FUSION_MHGUIDE: &str = include_str!("../testfiles/rnafusion-mhguide.json");
+
+        let cnv_mhguide = serde_json::from_str::<MhGuide>(&CNV_MHGUIDE.replace(
+            r#""PROTEIN_MODIFICATION": null"#,
+            r#""PROTEIN_MODIFICATION": "Copy number gain""#,
+        ))
+        .unwrap();
+        let rna_fusion_mhguide = serde_json::from_str::<MhGuide>(RNA_FUSION_MHGUIDE).unwrap();
+
+        let cases = vec![
+            Case::new(&rna_fusion_mhguide, vec![], &[]),
+            Case::new(&cnv_mhguide, vec![], &cnv_mhguide.all_variants()),
+        ];
+
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let path = directory.join("mhguide_cbioportal_test");
+        write_study(&path, &cases).unwrap();
+
+        let meta_study = fs::read_to_string(path.join("meta_study.txt")).unwrap();
+        assert!(meta_study.contains("cancer_study_identifier: mhguide_cbioportal_test\n"));
+
+        let cna = fs::read_to_string(path.join("data_cna.txt")).unwrap();
+        assert_eq!(cna, "Hugo_Symbol\tH10000-26\nBRAF\t2\n");
+
+        let sv = fs::read_to_string(path.join("data_sv.txt")).unwrap();
+        assert!(sv.contains("H10000-26\tSOMATIC\tABCD1\t1\t12345678\tABCD2\t2\t13456789"));
+
+        let clinical = fs::read_to_string(path.join("data_clinical_sample.txt")).unwrap();
+        assert!(clinical.contains("PID0123456\tH10000-26\tPancreatic Neoplasms\t2026-02-11\tHG19"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,12 +1,22 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use mhguide_to_csv::{Encoding, Language, LineEnding, OutputTarget, QuoteStyle, exporters};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(author, version, about)]
-#[command(arg_required_else_help(true))]
+#[command(arg_required_else_help(true), subcommand_negates_reqs(true))]
+#[command(group(ArgGroup::new("output_format").args([
+    "xlsx", "ods", "html", "template", "json", "maf", "fhir", "phenopacket", "obds", "cbioportal",
+    "parquet", "sqlite", "format",
+])))]
 pub(crate) struct Cli {
-    #[arg(help = "Zu lesende JSON-Datei")]
-    pub(crate) input_file: PathBuf,
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
+    #[arg(required = true, help = "Zu lesende JSON-, ZIP- oder VCF-Datei(en)")]
+    pub(crate) input_files: Vec<PathBuf>,
 
     #[arg(
         long,
@@ -30,17 +40,319 @@ pub(crate) struct Cli {
     )]
     pub(crate) no_artifacts: bool,
 
+    #[arg(long, help = "Exportiere im XLSX-Format (Excel 2007-365)")]
+    pub(crate) xlsx: bool,
+
+    #[arg(long, help = "Exportiere im ODS-Format (OpenDocument, LibreOffice)")]
+    pub(crate) ods: bool,
+
     #[arg(
         long,
-        conflicts_with = "json",
-        help = "Exportiere im XLSX-Format (Excel 2007-365)"
+        help = "Erzeuge einen HTML-Bericht zur Durchsicht der exportierten Angaben"
     )]
-    pub(crate) xlsx: bool,
+    pub(crate) html: bool,
 
     #[arg(
         long,
-        conflicts_with = "xlsx",
-        help = "Exportiere JSON gemäß DNPM-Datenmodell 2.1"
+        value_name = "TEMPLATE_FILE",
+        help = "Erzeuge die Ausgabe mit der angegebenen Tera-Vorlage"
     )]
+    pub(crate) template: Option<PathBuf>,
+
+    #[arg(long, help = "Exportiere JSON gemäß DNPM-Datenmodell 2.1")]
     pub(crate) json: bool,
+
+    #[arg(
+        long,
+        help = "Exportiere Einfache Varianten im MAF-Format (Mutation Annotation Format)"
+    )]
+    pub(crate) maf: bool,
+
+    #[arg(
+        long,
+        help = "Exportiere als HL7 FHIR R4 Bundle gemäß Genomics Reporting IG"
+    )]
+    pub(crate) fhir: bool,
+
+    #[arg(long, help = "Exportiere das FHIR Bundle als Transaction-Bundle")]
+    pub(crate) fhir_transaction: bool,
+
+    #[arg(long, help = "Exportiere als GA4GH Phenopacket (Version 2)")]
+    pub(crate) phenopacket: bool,
+
+    #[arg(
+        long,
+        help = "Exportiere als oBDS-XML (Modul Genetik) für das Krebsregister"
+    )]
+    pub(crate) obds: bool,
+
+    #[arg(
+        long,
+        value_name = "STUDY_DIR",
+        help = "Exportiere alle Dateien als cBioPortal-Studie in das angegebene Verzeichnis"
+    )]
+    pub(crate) cbioportal: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "OUTPUT_DIR",
+        help = "Exportiere alle Dateien als Parquet-Dateien je Ergebnistyp in das angegebene Verzeichnis"
+    )]
+    pub(crate) parquet: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DB",
+        help = "Übernehme alle Dateien in die angegebene SQLite-Datenbank"
+    )]
+    pub(crate) sqlite: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_parser = PossibleValuesParser::new(
+            exporters()
+                .iter()
+                .filter(|exporter| exporter.target() == OutputTarget::File)
+                .map(|exporter| exporter.name())
+        ),
+        help = "Exportiere im angegebenen Format"
+    )]
+    pub(crate) format: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PROFILE_FILE",
+        help = "Verwende Auswahl, Reihenfolge und Namen der Spalten aus der Profildatei (CSV, XLSX, ODS)"
+    )]
+    pub(crate) profile: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with = "output_format",
+        help = "Schreibe alle Ergebnistypen in eine einzige CSV-Tabelle mit Spalten für Ergebnistyp und Zeilen-ID"
+    )]
+    pub(crate) flat: bool,
+
+    #[arg(
+        long,
+        help = "Ersetze H-Nummer und PID in allen Ausgaben durch Pseudonyme und entferne den Befundtext"
+    )]
+    pub(crate) pseudonymize: bool,
+
+    #[arg(
+        long,
+        requires = "pseudonymize",
+        conflicts_with = "pseudonym_mapping",
+        help = "Datei mit dem geheimen Schlüssel für Pseudonyme, sonst wird MHGUIDE_TO_CSV_PSEUDONYM_KEY verwendet"
+    )]
+    pub(crate) pseudonym_key_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "pseudonymize",
+        help = "CSV-Datei mit Zuordnung von H-Nummern und PIDs zu Pseudonymen"
+    )]
+    pub(crate) pseudonym_mapping: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Language::De,
+        help = "Sprache der Spaltenüberschriften, Ergebnistypen und Tabellenblätter (CSV, XLSX, ODS)"
+    )]
+    pub(crate) lang: Language,
+
+    #[arg(
+        long,
+        default_value_t = ';',
+        help = "Trennzeichen zwischen den Spalten in CSV-Dateien"
+    )]
+    pub(crate) delimiter: char,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = QuoteStyle::Necessary,
+        help = "Verwendung von Anführungszeichen in CSV-Dateien"
+    )]
+    pub(crate) quote_style: QuoteStyle,
+
+    #[arg(
+        long,
+        default_value_t = ',',
+        help = "Dezimaltrennzeichen für Zahlen in CSV-Dateien"
+    )]
+    pub(crate) decimal_separator: char,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LineEnding::Lf,
+        help = "Zeilenende in CSV-Dateien"
+    )]
+    pub(crate) line_ending: LineEnding,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Encoding::Utf8,
+        help = "Zeichenkodierung von CSV-Dateien"
+    )]
+    pub(crate) encoding: Encoding,
+
+    #[arg(
+        long,
+        help = "Herkunftsangaben als Kommentarzeilen (#) an CSV-Dateien anhängen"
+    )]
+    pub(crate) csv_provenance: bool,
+}
+
+impl Cli {
+    /// Returns the name of the selected output format, options like `--xlsx` are shortcuts
+    /// for `--format xlsx`
+    pub(crate) fn format_name(&self) -> &str {
+        let shortcuts = [
+            (self.maf, "maf"),
+            (self.xlsx, "xlsx"),
+            (self.ods, "ods"),
+            (self.html, "html"),
+            (self.fhir, "fhir"),
+            (self.phenopacket, "phenopacket"),
+            (self.obds, "obds"),
+            (self.json, "json"),
+            (self.cbioportal.is_some(), "cbioportal"),
+            (self.parquet.is_some(), "parquet"),
+            (self.sqlite.is_some(), "sqlite"),
+        ];
+        shortcuts
+            .into_iter()
+            .find_map(|(selected, name)| selected.then_some(name))
+            .or(self.format.as_deref())
+            .unwrap_or("csv")
+    }
+
+    /// Checks options that depend on the resolved output format, e.g. `--fhir-transaction`
+    /// is valid with `--fhir` and `--format fhir`
+    pub(crate) fn validate(&self) -> Result<(), clap::Error> {
+        if self.fhir_transaction && self.format_name() != "fhir" {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                "--fhir-transaction can only be used with --fhir or --format fhir",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the directory or database file of formats writing all input files together,
+    /// e.g. given with `--parquet <OUTPUT_DIR>`
+    pub(crate) fn output_path(&self) -> Option<&Path> {
+        self.cbioportal
+            .as_deref()
+            .or(self.parquet.as_deref())
+            .or(self.sqlite.as_deref())
+    }
+}
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Überwache ein Verzeichnis und konvertiere neue oder geänderte JSON-, ZIP- und VCF-Dateien
+    Watch {
+        #[arg(help = "Zu überwachendes Verzeichnis")]
+        directory: PathBuf,
+
+        #[arg(
+            long,
+            default_value_t = 5,
+            help = "Abstand zwischen zwei Prüfungen des Verzeichnisses in Sekunden"
+        )]
+        interval: u64,
+    },
+    /// Starte einen HTTP-Dienst zur Konvertierung hochgeladener JSON-, ZIP- und VCF-Dateien
+    Serve {
+        #[arg(
+            long,
+            default_value = "127.0.0.1:8080",
+            help = "Adresse und Port des HTTP-Dienstes"
+        )]
+        address: String,
+
+        #[arg(
+            long,
+            default_value_t = 20,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=1024),
+            help = "Maximale Größe hochgeladener Dateien in MiB (1 bis 1024)"
+        )]
+        max_size: usize,
+    },
+    /// Lese bearbeitete CSV- oder XLSX-Dateien wieder ein und erstelle daraus DNPM JSON
+    Import {
+        #[arg(required = true, help = "Zu lesende CSV- oder XLSX-Datei(en)")]
+        input_files: Vec<PathBuf>,
+    },
+    /// Lese DNPM JSON-Dateien und erstelle daraus CSV-, XLSX- oder ODS-Dateien
+    FromDnpm {
+        #[arg(
+            required = true,
+            help = "Zu lesende DNPM JSON-Datei(en), ohne Patienten-ID wird der Dateiname als H-Nummer verwendet"
+        )]
+        input_files: Vec<PathBuf>,
+    },
+    /// Vergleiche zwei Dateien eines Falls und zeige neue, entfernte und geänderte Varianten
+    Diff {
+        #[arg(help = "Ältere JSON-, ZIP- oder VCF-Datei")]
+        old_file: PathBuf,
+
+        #[arg(help = "Neuere JSON-, ZIP- oder VCF-Datei")]
+        new_file: PathBuf,
+
+        #[arg(
+            long,
+            value_enum,
+            default_value_t = DiffFormat::Table,
+            help = "Ausgabeformat der Änderungen"
+        )]
+        output: DiffFormat,
+    },
+}
+
+/// Output format of the `diff` subcommand
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum DiffFormat {
+    /// Tabelle
+    Table,
+    /// JSON
+    Json,
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::cli::Cli;
+    use clap::Parser;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&["--fhir", "--fhir-transaction"], true)]
+    #[case(&["--format", "fhir", "--fhir-transaction"], true)]
+    #[case(&["--format", "csv", "--fhir-transaction"], false)]
+    #[case(&["--fhir-transaction"], false)]
+    fn test_should_validate_fhir_transaction(#[case] args: &[&str], #[case] valid: bool) {
+        let cli =
+            Cli::try_parse_from(["mhguide-to-csv"].iter().chain(args).chain(&["H1.json"])).unwrap();
+
+        assert_eq!(cli.validate().is_ok(), valid);
+    }
+
+    #[rstest]
+    #[case(&["--xlsx", "--ods"])]
+    #[case(&["--xlsx", "--fhir"])]
+    #[case(&["--json", "--format", "csv"])]
+    #[case(&["--html", "--sqlite", "db.sqlite"])]
+    #[case(&["--flat", "--xlsx"])]
+    #[case(&["--format", "sqlite"])]
+    fn test_should_reject_conflicting_formats(#[case] args: &[&str]) {
+        assert!(
+            Cli::try_parse_from(["mhguide-to-csv"].iter().chain(args).chain(&["H1.json"])).is_err()
+        );
+    }
 }
@@ -0,0 +1,334 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, FusionRecord, Records, SimpleVariantRecord, integer,
+};
+use arrow_array::{ArrayRef, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Records of one MH Guide file with its order date
+pub(crate) struct Case<'a> {
+    pub(crate) order_date: &'a str,
+    pub(crate) records: &'a Records,
+}
+
+/// Days since 1970-01-01 as used by the Arrow `Date32` type
+fn date(value: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let days = date.signed_duration_since(NaiveDate::default()).num_days();
+    i32::try_from(days).ok()
+}
+
+/// Builder for the typed columns of one Parquet file.
+///
+/// Each row consists of the case and one record. The provenance of all cases is written into the
+/// key-value metadata of the file.
+struct Columns<'a, T> {
+    rows: Vec<(&'a Case<'a>, &'a T)>,
+    provenance: String,
+    fields: Vec<Field>,
+    arrays: Vec<ArrayRef>,
+}
+
+impl<'a, T> Columns<'a, T> {
+    fn new(cases: &'a [Case<'a>], records: impl Fn(&'a Records) -> &'a [T]) -> Self {
+        let rows = cases
+            .iter()
+            .flat_map(|case| {
+                records(case.records)
+                    .iter()
+                    .map(move |record| (case, record))
+            })
+            .collect();
+        let provenance = cases
+            .iter()
+            .filter_map(|case| case.records.provenance.as_ref())
+            .map(|provenance| {
+                provenance
+                    .entries()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), serde_json::Value::from(value)))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        Columns {
+            rows,
+            provenance: serde_json::Value::from(provenance).to_string(),
+            fields: vec![],
+            arrays: vec![],
+        }
+    }
+
+    fn string(mut self, name: &str, value: impl Fn(&T) -> &str) -> Self {
+        self.fields.push(Field::new(name, DataType::Utf8, false));
+        self.arrays.push(Arc::new(StringArray::from_iter_values(
+            self.rows.iter().map(|(_, record)| value(record)),
+        )));
+        self
+    }
+
+    fn integer(mut self, name: &str, value: impl Fn(&T) -> Option<i64>) -> Self {
+        self.fields.push(Field::new(name, DataType::Int64, true));
+        self.arrays.push(Arc::new(
+            self.rows
+                .iter()
+                .map(|(_, record)| value(record))
+                .collect::<Int64Array>(),
+        ));
+        self
+    }
+
+    fn float(mut self, name: &str, value: impl Fn(&T) -> Option<f64>) -> Self {
+        self.fields.push(Field::new(name, DataType::Float64, true));
+        self.arrays.push(Arc::new(
+            self.rows
+                .iter()
+                .map(|(_, record)| value(record))
+                .collect::<Float64Array>(),
+        ));
+        self
+    }
+
+    fn order_date(mut self) -> Self {
+        self.fields
+            .push(Field::new("order_date", DataType::Date32, true));
+        self.arrays.push(Arc::new(
+            self.rows
+                .iter()
+                .map(|(case, _)| date(case.order_date))
+                .collect::<Date32Array>(),
+        ));
+        self
+    }
+
+    /// SHA-256 of the file the case was read from
+    fn input_sha256(mut self) -> Self {
+        self.fields
+            .push(Field::new("input_sha256", DataType::Utf8, true));
+        self.arrays.push(Arc::new(
+            self.rows
+                .iter()
+                .map(|(case, _)| {
+                    case.records
+                        .provenance
+                        .as_ref()
+                        .and_then(|provenance| provenance.input_sha256.as_deref())
+                })
+                .collect::<StringArray>(),
+        ));
+        self
+    }
+
+    /// Writes the Parquet file, if there is at least one row. Otherwise, a file written into
+    /// the same directory before is removed.
+    fn write(self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if self.rows.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        let schema = Arc::new(Schema::new(self.fields));
+        let batch = RecordBatch::try_new(schema.clone(), self.arrays)?;
+        let properties = WriterProperties::builder()
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                "provenance".to_string(),
+                self.provenance,
+            )]))
+            .build();
+        let mut writer = ArrowWriter::try_new(fs::File::create(path)?, schema, Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+fn write_simple_variants(path: &Path, cases: &[Case]) -> Result<(), Box<dyn std::error::Error>> {
+    Columns::new(cases, |records| &records.simple_variants)
+        .string("h_number", |r: &SimpleVariantRecord| &r.h_nummer)
+        .order_date()
+        .input_sha256()
+        .string("ref_genome", |r| &r.ref_genome)
+        .string("result_type", |r| &r.ergebnis)
+        .string("gene", |r| &r.gene)
+        .string("hgnc_id", |r| &r.hgnc_id)
+        .string("ensembl_id", |r| &r.ensembl_id)
+        .string("chromosome", |r| &r.chromosome)
+        .integer("start", |r| integer(&r.start))
+        .integer("end", |r| integer(&r.end))
+        .string("ref_allele", |r| &r.ref_allele)
+        .string("alt_allele", |r| &r.alt_allele)
+        .string("genomic_position", |r| &r.genomic_position)
+        .string("cdna", |r| &r.cdna)
+        .string("protein", |r| &r.protein)
+        .integer("read_depth", |r| r.read_depth.map(i64::from))
+        .float("allelic_frequency", |r| r.allelic_frequency.map(f64::from))
+        .string("dbsnp", |r| &r.dbsnp)
+        .string("classification", |r| &r.classification)
+        .write(&path.join("simple_variants.parquet"))
+}
+
+fn write_copy_numbers(path: &Path, cases: &[Case]) -> Result<(), Box<dyn std::error::Error>> {
+    Columns::new(cases, |records| &records.copy_numbers)
+        .string("h_number", |r: &CopyNumberRecord| &r.h_nummer)
+        .order_date()
+        .input_sha256()
+        .string("ref_genome", |r| &r.ref_genome)
+        .string("result_type", |r| &r.ergebnis)
+        .string("cnv_type", |r| &r.cnv_type)
+        .string("gene", |r| &r.gene)
+        .string("hgnc_id", |r| &r.hgnc_id)
+        .string("ensembl_id", |r| &r.ensembl_id)
+        .string("chromosome", |r| &r.chromosome)
+        .float("total_copy_number", |r| r.total_copy_number.map(f64::from))
+        .string("classification", |r| &r.classification)
+        .write(&path.join("copy_number_variants.parquet"))
+}
+
+fn write_fusions(path: &Path, cases: &[Case]) -> Result<(), Box<dyn std::error::Error>> {
+    Columns::new(cases, |records| &records.fusions)
+        .string("h_number", |r: &FusionRecord| &r.h_nummer)
+        .order_date()
+        .input_sha256()
+        .string("ref_genome", |r| &r.ref_genome)
+        .string("result_type", |r| &r.ergebnis)
+        .string("gene_5", |r| &r.gene)
+        .string("hgnc_id_5", |r| &r.hgnc_id_5)
+        .string("transcript_id_5", |r| &r.transcript_id_5)
+        .string("exon_id_5", |r| &r.exon_id_5)
+        .integer("transcript_position_5", |r| {
+            integer(&r.transcript_position_5)
+        })
+        .string("strand_5", |r| &r.strand_5)
+        .string("gene_3", |r| &r.fusion_gene)
+        .string("hgnc_id_3", |r| &r.hgnc_id_3)
+        .string("transcript_id_3", |r| &r.transcript_id_3)
+        .string("exon_id_3", |r| &r.exon_id_3)
+        .integer("transcript_position_3", |r| {
+            integer(&r.transcript_position_3)
+        })
+        .string("strand_3", |r| &r.strand_3)
+        .integer("number_reported_reads", |r| {
+            integer(&r.number_reported_reads)
+        })
+        .write(&path.join("fusions.parquet"))
+}
+
+fn write_biomarkers(path: &Path, cases: &[Case]) -> Result<(), Box<dyn std::error::Error>> {
+    Columns::new(cases, |records| &records.biomarkers)
+        .string("h_number", |r: &BiomarkerRecord| &r.h_nummer)
+        .order_date()
+        .input_sha256()
+        .string("ref_genome", |r| &r.ref_genome)
+        .string("result_type", |r| &r.ergebnis)
+        .float("hrd", |r| r.hrd.map(f64::from))
+        .float("msi", |r| r.msi.map(f64::from))
+        .float("tmb", |r| r.tmb.map(f64::from))
+        .write(&path.join("biomarkers.parquet"))
+}
+
+/// Writes all records of the given cases into one Parquet file for each record type.
+///
+/// Files are only written if there is at least one record of this type, files of record types
+/// without records are removed from the directory. Decimal numbers are written unrounded.
+///
+/// # Errors
+///
+/// Returns an error if the directory or one of the files cannot be written.
+pub(crate) fn write_parquet_files(
+    path: &Path,
+    cases: &[Case],
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(path)?;
+    write_simple_variants(path, cases)?;
+    write_copy_numbers(path, cases)?;
+    write_fusions(path, cases)?;
+    write_biomarkers(path, cases)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::columnar::{Case, date, write_parquet_files};
+    use crate::export_record::{BiomarkerRecord, Records};
+    use crate::mhguide::RefGenomeVersion;
+    use crate::provenance::{Provenance, sha256};
+    use arrow_array::{Float64Array, RecordBatchReader, StringArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_should_parse_dates() {
+        assert_eq!(date("1970-01-02"), Some(1));
+        assert_eq!(date("2026-02-11"), Some(20_495));
+    }
+
+    #[test]
+    fn test_should_write_typed_parquet_file() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&path).unwrap();
+        std::fs::write(path.join("simple_variants.parquet"), "stale").unwrap();
+        let records = Records {
+            simple_variants: vec![],
+            copy_numbers: vec![],
+            fusions: vec![],
+            biomarkers: vec![BiomarkerRecord::from_tmb(
+                "H/2025/12345",
+                &RefGenomeVersion::Hg19,
+                5.234,
+            )],
+            provenance: Some(Provenance::from_content(b"mhguide")),
+        };
+        let cases = vec![Case {
+            order_date: "2026-02-11",
+            records: &records,
+        }];
+
+        write_parquet_files(&path, &cases).unwrap();
+
+        assert!(!path.join("simple_variants.parquet").exists());
+
+        let file = std::fs::File::open(path.join("biomarkers.parquet")).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let key_value_metadata = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap()
+            .iter()
+            .find(|key_value| key_value.key == "provenance")
+            .and_then(|key_value| key_value.value.clone())
+            .unwrap();
+        let reader = builder.build().unwrap();
+        let schema = reader.schema();
+        let batch = reader.into_iter().next().unwrap().unwrap();
+
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(
+            schema.field_with_name("order_date").unwrap().data_type(),
+            &arrow_schema::DataType::Date32
+        );
+        let tmb = batch
+            .column_by_name("tmb")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert!((tmb.value(0) - f64::from(5.234_f32)).abs() < f64::EPSILON);
+        let input_sha256 = batch
+            .column_by_name("input_sha256")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(input_sha256.value(0), sha256(b"mhguide"));
+        let provenance = serde_json::from_str::<serde_json::Value>(&key_value_metadata).unwrap();
+        assert_eq!(provenance[0]["inputSha256"], sha256(b"mhguide"));
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+use crate::VariantSelection;
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, ExportRecord, FieldValue, FusionRecord, SimpleVariantRecord,
+    ValueFormat,
+};
+use crate::input::InputSource;
+use crate::messages::{Language, translate};
+use serde::Serialize;
+
+/// Compared field of a record type with its headline
+struct Field<T> {
+    headline: &'static str,
+    value: fn(&T) -> FieldValue<'_>,
+}
+
+/// Compared fields of each record type, other fields are part of the key or derived from it
+const SIMPLE_VARIANT_FIELDS: [Field<SimpleVariantRecord>; 2] = [
+    Field {
+        headline: "Allelfrequenz (%)",
+        value: |record| FieldValue::Decimal(record.allelic_frequency),
+    },
+    Field {
+        headline: "Pathogenitätsklasse",
+        value: |record| FieldValue::Text(&record.classification),
+    },
+];
+const COPY_NUMBER_FIELDS: [Field<CopyNumberRecord>; 3] = [
+    Field {
+        headline: "Type",
+        value: |record| FieldValue::Label(&record.cnv_type),
+    },
+    Field {
+        headline: "Total CN",
+        value: |record| FieldValue::Decimal(record.total_copy_number),
+    },
+    Field {
+        headline: "Pathogenitätsklasse",
+        value: |record| FieldValue::Text(&record.classification),
+    },
+];
+const FUSION_FIELDS: [Field<FusionRecord>; 2] = [
+    Field {
+        headline: "Number reported reads",
+        value: |record| FieldValue::Text(&record.number_reported_reads),
+    },
+    Field {
+        headline: "Pathogenitätsklasse",
+        value: |record| FieldValue::Text(&record.classification),
+    },
+];
+const BIOMARKER_FIELDS: [Field<BiomarkerRecord>; 3] = [
+    Field {
+        headline: "HRD - Score/Ergebnis",
+        value: |record| FieldValue::Decimal(record.hrd),
+    },
+    Field {
+        headline: "MSI - Prozentwert",
+        value: |record| FieldValue::Decimal(record.msi),
+    },
+    Field {
+        headline: "TMB - Tumor Mutational Burden",
+        value: |record| FieldValue::Decimal(record.tmb),
+    },
+];
+
+/// Kind of a change between two files
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+    fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "hinzugefügt",
+            ChangeKind::Removed => "entfernt",
+            ChangeKind::Changed => "geändert",
+        }
+    }
+}
+
+/// Added or removed record or changed field of a record
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// Record type like `simple_variants`, see [`ExportRecord::RECORD_TYPE`]
+    pub record_type: &'static str,
+    /// Sheet name of the record type, see [`ExportRecord::SHEET_NAME`]
+    #[serde(skip)]
+    sheet_name: &'static str,
+    /// Gene and HGVS nomenclature of the variant, fusion partners or biomarker
+    pub key: String,
+    /// Headline of the changed field, empty for added or removed records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+}
+
+/// Differences between an older and a newer file of the same case
+#[derive(Debug, Serialize)]
+pub struct Diff {
+    pub old_report_version: Option<u32>,
+    pub new_report_version: Option<u32>,
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Formats the changes as a text table with aligned columns
+    #[must_use]
+    pub fn table(&self, language: Language) -> String {
+        let header = [
+            "Änderung",
+            "Datensatz",
+            "Variante",
+            "Feld",
+            "Alter Wert",
+            "Neuer Wert",
+        ]
+        .map(|headline| translate(language, headline));
+        let rows = self
+            .changes
+            .iter()
+            .map(|change| {
+                [
+                    translate(language, change.kind.label()),
+                    translate(language, change.sheet_name),
+                    change.key.clone(),
+                    change.field.clone().unwrap_or_default(),
+                    change.old.clone().unwrap_or_default(),
+                    change.new.clone().unwrap_or_default(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let widths = (0..header.len())
+            .map(|index| {
+                std::iter::once(&header)
+                    .chain(&rows)
+                    .map(|row| row[index].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| {
+                let line = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(value, width)| format!("{value:<width$}"))
+                    .collect::<Vec<_>>()
+                    .join("  ");
+                line.trim_end().to_string() + "\n"
+            })
+            .collect()
+    }
+}
+
+/// Record with the `DETECTED_VAR_ID` of its variant, if any, and the key used for matching
+struct Entry<T> {
+    id: Option<u32>,
+    key: String,
+    record: T,
+}
+
+impl<T> Entry<T> {
+    fn new(id: Option<u32>, key: String, record: T) -> Self {
+        Entry { id, key, record }
+    }
+}
+
+/// Key of a simple variant: the gene and the cDNA nomenclature, if missing the genomic
+/// position or the protein nomenclature
+fn simple_variant_key(record: &SimpleVariantRecord) -> String {
+    let hgvs = [&record.cdna, &record.genomic_position, &record.protein]
+        .into_iter()
+        .find(|hgvs| !hgvs.is_empty())
+        .map_or("", String::as_str);
+    format!("{} {hgvs}", record.gene).trim().to_string()
+}
+
+fn fusion_key(record: &FusionRecord) -> String {
+    format!("{}::{}", record.gene, record.fusion_gene)
+}
+
+/// Returns the position of the first remaining entry matching the entry
+fn position<T>(
+    entries: &[Option<Entry<T>>],
+    entry: &Entry<T>,
+    matches: impl Fn(&Entry<T>, &Entry<T>) -> bool,
+) -> Option<usize> {
+    entries
+        .iter()
+        .position(|other| other.as_ref().is_some_and(|other| matches(entry, other)))
+}
+
+/// Compares the fields of records matched by `DETECTED_VAR_ID` or, if there is no record with
+/// the same ID, by their key. Records without a match are reported as added or removed.
+fn compare<T: ExportRecord>(
+    old: Vec<Entry<T>>,
+    new: Vec<Entry<T>>,
+    fields: &[Field<T>],
+    value_format: ValueFormat,
+    changes: &mut Vec<Change>,
+) {
+    let change = |kind, key: &str, field: Option<String>, old, new| Change {
+        kind,
+        record_type: T::RECORD_TYPE,
+        sheet_name: T::SHEET_NAME,
+        key: key.to_string(),
+        field,
+        old,
+        new,
+    };
+
+    let mut old = old.into_iter().map(Some).collect::<Vec<_>>();
+    let mut new = new.into_iter().map(Some).collect::<Vec<_>>();
+    let mut pairs = vec![];
+    // Matching IDs first, so records are only matched by key if their ID changed or is missing
+    let same_id = |old: &Entry<T>, new: &Entry<T>| old.id.is_some() && old.id == new.id;
+    let same_key = |old: &Entry<T>, new: &Entry<T>| old.key == new.key;
+    for matches in [&same_id as &dyn Fn(&Entry<T>, &Entry<T>) -> bool, &same_key] {
+        for (index, old) in old.iter_mut().enumerate() {
+            let Some(position) = old
+                .as_ref()
+                .and_then(|entry| position(&new, entry, matches))
+            else {
+                continue;
+            };
+            if let (Some(old), Some(new)) = (old.take(), new[position].take()) {
+                pairs.push((index, old, new));
+            }
+        }
+    }
+    pairs.sort_by_key(|(index, _, _)| *index);
+
+    for (_, old, new) in pairs {
+        if old.key != new.key {
+            changes.push(change(
+                ChangeKind::Changed,
+                &new.key,
+                Some(translate(value_format.language, "Variante")),
+                Some(old.key.clone()),
+                Some(new.key.clone()),
+            ));
+        }
+        for field in fields {
+            let old_value = value_format.format(&(field.value)(&old.record));
+            let new_value = value_format.format(&(field.value)(&new.record));
+            if old_value != new_value {
+                changes.push(change(
+                    ChangeKind::Changed,
+                    &new.key,
+                    Some(translate(value_format.language, field.headline)),
+                    Some(old_value),
+                    Some(new_value),
+                ));
+            }
+        }
+    }
+
+    changes.extend(
+        old.into_iter()
+            .flatten()
+            .map(|old| change(ChangeKind::Removed, &old.key, None, None, None)),
+    );
+    changes.extend(
+        new.into_iter()
+            .flatten()
+            .map(|new| change(ChangeKind::Added, &new.key, None, None, None)),
+    );
+}
+
+/// Entries of all records of the input source
+struct Entries {
+    simple_variants: Vec<Entry<SimpleVariantRecord>>,
+    copy_numbers: Vec<Entry<CopyNumberRecord>>,
+    fusions: Vec<Entry<FusionRecord>>,
+    biomarkers: Vec<Entry<BiomarkerRecord>>,
+}
+
+impl Entries {
+    fn new(source: &dyn InputSource, selection: VariantSelection) -> Self {
+        let variants = crate::variants(source, selection);
+        let records = crate::records(source, selection);
+        // VCF files have no DETECTED_VAR_ID, their variants are matched by key only
+        let id = |id: u32| (id != 0).then_some(id);
+
+        Entries {
+            simple_variants: crate::simple_variants(&variants)
+                .iter()
+                .zip(records.simple_variants)
+                .map(|(variant, record)| {
+                    Entry::new(id(variant.id), simple_variant_key(&record), record)
+                })
+                .collect(),
+            copy_numbers: crate::copy_number_variants(&variants)
+                .iter()
+                .zip(records.copy_numbers)
+                .map(|(variant, record)| {
+                    Entry::new(id(variant.id), format!("{} CNV", record.gene), record)
+                })
+                .collect(),
+            fusions: records
+                .fusions
+                .into_iter()
+                .map(|record| Entry::new(None, fusion_key(&record), record))
+                .collect(),
+            biomarkers: records
+                .biomarkers
+                .into_iter()
+                .map(|record| Entry::new(None, record.ergebnis.clone(), record))
+                .collect(),
+        }
+    }
+}
+
+/// Compares the selected variants, fusions and biomarkers of two input sources of the same case
+pub(crate) fn diff(
+    old: &dyn InputSource,
+    new: &dyn InputSource,
+    selection: VariantSelection,
+    value_format: ValueFormat,
+) -> Result<Diff, Box<dyn std::error::Error>> {
+    let old_h_number = &old.general().patient_identifier.h_number;
+    let new_h_number = &new.general().patient_identifier.h_number;
+    if old_h_number != new_h_number {
+        return Err(format!(
+            "Files belong to different cases, found H-numbers '{old_h_number}' and '{new_h_number}'"
+        )
+        .into());
+    }
+
+    let old_entries = Entries::new(old, selection);
+    let new_entries = Entries::new(new, selection);
+
+    let mut changes = vec![];
+    compare(
+        old_entries.simple_variants,
+        new_entries.simple_variants,
+        &SIMPLE_VARIANT_FIELDS,
+        value_format,
+        &mut changes,
+    );
+    compare(
+        old_entries.copy_numbers,
+        new_entries.copy_numbers,
+        &COPY_NUMBER_FIELDS,
+        value_format,
+        &mut changes,
+    );
+    compare(
+        old_entries.fusions,
+        new_entries.fusions,
+        &FUSION_FIELDS,
+        value_format,
+        &mut changes,
+    );
+    compare(
+        old_entries.biomarkers,
+        new_entries.biomarkers,
+        &BIOMARKER_FIELDS,
+        value_format,
+        &mut changes,
+    );
+
+    Ok(Diff {
+        old_report_version: old.general().report_version,
+        new_report_version: new.general().report_version,
+        changes,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::diff::{
+        BIOMARKER_FIELDS, COPY_NUMBER_FIELDS, ChangeKind, FUSION_FIELDS, Field,
+        SIMPLE_VARIANT_FIELDS, diff,
+    };
+    use crate::export_record::{ExportRecord, ValueFormat};
+    use crate::messages::Language;
+    use crate::mhguide::MhGuide;
+    use crate::{VariantSelection, Vcf, read};
+
+    fn read_mhguide(edit: impl Fn(&mut serde_json::Value)) -> MhGuide {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mut value = serde_json::from_slice::<serde_json::Value>(&content).unwrap();
+        edit(&mut value);
+        read(&serde_json::to_vec(&value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_should_not_find_changes_in_same_file() {
+        let mhguide = read_mhguide(|_| {});
+
+        let diff = diff(
+            &mhguide,
+            &mhguide,
+            VariantSelection::All,
+            ValueFormat::default(),
+        )
+        .unwrap();
+
+        assert!(diff.changes.is_empty());
+    }
+
+    #[test]
+    fn test_should_find_changed_allele_frequency() {
+        let old = read_mhguide(|_| {});
+        let new = read_mhguide(|value| {
+            value["VARIANT_LONG_LIST"][0]["VARIANT_ALLELE_FREQUENCY_IN_TUMOR"] = 12.5.into();
+        });
+
+        let diff = diff(&old, &new, VariantSelection::All, ValueFormat::default()).unwrap();
+
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].kind, ChangeKind::Changed);
+        assert_eq!(diff.changes[0].key, "BRAF c.123C>T");
+        assert_eq!(diff.changes[0].field.as_deref(), Some("Allelfrequenz (%)"));
+        assert_eq!(diff.changes[0].old.as_deref(), Some("42,42"));
+        assert_eq!(diff.changes[0].new.as_deref(), Some("12,50"));
+    }
+
+    #[test]
+    fn test_should_find_added_and_removed_variants() {
+        let old = read_mhguide(|_| {});
+        let new = read_mhguide(|value| {
+            value["VARIANT_LONG_LIST"][0]["DETECTED_VAR_ID"] = 1.into();
+            value["VARIANT_LONG_LIST"][0]["GENE_SYMBOL"] = "KRAS".into();
+        });
+
+        let diff = diff(&old, &new, VariantSelection::All, ValueFormat::default()).unwrap();
+        let table = diff.table(Language::En);
+
+        assert_eq!(diff.changes.len(), 2);
+        assert_eq!(diff.changes[0].kind, ChangeKind::Removed);
+        assert_eq!(diff.changes[1].kind, ChangeKind::Added);
+        assert!(table.starts_with("Change   Record"));
+        assert!(table.contains("removed  Simple Variants  BRAF c.123C>T"));
+    }
+
+    #[test]
+    fn test_should_match_vcf_variants_by_gene_and_hgvs() {
+        let mhguide = read_mhguide(|_| {});
+        let vcf = Vcf::read(&std::fs::read("./testfiles/sv-vep.vcf").unwrap()).unwrap();
+
+        let diff = diff(
+            &mhguide,
+            &vcf,
+            VariantSelection::All,
+            ValueFormat::default(),
+        )
+        .unwrap();
+
+        // Matched BRAF variant without classification in the VCF file, other variants are added
+        assert_eq!(diff.changes[0].kind, ChangeKind::Changed);
+        assert_eq!(diff.changes[0].key, "BRAF c.123C>T");
+        assert_eq!(
+            diff.changes[0].field.as_deref(),
+            Some("Pathogenitätsklasse")
+        );
+        assert_eq!(diff.changes[0].old.as_deref(), Some("Likely benign"));
+        assert_eq!(diff.changes[0].new.as_deref(), Some(""));
+        assert!(
+            !diff
+                .changes
+                .iter()
+                .any(|change| change.kind == ChangeKind::Removed)
+        );
+        let added = diff
+            .changes
+            .iter()
+            .filter(|change| change.kind == ChangeKind::Added)
+            .map(|change| change.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            added,
+            ["BRAF c.1798_1799insAC", "TP53 c.818G>A", "BRAF CNV"]
+        );
+    }
+
+    #[test]
+    fn test_should_match_changed_ids_by_key() {
+        let old = read_mhguide(|_| {});
+        let new = read_mhguide(|value| {
+            value["VARIANT_LONG_LIST"][0]["DETECTED_VAR_ID"] = 1.into();
+        });
+
+        let diff = diff(&old, &new, VariantSelection::All, ValueFormat::default()).unwrap();
+
+        assert!(diff.changes.is_empty());
+    }
+
+    #[test]
+    fn test_should_reject_files_of_different_cases() {
+        let old = read_mhguide(|_| {});
+        let new = read_mhguide(|value| {
+            value["GENERAL"]["PATIENT_IDENTIFIER"] = "H20000-26_PID0123456".into();
+        });
+
+        assert!(diff(&old, &new, VariantSelection::All, ValueFormat::default()).is_err());
+    }
+
+    #[test]
+    fn test_should_compare_fields_with_headlines() {
+        fn assert_headlines<T: ExportRecord>(fields: &[Field<T>]) {
+            let headlines = T::csv_headlines();
+            for field in fields {
+                assert!(headlines.iter().any(|headline| headline == field.headline));
+            }
+        }
+
+        assert_headlines(&SIMPLE_VARIANT_FIELDS);
+        assert_headlines(&COPY_NUMBER_FIELDS);
+        assert_headlines(&FUSION_FIELDS);
+        assert_headlines(&BIOMARKER_FIELDS);
+    }
+}
@@ -0,0 +1,594 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, FusionRecord, Records, SimpleVariantRecord, find_gene,
+};
+use crate::mhguide::{ResultType, one_letter_protein_modification};
+use mv64e_mtb_dto::{
+    Chromosome, Cnv, CnvCodingCode, DnaFusion, NgsReportResults, RnaFusion, RnaFusionStrand, Snv,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Parts of an MTB file read here, the NGS report results are read with the DTO
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MtbFile {
+    patient: Option<PatientReference>,
+    ngs_reports: Vec<NgsReport>,
+}
+
+#[derive(Deserialize)]
+struct PatientReference {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct NgsReport {
+    #[serde(default)]
+    metadata: Vec<NgsReportMetadata>,
+    results: NgsReportResults,
+}
+
+/// Metadata written next to NGS report results, see [`crate::dnpm_metadata_json`]
+#[derive(Default, Deserialize)]
+struct ResultsMetadata {
+    #[serde(default)]
+    metadata: Vec<NgsReportMetadata>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NgsReportMetadata {
+    #[serde(default)]
+    reference_genome: String,
+}
+
+/// Returns the chromosome as written in exported records
+fn chromosome(chromosome: &Chromosome) -> &'static str {
+    match chromosome {
+        Chromosome::Chr1 => "chr1",
+        Chromosome::Chr2 => "chr2",
+        Chromosome::Chr3 => "chr3",
+        Chromosome::Chr4 => "chr4",
+        Chromosome::Chr5 => "chr5",
+        Chromosome::Chr6 => "chr6",
+        Chromosome::Chr7 => "chr7",
+        Chromosome::Chr8 => "chr8",
+        Chromosome::Chr9 => "chr9",
+        Chromosome::Chr10 => "chr10",
+        Chromosome::Chr11 => "chr11",
+        Chromosome::Chr12 => "chr12",
+        Chromosome::Chr13 => "chr13",
+        Chromosome::Chr14 => "chr14",
+        Chromosome::Chr15 => "chr15",
+        Chromosome::Chr16 => "chr16",
+        Chromosome::Chr17 => "chr17",
+        Chromosome::Chr18 => "chr18",
+        Chromosome::Chr19 => "chr19",
+        Chromosome::Chr20 => "chr20",
+        Chromosome::Chr21 => "chr21",
+        Chromosome::Chr22 => "chr22",
+        Chromosome::ChrX => "chrX",
+        Chromosome::ChrY => "chrY",
+        Chromosome::ChrMt => "chrM",
+    }
+}
+
+/// Formats positions written as JSON numbers without decimal places
+fn position(position: f64) -> String {
+    format!("{position:.0}")
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn simple_variant_record(h_number: &str, ref_genome: &str, snv: &Snv) -> SimpleVariantRecord {
+    let ref_allele = if snv.ref_allele == "-" {
+        ""
+    } else {
+        &snv.ref_allele
+    };
+    let alt_allele = if snv.alt_allele == "-" {
+        ""
+    } else {
+        &snv.alt_allele
+    };
+    let start = position(snv.position.start);
+    let end = snv.position.end.map(position).unwrap_or_default();
+
+    let (variant_type, genomic_position) = match (ref_allele.len(), alt_allele.len()) {
+        (1, 1) => ("SNV", format!("g.{start}{ref_allele}>{alt_allele}")),
+        (0, _) => ("ins", format!("g.{start}_{end}ins{alt_allele}")),
+        (_, 0) if end.is_empty() || end == start => ("del", format!("g.{start}del")),
+        (_, 0) => ("del", format!("g.{start}_{end}del")),
+        _ => ("delins", format!("g.{start}_{end}delins{alt_allele}")),
+    };
+    let protein = snv.protein_change.clone().unwrap_or_default();
+
+    SimpleVariantRecord {
+        h_nummer: h_number.to_string(),
+        ref_genome: ref_genome.to_string(),
+        ergebnis: ResultType::SimpleVariant(variant_type.to_string()).to_string(),
+        gene: snv.gene.code.clone(),
+        genomic_position,
+        cdna: snv.dna_change.clone(),
+        protein_orig: one_letter_protein_modification(&protein),
+        protein,
+        chromosome: chromosome(&snv.chromosome).to_string(),
+        ensembl_id: snv.transcript_id.value.clone(),
+        hgnc_id: find_gene(&snv.gene.code).hgnc_id,
+        hgnc_name: snv.gene.display.clone().unwrap_or_default(),
+        start,
+        end,
+        alt_allele: alt_allele.to_string(),
+        ref_allele: ref_allele.to_string(),
+        // A read depth of 0 is written for non present values
+        read_depth: u32::try_from(snv.read_depth)
+            .ok()
+            .filter(|depth| *depth > 0),
+        allelic_frequency: Some(snv.allelic_frequency as f32),
+        dbsnp: String::new(),
+        classification: String::new(),
+        variant_id: None,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn copy_number_record(h_number: &str, ref_genome: &str, cnv: &Cnv) -> CopyNumberRecord {
+    let gene = cnv
+        .reported_affected_genes
+        .as_ref()
+        .and_then(|genes| genes.first());
+    let symbol = gene.map(|gene| gene.code.clone()).unwrap_or_default();
+    let found_gene = find_gene(&symbol);
+
+    CopyNumberRecord {
+        h_nummer: h_number.to_string(),
+        ref_genome: ref_genome.to_string(),
+        ergebnis: ResultType::CopyNumberVariant.to_string(),
+        cnv_type: match cnv.cnv_type.code {
+            CnvCodingCode::Loss => "loss",
+            CnvCodingCode::LowLevelGain => "low level gain",
+            CnvCodingCode::HighLevelGain => "high level gain",
+        }
+        .to_string(),
+        chromosome: chromosome(&cnv.chromosome).to_string(),
+        ensembl_id: found_gene.ensembl_id.unwrap_or_default(),
+        hgnc_id: found_gene.hgnc_id,
+        hgnc_name: gene
+            .and_then(|gene| gene.display.clone())
+            .unwrap_or(found_gene.name),
+        gene: symbol,
+        total_copy_number: cnv.total_copy_number.map(|value| value as f32),
+        classification: String::new(),
+    }
+}
+
+/// Returns the strand as written in the DTO, e.g. `+`
+fn strand(strand: &RnaFusionStrand) -> String {
+    serde_json::to_value(strand)
+        .ok()
+        .and_then(|value| value.as_str().map(ToString::to_string))
+        .unwrap_or_default()
+}
+
+fn rna_fusion_record(h_number: &str, ref_genome: &str, fusion: &RnaFusion) -> FusionRecord {
+    let partner_5 = &fusion.fusion_partner5prime;
+    let partner_3 = &fusion.fusion_partner3prime;
+    let gene_5 = find_gene(&partner_5.gene.code);
+    let gene_3 = find_gene(&partner_3.gene.code);
+
+    FusionRecord {
+        h_nummer: h_number.to_string(),
+        ref_genome: ref_genome.to_string(),
+        ergebnis: ResultType::RnaFusion.to_string(),
+        gene: partner_5.gene.code.clone(),
+        fusion_gene: partner_3.gene.code.clone(),
+        ensembl_id_5: gene_5.ensembl_id.unwrap_or_default(),
+        hgnc_id_5: gene_5.hgnc_id,
+        hgnc_name_5: gene_5.name,
+        transcript_id_5: partner_5.transcript_id.value.clone(),
+        exon_id_5: partner_5.exon_id.clone(),
+        transcript_position_5: position(partner_5.position),
+        strand_5: strand(&partner_5.strand),
+        ensembl_id_3: gene_3.ensembl_id.unwrap_or_default(),
+        hgnc_id_3: gene_3.hgnc_id,
+        hgnc_name_3: gene_3.name,
+        transcript_id_3: partner_3.transcript_id.value.clone(),
+        exon_id_3: partner_3.exon_id.clone(),
+        transcript_position_3: position(partner_3.position),
+        strand_3: strand(&partner_3.strand),
+        number_reported_reads: fusion.reported_num_reads.to_string(),
+        classification: String::new(),
+    }
+}
+
+/// DNA fusions only contain the genes and their genomic positions, no transcripts
+fn dna_fusion_record(h_number: &str, ref_genome: &str, fusion: &DnaFusion) -> FusionRecord {
+    let symbol_5 = &fusion.fusion_partner5prime.gene.code;
+    let symbol_3 = &fusion.fusion_partner3prime.gene.code;
+    let gene_5 = find_gene(symbol_5);
+    let gene_3 = find_gene(symbol_3);
+
+    FusionRecord {
+        h_nummer: h_number.to_string(),
+        ref_genome: ref_genome.to_string(),
+        ergebnis: ResultType::DnaFusion.to_string(),
+        gene: symbol_5.clone(),
+        fusion_gene: symbol_3.clone(),
+        ensembl_id_5: gene_5.ensembl_id.unwrap_or_default(),
+        hgnc_id_5: gene_5.hgnc_id,
+        hgnc_name_5: gene_5.name,
+        ensembl_id_3: gene_3.ensembl_id.unwrap_or_default(),
+        hgnc_id_3: gene_3.hgnc_id,
+        hgnc_name_3: gene_3.name,
+        number_reported_reads: fusion.reported_num_reads.to_string(),
+        ..FusionRecord::default()
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn biomarker_record(
+    h_number: &str,
+    ref_genome: &str,
+    result_type: &ResultType,
+    value: f64,
+) -> BiomarkerRecord {
+    let value = value as f32;
+    BiomarkerRecord {
+        h_nummer: h_number.to_string(),
+        ref_genome: ref_genome.to_string(),
+        ergebnis: result_type.to_string(),
+        hrd: (*result_type == ResultType::HRD).then_some(value),
+        msi: None,
+        tmb: (*result_type == ResultType::TMB).then_some(value),
+    }
+}
+
+/// Returns the reference genome of the report metadata as written in exported records
+fn ref_genome(metadata: &[NgsReportMetadata]) -> String {
+    let reference = metadata
+        .first()
+        .map(|metadata| metadata.reference_genome.to_ascii_uppercase())
+        .unwrap_or_default();
+    if reference.contains("38") {
+        "HG38".to_string()
+    } else if reference.contains("19") || reference.contains("37") {
+        "HG19".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Adds the records of the NGS report results
+fn add_results(
+    records: &mut Records,
+    h_number: &str,
+    ref_genome: &str,
+    results: &NgsReportResults,
+) {
+    for snv in results.simple_variants.iter().flatten() {
+        records
+            .simple_variants
+            .push(simple_variant_record(h_number, ref_genome, snv));
+    }
+    for cnv in results.copy_number_variants.iter().flatten() {
+        records
+            .copy_numbers
+            .push(copy_number_record(h_number, ref_genome, cnv));
+    }
+    for fusion in results.dna_fusions.iter().flatten() {
+        records
+            .fusions
+            .push(dna_fusion_record(h_number, ref_genome, fusion));
+    }
+    for fusion in results.rna_fusions.iter().flatten() {
+        records
+            .fusions
+            .push(rna_fusion_record(h_number, ref_genome, fusion));
+    }
+    if let Some(tmb) = &results.tmb {
+        records.biomarkers.push(biomarker_record(
+            h_number,
+            ref_genome,
+            &ResultType::TMB,
+            tmb.value.value,
+        ));
+    }
+    if let Some(hrd_score) = &results.hrd_score {
+        records.biomarkers.push(biomarker_record(
+            h_number,
+            ref_genome,
+            &ResultType::HRD,
+            hrd_score.value,
+        ));
+    }
+}
+
+/// Reads the records of an MTB file with NGS reports or of NGS report results only, as written
+/// with `--json`. The ID of the patient in an MTB file is used as H-number, otherwise the given
+/// H-number is used, which is not contained in the results. The reference genome of results only
+/// is read from the metadata written next to them.
+pub(crate) fn dnpm_records(
+    content: &[u8],
+    metadata: Option<&[u8]>,
+    h_number: &str,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let value = serde_json::from_slice::<Value>(content)?;
+    let mut records = Records::default();
+
+    if value.get("ngsReports").is_some() {
+        let mtb_file = serde_json::from_value::<MtbFile>(value)?;
+        let h_number = mtb_file
+            .patient
+            .as_ref()
+            .map(|patient| patient.id.as_str())
+            .filter(|id| !id.is_empty())
+            .unwrap_or(h_number);
+        for report in mtb_file.ngs_reports {
+            add_results(
+                &mut records,
+                h_number,
+                &ref_genome(&report.metadata),
+                &report.results,
+            );
+        }
+    } else {
+        let metadata = metadata
+            .map(serde_json::from_slice::<ResultsMetadata>)
+            .transpose()?
+            .unwrap_or_default();
+        let results = serde_json::from_value::<NgsReportResults>(value)?;
+        add_results(
+            &mut records,
+            h_number,
+            &ref_genome(&metadata.metadata),
+            &results,
+        );
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::dnpm::dnpm_records;
+    use crate::{
+        Records, SimpleVariantRecord, VariantSelection, dnpm_json, dnpm_metadata_json, read,
+        records,
+    };
+    use mv64e_mtb_dto::{
+        Chromosome, Coding, DnaFusion, DnaFusionFusionPartner3Prime, DnaFusionFusionPartner5Prime,
+        HrdScore, HrdScoreComponents, NgsReportResults, Reference, RnaFusion,
+        RnaFusionFusionPartner3Prime, RnaFusionFusionPartner5Prime, RnaFusionStrand, Tmb,
+        TmbResult, TranscriptId, TranscriptIdSystem,
+    };
+    use rstest::rstest;
+
+    #[test]
+    fn test_should_read_written_dnpm_json() {
+        let content = std::fs::read("./testfiles/cnv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let exported = records(&mhguide, VariantSelection::All);
+        let metadata = dnpm_metadata_json(&exported).unwrap();
+
+        let imported = dnpm_records(
+            dnpm_json(&exported).unwrap().as_bytes(),
+            Some(metadata.as_bytes()),
+            "H10000-26",
+        )
+        .unwrap();
+
+        assert_eq!(imported.copy_numbers.len(), exported.copy_numbers.len());
+        let (imported, exported) = (&imported.copy_numbers[0], &exported.copy_numbers[0]);
+        assert_eq!(imported.h_nummer, "H10000-26");
+        assert_eq!(imported.gene, exported.gene);
+        assert_eq!(imported.chromosome, exported.chromosome);
+        // No CNV type in the MH Guide file, written as high level gain with a copy number of 12.34
+        assert_eq!(imported.cnv_type, "high level gain");
+        assert_eq!(imported.hgnc_id, exported.hgnc_id);
+        assert_eq!(imported.ref_genome, exported.ref_genome);
+    }
+
+    #[test]
+    fn test_should_read_results_without_metadata() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let exported = records(&mhguide, VariantSelection::All);
+        let content = dnpm_json(&exported).unwrap();
+
+        let imported = dnpm_records(content.as_bytes(), None, "H10000-26").unwrap();
+
+        assert_eq!(
+            imported.simple_variants.len(),
+            exported.simple_variants.len()
+        );
+        assert_eq!(imported.simple_variants[0].h_nummer, "H10000-26");
+        assert_eq!(imported.simple_variants[0].ref_genome, "");
+    }
+
+    #[test]
+    fn test_should_read_simple_variants_of_written_dnpm_json() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let exported = records(&mhguide, VariantSelection::All);
+
+        let imported =
+            dnpm_records(dnpm_json(&exported).unwrap().as_bytes(), None, "H10000-26").unwrap();
+
+        let (imported, exported) = (&imported.simple_variants[0], &exported.simple_variants[0]);
+        assert_eq!(imported.genomic_position, exported.genomic_position);
+        assert_eq!(imported.cdna, exported.cdna);
+        assert_eq!(imported.protein, exported.protein);
+        assert_eq!(imported.protein_orig, exported.protein_orig);
+        assert_eq!(imported.read_depth, exported.read_depth);
+        assert_eq!(imported.allelic_frequency, exported.allelic_frequency);
+    }
+
+    #[test]
+    fn test_should_read_mitochondrial_chromosome() {
+        let exported = Records {
+            simple_variants: vec![SimpleVariantRecord {
+                h_nummer: "H10000-26".to_string(),
+                chromosome: "chrM".to_string(),
+                gene: "MT-ND1".to_string(),
+                ..SimpleVariantRecord::default()
+            }],
+            ..Records::default()
+        };
+
+        let imported =
+            dnpm_records(dnpm_json(&exported).unwrap().as_bytes(), None, "H10000-26").unwrap();
+
+        assert_eq!(imported.simple_variants[0].chromosome, "chrM");
+    }
+
+    #[test]
+    fn test_should_not_write_unknown_chromosome() {
+        let exported = Records {
+            simple_variants: vec![SimpleVariantRecord {
+                h_nummer: "H10000-26".to_string(),
+                gene: "BRAF".to_string(),
+                ..SimpleVariantRecord::default()
+            }],
+            ..Records::default()
+        };
+
+        assert!(dnpm_json(&exported).is_err());
+    }
+
+    fn coding(code: &str) -> Coding {
+        Coding {
+            code: code.to_string(),
+            display: None,
+            system: None,
+            version: None,
+        }
+    }
+
+    fn reference(id: &str) -> Reference {
+        Reference {
+            display: None,
+            id: id.to_string(),
+            reference_type: None,
+            system: None,
+        }
+    }
+
+    fn rna_fusion() -> RnaFusion {
+        RnaFusion {
+            effect: None,
+            external_ids: None,
+            fusion_partner5prime: RnaFusionFusionPartner5Prime {
+                exon_id: "exon13".to_string(),
+                gene: coding("EML4"),
+                position: 1751.0,
+                strand: RnaFusionStrand::Empty,
+                transcript_id: TranscriptId {
+                    system: TranscriptIdSystem::NcbiNlmNihGov,
+                    value: "NM_019063.5".to_string(),
+                },
+            },
+            fusion_partner3prime: RnaFusionFusionPartner3Prime {
+                exon_id: "exon20".to_string(),
+                gene: coding("ALK"),
+                position: 4080.0,
+                strand: RnaFusionStrand::Empty,
+                transcript_id: TranscriptId {
+                    system: TranscriptIdSystem::NcbiNlmNihGov,
+                    value: "NM_004304.5".to_string(),
+                },
+            },
+            id: "rna-fusion".to_string(),
+            localization: None,
+            patient: reference("H20000-26"),
+            reported_num_reads: 123,
+        }
+    }
+
+    fn dna_fusion() -> DnaFusion {
+        DnaFusion {
+            external_ids: None,
+            fusion_partner5prime: DnaFusionFusionPartner5Prime {
+                chromosome: Chromosome::Chr2,
+                gene: coding("EML4"),
+                position: 42_522_656.0,
+            },
+            fusion_partner3prime: DnaFusionFusionPartner3Prime {
+                chromosome: Chromosome::Chr2,
+                gene: coding("ALK"),
+                position: 29_446_394.0,
+            },
+            id: "dna-fusion".to_string(),
+            localization: None,
+            patient: reference("H20000-26"),
+            reported_num_reads: 45,
+        }
+    }
+
+    #[test]
+    fn test_should_read_mtb_file() {
+        let results = NgsReportResults {
+            brcaness: None,
+            copy_number_variants: None,
+            dna_fusions: Some(vec![dna_fusion()]),
+            hrd_score: Some(HrdScore {
+                components: HrdScoreComponents {
+                    loh: 10.0,
+                    lst: 20.0,
+                    tai: 12.0,
+                },
+                id: "hrd".to_string(),
+                interpretation: None,
+                patient: reference("H20000-26"),
+                specimen: reference("specimen"),
+                value: 42.0,
+            }),
+            rna_fusions: Some(vec![rna_fusion()]),
+            rna_seqs: None,
+            simple_variants: None,
+            tmb: Some(Tmb {
+                id: "tmb".to_string(),
+                interpretation: None,
+                patient: reference("H20000-26"),
+                specimen: reference("specimen"),
+                value: TmbResult {
+                    unit: Some("mutations/mB".to_string()),
+                    value: 12.3,
+                },
+            }),
+            tumor_cell_content: None,
+        };
+        let content = serde_json::json!({
+            "patient": { "id": "H20000-26" },
+            "ngsReports": [{
+                "id": "report",
+                "metadata": [{ "referenceGenome": "HG38" }],
+                "results": results
+            }]
+        })
+        .to_string();
+
+        let records = dnpm_records(content.as_bytes(), None, "file-name").unwrap();
+
+        assert_eq!(records.biomarkers.len(), 2);
+        assert_eq!(records.biomarkers[0].h_nummer, "H20000-26");
+        assert_eq!(records.biomarkers[0].ref_genome, "HG38");
+        assert_eq!(records.biomarkers[0].tmb, Some(12.3));
+        assert_eq!(records.biomarkers[1].hrd, Some(42.0));
+        assert_eq!(records.fusions.len(), 2);
+        assert_eq!(records.fusions[0].ergebnis, "DNA Fusion");
+        assert_eq!(records.fusions[0].number_reported_reads, "45");
+        assert_eq!(records.fusions[1].gene, "EML4");
+        assert_eq!(records.fusions[1].fusion_gene, "ALK");
+        assert_eq!(records.fusions[1].transcript_position_3, "4080");
+        assert_eq!(records.fusions[1].transcript_id_5, "NM_019063.5");
+        assert_eq!(records.fusions[1].exon_id_5, "exon13");
+        assert_eq!(records.fusions[1].strand_5, "+");
+        assert_eq!(records.fusions[1].number_reported_reads, "123");
+    }
+
+    #[test]
+    fn test_should_reject_invalid_results() {
+        let content = r#"{ "simpleVariants": [{ "gene": "BRAF" }] }"#;
+        assert!(dnpm_records(content.as_bytes(), None, "H20000-26").is_err());
+    }
+}
@@ -1,53 +1,168 @@
 use crate::hgnc::{Gene, Genes};
+use crate::messages::{Language, translate};
 use crate::mhguide;
-use crate::mhguide::{Fusion, RefGenomeVersion, ResultType, three_letter_protein_modification};
-use serde::{Deserialize, Serialize};
+use crate::mhguide::{
+    Fusion, RefGenomeVersion, ResultType, VariantEffect, three_letter_protein_modification,
+};
+use crate::profile::{ColumnEntry, Profile};
+use crate::provenance::Provenance;
+use serde::{Deserialize, Serialize, Serializer};
 use std::sync::LazyLock;
 
 static GENES: LazyLock<Genes> = LazyLock::new(Genes::new);
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub(crate) struct SimpleVariantRecord {
+/// Finds the gene by its approved or a previous symbol
+pub(crate) fn find_gene(symbol: &str) -> Gene {
+    match GENES.find_by_symbol(symbol) {
+        Some(gene) => gene,
+        None => GENES.find_by_previous_symbol(symbol).unwrap_or_default(),
+    }
+}
+
+/// Value of a record field. Numbers are kept unformatted until the record is written.
+pub enum FieldValue<'a> {
+    Text(&'a str),
+    /// Text like the result type, which is translated into the output language
+    Label(&'a str),
+    Integer(Option<u32>),
+    Decimal(Option<f32>),
+}
+
+/// Format of numbers and language of labels in written output
+#[derive(Clone, Copy, Debug)]
+pub struct ValueFormat {
+    pub decimal_separator: char,
+    pub language: Language,
+}
+
+impl Default for ValueFormat {
+    fn default() -> Self {
+        ValueFormat {
+            decimal_separator: ',',
+            language: Language::De,
+        }
+    }
+}
+
+impl ValueFormat {
+    /// Formats the decimal number rounded to two decimal places
+    #[must_use]
+    pub fn decimal(self, value: f32) -> String {
+        format!("{value:.2}").replace('.', &self.decimal_separator.to_string())
+    }
+
+    /// Formats the value of a record field
+    #[must_use]
+    pub fn format(self, value: &FieldValue) -> String {
+        match value {
+            FieldValue::Text(value) => (*value).to_string(),
+            FieldValue::Label(value) => translate(self.language, value),
+            FieldValue::Integer(Some(value)) => value.to_string(),
+            FieldValue::Decimal(Some(value)) => self.decimal(*value),
+            FieldValue::Integer(None) | FieldValue::Decimal(None) => String::new(),
+        }
+    }
+}
+
+/// Parses the integer value of a text column, e.g. a position, for typed exports
+pub(crate) fn integer(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok()
+}
+
+/// Converts the decimal number to `f64` rounded to the two decimal places used in all exports
+pub(crate) fn decimal(value: Option<f32>) -> Option<f64> {
+    value.and_then(|value| format!("{value:.2}").parse().ok())
+}
+
+/// Serializes decimal numbers rounded to two decimal places, e.g. for XLSX cells
+#[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+fn serialize_decimal<S: Serializer>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+    decimal(*value).serialize(serializer)
+}
+
+/// Record exported as one row of a table
+pub trait ExportRecord {
+    /// Name of the record type, e.g. used as key in column profiles
+    const RECORD_TYPE: &'static str;
+
+    /// Name of the sheet or table containing records of this type, translated in messages
+    const SHEET_NAME: &'static str;
+
+    fn csv_headlines() -> Vec<String>;
+
+    /// Columns of this record type in the column profile, if the profile contains the record type
+    fn profile_columns(profile: &Profile) -> Option<&[ColumnEntry]>;
+
+    fn h_number(&self) -> &str;
+
+    /// Reference genome of the record, e.g. `HG19`
+    fn ref_genome(&self) -> &str;
+
+    /// Chromosome of the record, if the record type contains one
+    fn chromosome(&self) -> Option<&str> {
+        None
+    }
+
+    /// Key of the record within the case and its record type, which does not depend on the
+    /// position of the record, e.g. the variant ID or the gene
+    fn row_key(&self) -> String;
+
+    /// Values of all fields in the same order as the headlines
+    fn values(&self) -> Vec<FieldValue<'_>>;
+
+    fn formatted_values(&self, value_format: ValueFormat) -> Vec<String> {
+        self.values()
+            .iter()
+            .map(|value| value_format.format(value))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SimpleVariantRecord {
     #[serde(rename = "H-Nummer")]
-    h_nummer: String,
+    pub h_nummer: String,
     #[serde(rename = "Referenz-Genom")]
-    ref_genome: String,
+    pub ref_genome: String,
     #[serde(rename = "Ergebnis")]
-    ergebnis: String,
+    pub ergebnis: String,
     #[serde(rename = "Gen")]
-    pub(crate) gene: String,
+    pub gene: String,
     #[serde(rename = "Genomposition (g.)")]
-    pub(crate) genomic_position: String,
+    pub genomic_position: String,
     #[serde(rename = "cDNA Nomenklatur (c.)")]
-    pub(crate) cdna: String,
+    pub cdna: String,
     #[serde(rename = "Proteinebene (original)")]
-    protein_orig: String,
+    pub protein_orig: String,
     #[serde(rename = "Proteinebene Nomenklatur (p.)")]
-    pub(crate) protein: String,
+    pub protein: String,
     #[serde(rename = "Chromosom")]
-    pub(crate) chromosome: String,
+    pub chromosome: String,
     #[serde(rename = "EnsemblID")]
-    pub(crate) ensembl_id: String,
+    pub ensembl_id: String,
     #[serde(rename = "HGNC ID")]
-    pub(crate) hgnc_id: String,
+    pub hgnc_id: String,
     #[serde(rename = "HGNC Name")]
-    pub(crate) hgnc_name: String,
+    pub hgnc_name: String,
     #[serde(rename = "Start")]
-    pub(crate) start: String,
+    pub start: String,
     #[serde(rename = "Ende")]
-    pub(crate) end: String,
+    pub end: String,
     #[serde(rename = "Alternative Nucleotide")]
-    pub(crate) alt_allele: String,
+    pub alt_allele: String,
     #[serde(rename = "Reference Nucleotide")]
-    pub(crate) ref_allele: String,
+    pub ref_allele: String,
     #[serde(rename = "Read-Depth")]
-    pub(crate) read_depth: String,
-    #[serde(rename = "Allelfrequenz (%)")]
-    pub(crate) allelic_frequency: String,
+    pub read_depth: Option<u32>,
+    #[serde(rename = "Allelfrequenz (%)", serialize_with = "serialize_decimal")]
+    pub allelic_frequency: Option<f32>,
     #[serde(rename = "dbSNP ID")]
-    dbsnp: String,
+    pub dbsnp: String,
     #[serde(rename = "Pathogenitätsklasse")]
-    classification: String,
+    pub classification: String,
+    /// `DETECTED_VAR_ID` of the MH Guide variant, not exported as column
+    #[serde(skip)]
+    pub variant_id: Option<u32>,
 }
 
 impl SimpleVariantRecord {
@@ -78,7 +193,7 @@ impl SimpleVariantRecord {
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```ignore
     /// let variant = mhguide::Variant::new();
     /// let record = SimpleVariantRecord::from_variant("H12345", &RefGenomeVersion::HG38, &variant);
     /// println!("{:?}", record);
@@ -88,12 +203,7 @@ impl SimpleVariantRecord {
         ref_genome_version: &RefGenomeVersion,
         variant: &mhguide::Variant,
     ) -> SimpleVariantRecord {
-        let gene = match GENES.find_by_symbol(&variant.gene_symbol.clone().unwrap_or_default()) {
-            Some(gene) => gene,
-            None => GENES
-                .find_by_previous_symbol(&variant.gene_symbol.clone().unwrap_or_default())
-                .unwrap_or_default(),
-        };
+        let gene = find_gene(variant.gene_symbol.as_deref().unwrap_or_default());
 
         let dna_change = variant.dna_change();
 
@@ -167,20 +277,41 @@ impl SimpleVariantRecord {
             end: dna_change.end,
             ref_allele: dna_change.ref_allele,
             alt_allele: dna_change.alt_allele,
-            read_depth: match variant.total_reads_in_tumor {
-                Some(value) => value.to_string(),
-                None => String::new(),
-            },
-            allelic_frequency: match variant.variant_allele_frequency_in_tumor {
-                Some(value) => format!("{value:.2}").replace('.', ","),
-                None => String::new(),
-            },
+            read_depth: variant.total_reads_in_tumor,
+            allelic_frequency: variant.variant_allele_frequency_in_tumor,
             dbsnp: variant.db_snp.clone().unwrap_or_default(),
             classification: variant.classification_name.clone().unwrap_or_default(),
+            // Variants read from VCF files have no ID
+            variant_id: Some(variant.id).filter(|id| *id > 0),
+        }
+    }
+}
+
+impl ExportRecord for SimpleVariantRecord {
+    const RECORD_TYPE: &'static str = "simple_variants";
+    const SHEET_NAME: &'static str = "Einfache Varianten";
+
+    fn h_number(&self) -> &str {
+        &self.h_nummer
+    }
+
+    fn ref_genome(&self) -> &str {
+        &self.ref_genome
+    }
+
+    fn chromosome(&self) -> Option<&str> {
+        Some(&self.chromosome)
+    }
+
+    fn row_key(&self) -> String {
+        match self.variant_id {
+            Some(variant_id) => variant_id.to_string(),
+            None if self.genomic_position.is_empty() => format!("{}:{}", self.gene, self.cdna),
+            None => self.genomic_position.clone(),
         }
     }
 
-    pub(crate) fn csv_headlines() -> Vec<String> {
+    fn csv_headlines() -> Vec<String> {
         vec![
             "H-Nummer".to_string(),
             "Referenz-Genom".to_string(),
@@ -204,32 +335,61 @@ impl SimpleVariantRecord {
             "Pathogenitätsklasse".to_string(),
         ]
     }
+
+    fn profile_columns(profile: &Profile) -> Option<&[ColumnEntry]> {
+        profile.simple_variants.as_deref()
+    }
+
+    fn values(&self) -> Vec<FieldValue<'_>> {
+        vec![
+            FieldValue::Text(&self.h_nummer),
+            FieldValue::Text(&self.ref_genome),
+            FieldValue::Label(&self.ergebnis),
+            FieldValue::Text(&self.gene),
+            FieldValue::Text(&self.genomic_position),
+            FieldValue::Text(&self.cdna),
+            FieldValue::Text(&self.protein_orig),
+            FieldValue::Text(&self.protein),
+            FieldValue::Text(&self.chromosome),
+            FieldValue::Text(&self.ensembl_id),
+            FieldValue::Text(&self.hgnc_id),
+            FieldValue::Text(&self.hgnc_name),
+            FieldValue::Text(&self.start),
+            FieldValue::Text(&self.end),
+            FieldValue::Text(&self.alt_allele),
+            FieldValue::Text(&self.ref_allele),
+            FieldValue::Integer(self.read_depth),
+            FieldValue::Decimal(self.allelic_frequency),
+            FieldValue::Text(&self.dbsnp),
+            FieldValue::Text(&self.classification),
+        ]
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub(crate) struct CopyNumberRecord {
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CopyNumberRecord {
     #[serde(rename = "H-Nummer")]
-    h_nummer: String,
+    pub h_nummer: String,
     #[serde(rename = "Referenz-Genom")]
-    ref_genome: String,
+    pub ref_genome: String,
     #[serde(rename = "Ergebnis")]
-    ergebnis: String,
+    pub ergebnis: String,
     #[serde(rename = "Type")]
-    pub(crate) cnv_type: String,
+    pub cnv_type: String,
     #[serde(rename = "Gen")]
-    pub(crate) gene: String,
+    pub gene: String,
     #[serde(rename = "Chromosom")]
-    pub(crate) chromosome: String,
+    pub chromosome: String,
     #[serde(rename = "EnsemblID")]
-    pub(crate) ensembl_id: String,
+    pub ensembl_id: String,
     #[serde(rename = "HGNC ID")]
-    pub(crate) hgnc_id: String,
+    pub hgnc_id: String,
     #[serde(rename = "HGNC Name")]
-    pub(crate) hgnc_name: String,
-    #[serde(rename = "Total CN")]
-    pub(crate) total_copy_number: String,
+    pub hgnc_name: String,
+    #[serde(rename = "Total CN", serialize_with = "serialize_decimal")]
+    pub total_copy_number: Option<f32>,
     #[serde(rename = "Pathogenitätsklasse")]
-    pub(crate) classification: String,
+    pub classification: String,
 }
 
 impl CopyNumberRecord {
@@ -259,7 +419,7 @@ impl CopyNumberRecord {
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```ignore
     /// let variant = mhguide::Variant::new();
     /// let record = CopyNumberRecord::from_variant("H12345", &RefGenomeVersion::HG38, &variant);
     /// println!("{:?}", record);
@@ -269,12 +429,7 @@ impl CopyNumberRecord {
         ref_genome_version: &RefGenomeVersion,
         variant: &mhguide::Variant,
     ) -> CopyNumberRecord {
-        let gene = match GENES.find_by_symbol(&variant.gene_symbol.clone().unwrap_or_default()) {
-            Some(gene) => gene,
-            None => GENES
-                .find_by_previous_symbol(&variant.gene_symbol.clone().unwrap_or_default())
-                .unwrap_or_default(),
-        };
+        let gene = find_gene(variant.gene_symbol.as_deref().unwrap_or_default());
 
         CopyNumberRecord {
             h_nummer: h_number.to_string(),
@@ -291,10 +446,7 @@ impl CopyNumberRecord {
             ensembl_id: gene.ensembl_id.unwrap_or_default(),
             hgnc_id: gene.hgnc_id,
             hgnc_name: gene.name,
-            total_copy_number: match variant.copy_number {
-                Some(value) => format!("{value:.2}"),
-                None => String::new(),
-            },
+            total_copy_number: variant.copy_number,
             cnv_type: match variant
                 .protein_modification
                 .clone()
@@ -319,8 +471,29 @@ impl CopyNumberRecord {
             classification: variant.classification_name.clone().unwrap_or_default(),
         }
     }
+}
+
+impl ExportRecord for CopyNumberRecord {
+    const RECORD_TYPE: &'static str = "copy_number_variants";
+    const SHEET_NAME: &'static str = "Copy Number Varianten";
+
+    fn h_number(&self) -> &str {
+        &self.h_nummer
+    }
+
+    fn ref_genome(&self) -> &str {
+        &self.ref_genome
+    }
+
+    fn chromosome(&self) -> Option<&str> {
+        Some(&self.chromosome)
+    }
 
-    pub(crate) fn csv_headlines() -> Vec<String> {
+    fn row_key(&self) -> String {
+        self.gene.clone()
+    }
+
+    fn csv_headlines() -> Vec<String> {
         vec![
             "H-Nummer".to_string(),
             "Referenz-Genom".to_string(),
@@ -335,55 +508,75 @@ impl CopyNumberRecord {
             "Pathogenitätsklasse".to_string(),
         ]
     }
+
+    fn profile_columns(profile: &Profile) -> Option<&[ColumnEntry]> {
+        profile.copy_number_variants.as_deref()
+    }
+
+    fn values(&self) -> Vec<FieldValue<'_>> {
+        vec![
+            FieldValue::Text(&self.h_nummer),
+            FieldValue::Text(&self.ref_genome),
+            FieldValue::Label(&self.ergebnis),
+            FieldValue::Label(&self.cnv_type),
+            FieldValue::Text(&self.gene),
+            FieldValue::Text(&self.chromosome),
+            FieldValue::Text(&self.ensembl_id),
+            FieldValue::Text(&self.hgnc_id),
+            FieldValue::Text(&self.hgnc_name),
+            FieldValue::Decimal(self.total_copy_number),
+            FieldValue::Text(&self.classification),
+        ]
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub(crate) struct FusionRecord {
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FusionRecord {
     #[serde(rename = "H-Nummer")]
-    h_nummer: String,
+    pub h_nummer: String,
     #[serde(rename = "Referenz-Genom")]
-    ref_genome: String,
+    pub ref_genome: String,
     #[serde(rename = "Ergebnis")]
-    ergebnis: String,
+    pub ergebnis: String,
     #[serde(rename = "Gen")]
-    gene: String,
+    pub gene: String,
     #[serde(rename = "Fusioniertes Gen")]
-    fusion_gene: String,
+    pub fusion_gene: String,
 
     #[serde(rename = "5' Partner EnsemblID")]
-    ensembl_id_5: String,
+    pub ensembl_id_5: String,
     #[serde(rename = "5' Partner HGNC ID")]
-    hgnc_id_5: String,
+    pub hgnc_id_5: String,
     #[serde(rename = "5' Partner HGNC Name")]
-    hgnc_name_5: String,
+    pub hgnc_name_5: String,
     #[serde(rename = "5' Partner Transcript ID")]
-    transcript_id_5: String,
+    pub transcript_id_5: String,
     #[serde(rename = "5' Partner Exon ID")]
-    exon_id_5: String,
+    pub exon_id_5: String,
     #[serde(rename = "5' Partner Transcript Position")]
-    transcript_position_5: String,
+    pub transcript_position_5: String,
     #[serde(rename = "5' Partner Strand")]
-    strand_5: String,
+    pub strand_5: String,
 
     #[serde(rename = "3' Partner EnsemblID")]
-    ensembl_id_3: String,
+    pub ensembl_id_3: String,
     #[serde(rename = "3' Partner HGNC ID")]
-    hgnc_id_3: String,
+    pub hgnc_id_3: String,
     #[serde(rename = "3' Partner HGNC Name")]
-    hgnc_name_3: String,
+    pub hgnc_name_3: String,
     #[serde(rename = "3' Partner Transcript ID")]
-    transcript_id_3: String,
+    pub transcript_id_3: String,
     #[serde(rename = "3' Partner Exon ID")]
-    exon_id_3: String,
+    pub exon_id_3: String,
     #[serde(rename = "3' Partner Transcript Position")]
-    transcript_position_3: String,
+    pub transcript_position_3: String,
     #[serde(rename = "3' Partner Strand")]
-    strand_3: String,
+    pub strand_3: String,
 
     #[serde(rename = "Number reported reads")]
-    number_reported_reads: String,
+    pub number_reported_reads: String,
     #[serde(rename = "Pathogenitätsklasse")]
-    classification: String,
+    pub classification: String,
 }
 
 impl FusionRecord {
@@ -409,7 +602,7 @@ impl FusionRecord {
     ///
     /// # Examples
     ///
-    /// ```rust
+    /// ```ignore
     /// let variant = mhguide::Variant::new();
     /// let record = FusionRecord::from_variant("H12345", &RefGenomeVersion::HG38, &variant);
     /// println!("{:?}", record);
@@ -419,15 +612,6 @@ impl FusionRecord {
         ref_genome_version: &RefGenomeVersion,
         fusion: &Fusion,
     ) -> FusionRecord {
-        fn gene(symbol_name: &str) -> Gene {
-            match GENES.find_by_symbol(symbol_name) {
-                Some(gene) => gene,
-                None => GENES
-                    .find_by_previous_symbol(symbol_name)
-                    .unwrap_or_default(),
-            }
-        }
-
         match fusion {
             Fusion::RnaFusion {
                 partner_3,
@@ -446,16 +630,16 @@ impl FusionRecord {
                 ergebnis: fusion.to_string(),
                 gene: partner_5.clone(),
                 fusion_gene: partner_3.clone(),
-                ensembl_id_5: gene(partner_5).ensembl_id.unwrap_or_default(),
-                hgnc_id_5: gene(partner_5).hgnc_id,
-                hgnc_name_5: gene(partner_5).name,
+                ensembl_id_5: find_gene(partner_5).ensembl_id.unwrap_or_default(),
+                hgnc_id_5: find_gene(partner_5).hgnc_id,
+                hgnc_name_5: find_gene(partner_5).name,
                 transcript_id_5: transcript_id_5.to_string(),
                 exon_id_5: exon_id_5.to_string(),
                 transcript_position_5: transcript_position_5.to_string(),
                 strand_5: strand.to_string(),
-                ensembl_id_3: gene(partner_3).ensembl_id.unwrap_or_default(),
-                hgnc_id_3: gene(partner_3).hgnc_id,
-                hgnc_name_3: gene(partner_3).name,
+                ensembl_id_3: find_gene(partner_3).ensembl_id.unwrap_or_default(),
+                hgnc_id_3: find_gene(partner_3).hgnc_id,
+                hgnc_name_3: find_gene(partner_3).name,
                 transcript_id_3: transcript_id_3.to_string(),
                 exon_id_3: exon_id_3.to_string(),
                 transcript_position_3: transcript_position_3.to_string(),
@@ -465,8 +649,25 @@ impl FusionRecord {
             },
         }
     }
+}
+
+impl ExportRecord for FusionRecord {
+    const RECORD_TYPE: &'static str = "fusions";
+    const SHEET_NAME: &'static str = "Fusionen";
+
+    fn h_number(&self) -> &str {
+        &self.h_nummer
+    }
+
+    fn ref_genome(&self) -> &str {
+        &self.ref_genome
+    }
 
-    pub(crate) fn csv_headlines() -> Vec<String> {
+    fn row_key(&self) -> String {
+        format!("{}::{}", self.gene, self.fusion_gene)
+    }
+
+    fn csv_headlines() -> Vec<String> {
         vec![
             "H-Nummer".to_string(),
             "Referenz-Genom".to_string(),
@@ -491,22 +692,55 @@ impl FusionRecord {
             "Pathogenitätsklasse".to_string(),
         ]
     }
+
+    fn profile_columns(profile: &Profile) -> Option<&[ColumnEntry]> {
+        profile.fusions.as_deref()
+    }
+
+    fn values(&self) -> Vec<FieldValue<'_>> {
+        vec![
+            FieldValue::Text(&self.h_nummer),
+            FieldValue::Text(&self.ref_genome),
+            FieldValue::Label(&self.ergebnis),
+            FieldValue::Text(&self.gene),
+            FieldValue::Text(&self.fusion_gene),
+            FieldValue::Text(&self.ensembl_id_5),
+            FieldValue::Text(&self.hgnc_id_5),
+            FieldValue::Text(&self.hgnc_name_5),
+            FieldValue::Text(&self.transcript_id_5),
+            FieldValue::Text(&self.exon_id_5),
+            FieldValue::Text(&self.transcript_position_5),
+            FieldValue::Text(&self.strand_5),
+            FieldValue::Text(&self.ensembl_id_3),
+            FieldValue::Text(&self.hgnc_id_3),
+            FieldValue::Text(&self.hgnc_name_3),
+            FieldValue::Text(&self.transcript_id_3),
+            FieldValue::Text(&self.exon_id_3),
+            FieldValue::Text(&self.transcript_position_3),
+            FieldValue::Text(&self.strand_3),
+            FieldValue::Text(&self.number_reported_reads),
+            FieldValue::Text(&self.classification),
+        ]
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct BiomarkerRecord {
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BiomarkerRecord {
     #[serde(rename = "H-Nummer")]
-    h_nummer: String,
+    pub h_nummer: String,
     #[serde(rename = "Referenz-Genom")]
-    ref_genome: String,
+    pub ref_genome: String,
     #[serde(rename = "Ergebnis")]
-    ergebnis: String,
-    #[serde(rename = "HRD - Score/Ergebnis")]
-    hrd: String,
-    #[serde(rename = "MSI - Prozentwert")]
-    msi: String,
-    #[serde(rename = "TMB - Tumor Mutational Burden")]
-    tmb: String,
+    pub ergebnis: String,
+    #[serde(rename = "HRD - Score/Ergebnis", serialize_with = "serialize_decimal")]
+    pub hrd: Option<f32>,
+    #[serde(rename = "MSI - Prozentwert", serialize_with = "serialize_decimal")]
+    pub msi: Option<f32>,
+    #[serde(
+        rename = "TMB - Tumor Mutational Burden",
+        serialize_with = "serialize_decimal"
+    )]
+    pub tmb: Option<f32>,
 }
 
 impl BiomarkerRecord {
@@ -519,9 +753,9 @@ impl BiomarkerRecord {
             h_nummer: h_number.to_string(),
             ref_genome: ref_genome_version.to_string(),
             ergebnis: ResultType::HRD.to_string(),
-            hrd: format!("{value:.2}").replace('.', ","),
-            msi: String::new(),
-            tmb: String::new(),
+            hrd: Some(value),
+            msi: None,
+            tmb: None,
         }
     }
 
@@ -534,9 +768,9 @@ impl BiomarkerRecord {
             h_nummer: h_number.to_string(),
             ref_genome: ref_genome_version.to_string(),
             ergebnis: ResultType::MSI.to_string(),
-            hrd: String::new(),
-            msi: format!("{value:.2}").replace('.', ","),
-            tmb: String::new(),
+            hrd: None,
+            msi: Some(value),
+            tmb: None,
         }
     }
 
@@ -549,13 +783,30 @@ impl BiomarkerRecord {
             h_nummer: h_number.to_string(),
             ref_genome: ref_genome_version.to_string(),
             ergebnis: ResultType::TMB.to_string(),
-            hrd: String::new(),
-            msi: String::new(),
-            tmb: format!("{value:.2}").replace('.', ","),
+            hrd: None,
+            msi: None,
+            tmb: Some(value),
         }
     }
+}
+
+impl ExportRecord for BiomarkerRecord {
+    const RECORD_TYPE: &'static str = "biomarkers";
+    const SHEET_NAME: &'static str = "Biomarker";
+
+    fn h_number(&self) -> &str {
+        &self.h_nummer
+    }
+
+    fn ref_genome(&self) -> &str {
+        &self.ref_genome
+    }
 
-    pub(crate) fn csv_headlines() -> Vec<String> {
+    fn row_key(&self) -> String {
+        self.ergebnis.clone()
+    }
+
+    fn csv_headlines() -> Vec<String> {
         vec![
             "H-Nummer".to_string(),
             "Referenz-Genom".to_string(),
@@ -565,4 +816,434 @@ impl BiomarkerRecord {
             "TMB - Tumor Mutational Burden".to_string(),
         ]
     }
+
+    fn profile_columns(profile: &Profile) -> Option<&[ColumnEntry]> {
+        profile.biomarkers.as_deref()
+    }
+
+    fn values(&self) -> Vec<FieldValue<'_>> {
+        vec![
+            FieldValue::Text(&self.h_nummer),
+            FieldValue::Text(&self.ref_genome),
+            FieldValue::Label(&self.ergebnis),
+            FieldValue::Decimal(self.hrd),
+            FieldValue::Decimal(self.msi),
+            FieldValue::Decimal(self.tmb),
+        ]
+    }
+}
+
+/// All records exported from one MH Guide file
+#[derive(Default)]
+pub struct Records {
+    pub simple_variants: Vec<SimpleVariantRecord>,
+    pub copy_numbers: Vec<CopyNumberRecord>,
+    pub fusions: Vec<FusionRecord>,
+    pub biomarkers: Vec<BiomarkerRecord>,
+    /// Origin of the records written into CSV, XLSX and DNPM JSON files, if known
+    pub provenance: Option<Provenance>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MafRecord {
+    #[serde(rename = "Hugo_Symbol")]
+    hugo_symbol: String,
+    #[serde(rename = "Entrez_Gene_Id")]
+    entrez_gene_id: String,
+    #[serde(rename = "HGNC_ID")]
+    hgnc_id: String,
+    #[serde(rename = "NCBI_Build")]
+    ncbi_build: String,
+    #[serde(rename = "Chromosome")]
+    chromosome: String,
+    #[serde(rename = "Start_Position")]
+    start_position: String,
+    #[serde(rename = "End_Position")]
+    end_position: String,
+    #[serde(rename = "Strand")]
+    strand: String,
+    #[serde(rename = "Variant_Classification")]
+    variant_classification: String,
+    #[serde(rename = "Variant_Type")]
+    variant_type: String,
+    #[serde(rename = "Reference_Allele")]
+    reference_allele: String,
+    #[serde(rename = "Tumor_Seq_Allele1")]
+    tumor_seq_allele_1: String,
+    #[serde(rename = "Tumor_Seq_Allele2")]
+    tumor_seq_allele_2: String,
+    #[serde(rename = "dbSNP_RS")]
+    dbsnp: String,
+    #[serde(rename = "Tumor_Sample_Barcode")]
+    tumor_sample_barcode: String,
+    #[serde(rename = "HGVSc")]
+    hgvsc: String,
+    #[serde(rename = "HGVSp")]
+    hgvsp: String,
+    #[serde(rename = "HGVSp_Short")]
+    hgvsp_short: String,
+    #[serde(rename = "t_depth")]
+    t_depth: String,
+    #[serde(rename = "t_alt_count")]
+    t_alt_count: String,
+}
+
+impl MafRecord {
+    /// Constructs a `MafRecord` (Mutation Annotation Format) from the input variant details.
+    ///
+    /// The record is derived from the `SimpleVariantRecord` of the variant, the reference genome
+    /// version and the `VARIANT_EFFECT` of the variant.
+    ///
+    /// # Arguments
+    ///
+    /// * `h_number` - A reference to the H-number string, used as `Tumor_Sample_Barcode`.
+    /// * `ref_genome_version` - The reference genome version, used as `NCBI_Build`.
+    /// * `variant` - A reference to a `mhguide::Variant` object which provides variant information.
+    ///
+    /// # Behavior
+    ///
+    /// * Inserted alleles of insertions and duplications and deleted alleles of deletions are
+    ///   exported as `-`, as common in MAF files. Duplications are exported as insertions.
+    /// * The `End_Position` of single nucleotide variants equals the `Start_Position`.
+    /// * `t_alt_count` is calculated from the read depth and the allelic frequency in tumor.
+    /// * The Entrez gene ID is taken from the gene list, if available, or else left empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the alleles are not contained in the DNA change, e.g. for
+    /// deletions like `c.123_124del` without the deleted bases.
+    pub(crate) fn from_variant(
+        h_number: &str,
+        ref_genome_version: &RefGenomeVersion,
+        variant: &mhguide::Variant,
+    ) -> Result<MafRecord, String> {
+        let record = SimpleVariantRecord::from_variant(h_number, ref_genome_version, variant);
+        let change = variant.chromosome_modification.clone().unwrap_or_default();
+        let alleles = maf_alleles(&change, &record)
+            .ok_or_else(|| format!("Missing alleles in DNA change '{change}'"))?;
+
+        Ok(MafRecord {
+            entrez_gene_id: find_gene(&record.gene).entrez_id.unwrap_or_default(),
+            hugo_symbol: record.gene,
+            hgnc_id: record.hgnc_id,
+            ncbi_build: ref_genome_version.ncbi_build().to_string(),
+            chromosome: record.chromosome.trim_start_matches("chr").to_string(),
+            start_position: alleles.start,
+            end_position: alleles.end,
+            strand: "+".to_string(),
+            variant_classification: maf_variant_classification(
+                variant.variant_effect.as_ref(),
+                alleles.variant_type,
+                &record.protein_orig,
+            )
+            .to_string(),
+            variant_type: alleles.variant_type.to_string(),
+            tumor_seq_allele_1: alleles.reference.clone(),
+            reference_allele: alleles.reference,
+            tumor_seq_allele_2: alleles.tumor,
+            dbsnp: record.dbsnp,
+            tumor_sample_barcode: record.h_nummer,
+            hgvsc: record.cdna,
+            hgvsp: record.protein,
+            hgvsp_short: record.protein_orig,
+            t_depth: record
+                .read_depth
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            t_alt_count: match (
+                variant.total_reads_in_tumor,
+                variant.variant_allele_frequency_in_tumor,
+            ) {
+                (Some(depth), Some(frequency)) => {
+                    format!("{:.0}", f64::from(depth) * f64::from(frequency) / 100.0)
+                }
+                _ => String::new(),
+            },
+        })
+    }
+
+    /// Chromosome, position and alleles identifying the variant in a sample
+    pub(crate) fn variant_key(&self) -> (&str, &str, &str, &str) {
+        (
+            &self.chromosome,
+            &self.start_position,
+            &self.reference_allele,
+            &self.tumor_seq_allele_2,
+        )
+    }
+}
+
+/// MAF records of the simple variants and the variants that could not be written as MAF record
+#[derive(Debug, Default)]
+pub struct MafRecords {
+    pub records: Vec<MafRecord>,
+    pub skipped: Vec<SkippedVariant>,
+}
+
+/// Simple variant not written as MAF record and the reason
+#[derive(Debug, PartialEq)]
+pub struct SkippedVariant {
+    pub gene: String,
+    pub dna_change: String,
+    pub error: String,
+}
+
+/// Builds the MAF records of the simple variants, variants without alleles are skipped and
+/// returned with the reason
+pub(crate) fn maf_records(
+    h_number: &str,
+    ref_genome_version: &RefGenomeVersion,
+    variants: &[&mhguide::Variant],
+) -> MafRecords {
+    let mut maf_records = MafRecords::default();
+    for variant in variants {
+        match MafRecord::from_variant(h_number, ref_genome_version, variant) {
+            Ok(record) => maf_records.records.push(record),
+            Err(error) => maf_records.skipped.push(SkippedVariant {
+                gene: variant.gene_symbol.clone().unwrap_or_default(),
+                dna_change: variant.chromosome_modification.clone().unwrap_or_default(),
+                error,
+            }),
+        }
+    }
+    maf_records
+}
+
+/// Positions, alleles and `Variant_Type` of a MAF record
+#[derive(Debug, PartialEq)]
+struct MafAlleles {
+    start: String,
+    end: String,
+    reference: String,
+    tumor: String,
+    variant_type: &'static str,
+}
+
+/// Derives the MAF alleles from the DNA change. Insertions and duplications span the two
+/// positions around the inserted bases.
+fn maf_alleles(change: &str, record: &SimpleVariantRecord) -> Option<MafAlleles> {
+    let end = if record.end.is_empty() {
+        record.start.clone()
+    } else {
+        record.end.clone()
+    };
+    if change.contains("delins") {
+        // The deleted bases are not contained in the DNA change
+        return None;
+    }
+    if change.contains("dup") {
+        let position = end.parse::<i64>().ok()?;
+        return (!record.alt_allele.is_empty()).then(|| MafAlleles {
+            start: position.to_string(),
+            end: (position + 1).to_string(),
+            reference: "-".to_string(),
+            tumor: record.alt_allele.clone(),
+            variant_type: "INS",
+        });
+    }
+    if change.contains("ins") {
+        return (!record.alt_allele.is_empty()).then(|| MafAlleles {
+            start: record.start.clone(),
+            end,
+            reference: "-".to_string(),
+            tumor: record.alt_allele.clone(),
+            variant_type: "INS",
+        });
+    }
+    if change.contains("del") {
+        return (!record.ref_allele.is_empty()).then(|| MafAlleles {
+            start: record.start.clone(),
+            end,
+            reference: record.ref_allele.clone(),
+            tumor: "-".to_string(),
+            variant_type: "DEL",
+        });
+    }
+    if record.ref_allele.is_empty() || record.alt_allele.is_empty() {
+        return None;
+    }
+    Some(MafAlleles {
+        start: record.start.clone(),
+        end,
+        variant_type: match record.ref_allele.len() {
+            1 => "SNP",
+            2 => "DNP",
+            3 => "TNP",
+            _ => "ONP",
+        },
+        reference: record.ref_allele.clone(),
+        tumor: record.alt_allele.clone(),
+    })
+}
+
+/// Maps the MH Guide `VARIANT_EFFECT` to a MAF `Variant_Classification`.
+///
+/// If the variant effect is missing or unknown, the classification is derived
+/// from the protein change, if possible.
+fn maf_variant_classification(
+    variant_effect: Option<&VariantEffect>,
+    variant_type: &str,
+    protein_change: &str,
+) -> &'static str {
+    let effect = match variant_effect {
+        Some(VariantEffect::Other(effect)) => effect.to_ascii_lowercase(),
+        _ => String::new(),
+    };
+
+    if effect.contains("frameshift") || effect.contains("frame shift") {
+        return if variant_type == "INS" {
+            "Frame_Shift_Ins"
+        } else {
+            "Frame_Shift_Del"
+        };
+    }
+    if effect.contains("inframe") || effect.contains("in-frame") || effect.contains("in frame") {
+        return if variant_type == "INS" {
+            "In_Frame_Ins"
+        } else {
+            "In_Frame_Del"
+        };
+    }
+
+    match effect.as_str() {
+        e if e.contains("missense") => "Missense_Mutation",
+        e if e.contains("nonsense") || e.contains("stop gain") || e.contains("stop_gain") => {
+            "Nonsense_Mutation"
+        }
+        e if e.contains("stop lost") || e.contains("stop_lost") || e.contains("nonstop") => {
+            "Nonstop_Mutation"
+        }
+        e if e.contains("splice region") || e.contains("splice_region") => "Splice_Region",
+        e if e.contains("splice") => "Splice_Site",
+        e if e.contains("synonymous") || e.contains("silent") => "Silent",
+        e if e.contains("start lost") || e.contains("start_lost") || e.contains("initiator") => {
+            "Translation_Start_Site"
+        }
+        e if e.contains("5'utr") || e.contains("5' utr") || e.contains("5_prime_utr") => "5'UTR",
+        e if e.contains("3'utr") || e.contains("3' utr") || e.contains("3_prime_utr") => "3'UTR",
+        e if e.contains("upstream") => "5'Flank",
+        e if e.contains("downstream") => "3'Flank",
+        e if e.contains("intron") => "Intron",
+        e if e.contains("intergenic") => "IGR",
+        e if e.contains("non-coding") || e.contains("non_coding") => "RNA",
+        _ => match protein_change {
+            p if p.ends_with("fs") && variant_type == "INS" => "Frame_Shift_Ins",
+            p if p.ends_with("fs") => "Frame_Shift_Del",
+            p if p.ends_with('*') => "Nonsense_Mutation",
+            p if p.ends_with('=') => "Silent",
+            p if p.contains("ins") || p.contains("dup") => "In_Frame_Ins",
+            p if p.contains("del") => "In_Frame_Del",
+            p if p.starts_with("p.") => "Missense_Mutation",
+            _ => "Targeted_Region",
+        },
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{
+        MafRecord, SkippedVariant, maf_records, maf_variant_classification,
+    };
+    use crate::mhguide::{RefGenomeVersion, Variant, VariantEffect};
+    use rstest::rstest;
+
+    fn variant(variant_type: &str, change: &str) -> Variant {
+        let json = serde_json::json!({
+            "DETECTED_VAR_ID": 1,
+            "GENE_SYMBOL": "BRAF",
+            "DISPLAY_VARIANT_TYPE": variant_type,
+            "CHROMOSOMAL_MODIFIED_OBJECT": "chr7",
+            "CHROMOSOMAL_MODIFICATION": change,
+        });
+        serde_json::from_str(&json.to_string()).unwrap()
+    }
+
+    fn maf_record(variant_type: &str, change: &str) -> Result<MafRecord, String> {
+        MafRecord::from_variant(
+            "H12345-25",
+            &RefGenomeVersion::Hg38,
+            &variant(variant_type, change),
+        )
+    }
+
+    #[rstest]
+    #[case("SNV", "g.140753336A>T", ("140753336", "140753336", "A", "T", "SNP"))]
+    #[case("del", "g.140753336_140753337delAG", ("140753336", "140753337", "AG", "-", "DEL"))]
+    #[case("del", "g.140753336delA", ("140753336", "140753336", "A", "-", "DEL"))]
+    #[case("ins", "g.140753336_140753337insTT", ("140753336", "140753337", "-", "TT", "INS"))]
+    #[case("SNV", "g.140753336_140753338dupTGA", ("140753338", "140753339", "-", "TGA", "INS"))]
+    #[case("SNV", "g.140753336dupA", ("140753336", "140753337", "-", "A", "INS"))]
+    fn test_should_export_maf_alleles(
+        #[case] variant_type: &str,
+        #[case] change: &str,
+        #[case] expected: (&str, &str, &str, &str, &str),
+    ) {
+        let record = maf_record(variant_type, change).unwrap();
+
+        assert_eq!(
+            (
+                record.start_position.as_str(),
+                record.end_position.as_str(),
+                record.reference_allele.as_str(),
+                record.tumor_seq_allele_2.as_str(),
+                record.variant_type.as_str(),
+            ),
+            expected
+        );
+        assert_eq!(record.tumor_seq_allele_1, record.reference_allele);
+    }
+
+    #[rstest]
+    #[case("del", "g.140753336_140753337del")]
+    #[case("SNV", "g.140753336_140753338dup")]
+    #[case("SNV", "g.140753336_140753337delinsTT")]
+    fn test_should_reject_maf_record_without_alleles(
+        #[case] variant_type: &str,
+        #[case] change: &str,
+    ) {
+        assert!(maf_record(variant_type, change).is_err());
+    }
+
+    #[test]
+    fn test_should_return_skipped_maf_variants() {
+        let snv = variant("SNV", "g.140753336A>T");
+        let deletion = variant("del", "g.140753336_140753337del");
+
+        let maf_records = maf_records("H12345-25", &RefGenomeVersion::Hg38, &[&snv, &deletion]);
+
+        assert_eq!(maf_records.records.len(), 1);
+        assert_eq!(
+            maf_records.skipped,
+            vec![SkippedVariant {
+                gene: "BRAF".to_string(),
+                dna_change: "g.140753336_140753337del".to_string(),
+                error: "Missing alleles in DNA change 'g.140753336_140753337del'".to_string(),
+            }]
+        );
+    }
+
+    #[rstest]
+    #[case(Some("Missense"), "SNP", "p.A123V", "Missense_Mutation")]
+    #[case(Some("Nonsense"), "SNP", "p.R123*", "Nonsense_Mutation")]
+    #[case(Some("Frameshift"), "DEL", "p.K1234fs", "Frame_Shift_Del")]
+    #[case(Some("Frameshift"), "INS", "p.K1234fs", "Frame_Shift_Ins")]
+    #[case(Some("In-frame deletion"), "DEL", "p.K1234del", "In_Frame_Del")]
+    #[case(Some("Splice site"), "SNP", "", "Splice_Site")]
+    #[case(Some("Synonymous"), "SNP", "p.A123=", "Silent")]
+    #[case(None, "SNP", "p.A123V", "Missense_Mutation")]
+    #[case(None, "DEL", "p.K1234fs", "Frame_Shift_Del")]
+    #[case(None, "SNP", "", "Targeted_Region")]
+    fn test_maf_variant_classification(
+        #[case] variant_effect: Option<&str>,
+        #[case] variant_type: &str,
+        #[case] protein_change: &str,
+        #[case] expected: &str,
+    ) {
+        let variant_effect = variant_effect.map(|effect| VariantEffect::Other(effect.to_string()));
+        assert_eq!(
+            maf_variant_classification(variant_effect.as_ref(), variant_type, protein_change),
+            expected
+        );
+    }
 }
@@ -0,0 +1,640 @@
+use crate::export_record::{MafRecord, MafRecords, Records, SkippedVariant, ValueFormat};
+use crate::files::CsvDialect;
+use crate::input::InputSource;
+use crate::profile::Profile;
+use crate::{VariantSelection, cbioportal, columnar, sqlite, template};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Converted input source with the case data and all records passed to an exporter
+pub struct Conversion<'a> {
+    pub source: &'a dyn InputSource,
+    pub selection: VariantSelection,
+    pub records: Records,
+    maf_records: OnceLock<MafRecords>,
+}
+
+impl<'a> Conversion<'a> {
+    /// Builds all records of the selected variants, fusions and biomarkers of the input source
+    #[must_use]
+    pub fn new(source: &'a dyn InputSource, selection: VariantSelection) -> Self {
+        Conversion {
+            source,
+            selection,
+            records: crate::records(source, selection),
+            maf_records: OnceLock::new(),
+        }
+    }
+
+    /// MAF records of the selected simple variants, only built when used by an exporter
+    pub fn maf_records(&self) -> &[MafRecord] {
+        &self
+            .maf_records
+            .get_or_init(|| crate::maf_records(self.source, self.selection))
+            .records
+    }
+
+    /// Simple variants skipped while building the MAF records, empty if no exporter used them
+    pub fn skipped_variants(&self) -> &[SkippedVariant] {
+        self.maf_records
+            .get()
+            .map_or(&[], |maf_records| maf_records.skipped.as_slice())
+    }
+}
+
+/// Target written by an exporter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputTarget {
+    /// One file for each input source, written by [`Exporter::export`]
+    File,
+    /// One directory for all input sources, written by [`Exporter::export_all`]
+    Directory,
+    /// One database file for all input sources, written by [`Exporter::export_all`]
+    Database,
+}
+
+/// Options used by exporters, formats ignore options not applicable to them
+#[derive(Default)]
+pub struct ExportOptions {
+    pub profile: Profile,
+    pub dialect: CsvDialect,
+    /// Format of numbers and language in XLSX and ODS files
+    pub value_format: ValueFormat,
+    /// Write all record types into one CSV table
+    pub flat: bool,
+    /// Create FHIR bundles of type `transaction`
+    pub fhir_transaction: bool,
+}
+
+/// Output format of converted MH Guide files
+pub trait Exporter {
+    /// Name of the format, e.g. used with `--format`
+    fn name(&self) -> &'static str;
+
+    /// Extension of written files, e.g. `csv` or `fhir.json`
+    fn extension(&self) -> &str;
+
+    /// Media type of the written content, e.g. used in HTTP responses
+    fn content_type(&self) -> &'static str;
+
+    /// Target of the written output, one file for each input source by default
+    fn target(&self) -> OutputTarget {
+        OutputTarget::File
+    }
+
+    /// Writes the converted MH Guide file into the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content cannot be created or written or the format is not
+    /// written into a single file.
+    fn export(
+        &self,
+        _conversion: &Conversion,
+        _options: &ExportOptions,
+        _output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Err(format!(
+            "Format '{}' cannot be written into a single file",
+            self.name()
+        )
+        .into())
+    }
+
+    /// Writes all converted MH Guide files into the directory or database at the path.
+    ///
+    /// Returns for each conversion, if it was written. A conversion is not written, e.g. if
+    /// the database already contains a newer report version of the case.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output cannot be written or the format is written into one
+    /// file for each input source.
+    fn export_all(
+        &self,
+        _conversions: &[Conversion],
+        _options: &ExportOptions,
+        _path: &Path,
+    ) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+        Err(format!(
+            "Format '{}' is written into one file for each input file",
+            self.name()
+        )
+        .into())
+    }
+}
+
+struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/csv"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        options: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = if options.flat {
+            crate::flat_csv(&conversion.records, &options.dialect, &options.profile)?
+        } else {
+            crate::csv(&conversion.records, &options.dialect, &options.profile)?
+        };
+        Ok(output.write_all(&content)?)
+    }
+}
+
+struct XlsxExporter;
+
+impl Exporter for XlsxExporter {
+    fn name(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn extension(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        options: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::xlsx(&conversion.records, options.value_format, &options.profile)?;
+        Ok(output.write_all(&content)?)
+    }
+}
+
+struct OdsExporter;
+
+impl Exporter for OdsExporter {
+    fn name(&self) -> &'static str {
+        "ods"
+    }
+
+    fn extension(&self) -> &'static str {
+        "ods"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/vnd.oasis.opendocument.spreadsheet"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        options: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::ods(&conversion.records, options.value_format, &options.profile)?;
+        Ok(output.write_all(&content)?)
+    }
+}
+
+struct DnpmJsonExporter;
+
+impl Exporter for DnpmJsonExporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn extension(&self) -> &'static str {
+        "dnpm.json"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::dnpm_json(&conversion.records)?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+struct MafExporter;
+
+impl Exporter for MafExporter {
+    fn name(&self) -> &'static str {
+        "maf"
+    }
+
+    fn extension(&self) -> &'static str {
+        "maf"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/tab-separated-values"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::maf(
+            conversion.maf_records(),
+            conversion.records.provenance.as_ref(),
+        )?;
+        Ok(output.write_all(&content)?)
+    }
+}
+
+struct HtmlExporter;
+
+impl Exporter for HtmlExporter {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/html; charset=utf-8"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::html(conversion.source, &conversion.records)?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+struct FhirExporter;
+
+impl Exporter for FhirExporter {
+    fn name(&self) -> &'static str {
+        "fhir"
+    }
+
+    fn extension(&self) -> &'static str {
+        "fhir.json"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/fhir+json"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        options: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::fhir_json(
+            &conversion.source.general().patient_identifier,
+            &conversion.records,
+            options.fhir_transaction,
+        )?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+struct PhenopacketExporter;
+
+impl Exporter for PhenopacketExporter {
+    fn name(&self) -> &'static str {
+        "phenopacket"
+    }
+
+    fn extension(&self) -> &'static str {
+        "phenopacket.json"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::phenopacket_json(conversion.source.general(), &conversion.records)?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+struct BwhcExporter;
+
+impl Exporter for BwhcExporter {
+    fn name(&self) -> &'static str {
+        "bwhc"
+    }
+
+    fn extension(&self) -> &'static str {
+        "bwhc.json"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::bwhc_json(conversion.source.general(), &conversion.records)?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+struct ObdsExporter;
+
+impl Exporter for ObdsExporter {
+    fn name(&self) -> &'static str {
+        "obds"
+    }
+
+    fn extension(&self) -> &'static str {
+        "obds.xml"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/xml"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content = crate::obds_xml(
+            &conversion.source.general().patient_identifier,
+            &conversion.records,
+        )?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+pub(crate) struct CbioportalExporter;
+
+impl Exporter for CbioportalExporter {
+    fn name(&self) -> &'static str {
+        "cbioportal"
+    }
+
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/tab-separated-values"
+    }
+
+    fn target(&self) -> OutputTarget {
+        OutputTarget::Directory
+    }
+
+    fn export_all(
+        &self,
+        conversions: &[Conversion],
+        _: &ExportOptions,
+        path: &Path,
+    ) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+        let cases = conversions
+            .iter()
+            .map(|conversion| {
+                let variants = crate::variants(conversion.source, conversion.selection);
+                cbioportal::Case::new(
+                    conversion.source,
+                    conversion.maf_records().to_vec(),
+                    &crate::copy_number_variants(&variants),
+                )
+            })
+            .collect::<Vec<_>>();
+        cbioportal::write_study(path, &cases)?;
+        Ok(vec![true; conversions.len()])
+    }
+}
+
+pub(crate) struct ParquetExporter;
+
+impl Exporter for ParquetExporter {
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn extension(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/vnd.apache.parquet"
+    }
+
+    fn target(&self) -> OutputTarget {
+        OutputTarget::Directory
+    }
+
+    fn export_all(
+        &self,
+        conversions: &[Conversion],
+        _: &ExportOptions,
+        path: &Path,
+    ) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+        let cases = conversions
+            .iter()
+            .map(|conversion| columnar::Case {
+                order_date: &conversion.source.general().order_date,
+                records: &conversion.records,
+            })
+            .collect::<Vec<_>>();
+        columnar::write_parquet_files(path, &cases)?;
+        Ok(vec![true; conversions.len()])
+    }
+}
+
+pub(crate) struct SqliteExporter;
+
+impl Exporter for SqliteExporter {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn extension(&self) -> &'static str {
+        "db"
+    }
+
+    fn content_type(&self) -> &'static str {
+        "application/vnd.sqlite3"
+    }
+
+    fn target(&self) -> OutputTarget {
+        OutputTarget::Database
+    }
+
+    fn export_all(
+        &self,
+        conversions: &[Conversion],
+        _: &ExportOptions,
+        path: &Path,
+    ) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+        let mut connection = sqlite::open_database(path)?;
+        conversions
+            .iter()
+            .map(|conversion| {
+                sqlite::upsert_case(
+                    &mut connection,
+                    conversion.source.general(),
+                    &conversion.records,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Exporter rendering a Tera template, the extension is the template file name without `.tera`
+pub struct TemplateExporter {
+    template: String,
+    extension: String,
+}
+
+impl TemplateExporter {
+    /// Reads the template file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template file cannot be read.
+    pub fn read(template_path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(TemplateExporter {
+            template: fs::read_to_string(template_path)?,
+            extension: template::output_extension(template_path),
+        })
+    }
+}
+
+impl Exporter for TemplateExporter {
+    fn name(&self) -> &'static str {
+        "template"
+    }
+
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn content_type(&self) -> &'static str {
+        "text/plain; charset=utf-8"
+    }
+
+    fn export(
+        &self,
+        conversion: &Conversion,
+        _: &ExportOptions,
+        output: &mut dyn Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let content =
+            crate::render_template(&self.template, conversion.source, &conversion.records)?;
+        Ok(output.write_all(content.as_bytes())?)
+    }
+}
+
+/// All registered output formats. The first format is used by default.
+const EXPORTERS: &[&dyn Exporter] = &[
+    &CsvExporter,
+    &XlsxExporter,
+    &OdsExporter,
+    &DnpmJsonExporter,
+    &MafExporter,
+    &HtmlExporter,
+    &FhirExporter,
+    &PhenopacketExporter,
+    &BwhcExporter,
+    &ObdsExporter,
+    &CbioportalExporter,
+    &ParquetExporter,
+    &SqliteExporter,
+];
+
+/// Returns the registered exporter of the format with the given name
+#[must_use]
+pub fn exporter(name: &str) -> Option<&'static dyn Exporter> {
+    EXPORTERS
+        .iter()
+        .find(|exporter| exporter.name() == name)
+        .copied()
+}
+
+/// Returns all registered exporters, starting with the default format
+#[must_use]
+pub fn exporters() -> &'static [&'static dyn Exporter] {
+    EXPORTERS
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::exporter::{Conversion, ExportOptions, exporter, exporters};
+    use crate::{VariantSelection, read};
+    use rstest::rstest;
+
+    #[test]
+    fn test_should_register_unique_names() {
+        let mut names = exporters()
+            .iter()
+            .map(|exporter| exporter.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], "csv");
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), exporters().len());
+        assert!(exporter("pdf").is_none());
+    }
+
+    #[rstest]
+    #[case("csv", "H-Nummer;")]
+    #[case("json", "{")]
+    #[case("fhir", "{")]
+    #[case("obds", "<")]
+    #[case("html", "<!DOCTYPE html>")]
+    fn test_should_export_format(#[case] name: &str, #[case] expected_start: &str) {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let conversion = Conversion::new(&mhguide, VariantSelection::default());
+
+        let mut output = vec![];
+        exporter(name)
+            .unwrap()
+            .export(&conversion, &ExportOptions::default(), &mut output)
+            .unwrap();
+
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .starts_with(expected_start)
+        );
+    }
+}
@@ -0,0 +1,543 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, FusionRecord, SimpleVariantRecord, decimal,
+};
+use crate::mhguide::PatientIdentifier;
+use crate::provenance::Provenance;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+const GENOMICS_REPORTING: &str = "http://hl7.org/fhir/uv/genomics-reporting/StructureDefinition";
+const LOINC: &str = "http://loinc.org";
+const HGNC: &str = "http://www.genenames.org/geneId";
+const HGVS: &str = "http://varnomen.hgvs.org";
+const SEQUENCE_ONTOLOGY: &str = "http://www.sequenceontology.org";
+const UCUM: &str = "http://unitsofmeasure.org";
+/// Code system for biomarker scores without a quantitative LOINC code
+const BIOMARKER_SCORE: &str =
+    "https://github.com/pcvolkmer/mhguide-to-csv/fhir/CodeSystem/biomarker-score";
+/// Extension with one string extension for each provenance entry
+const PROVENANCE_ENTRIES: &str =
+    "https://github.com/pcvolkmer/mhguide-to-csv/fhir/StructureDefinition/provenance-entries";
+/// Naming system for the SHA-256 of the read file
+const INPUT_SHA256: &str =
+    "https://github.com/pcvolkmer/mhguide-to-csv/fhir/NamingSystem/input-sha256";
+
+/// A FHIR resource together with its `fullUrl` within the bundle.
+struct Entry {
+    full_url: String,
+    resource: Value,
+}
+
+impl Entry {
+    fn new(resource: Value) -> Self {
+        Entry {
+            full_url: format!("urn:uuid:{}", Uuid::new_v4()),
+            resource,
+        }
+    }
+
+    fn reference(&self) -> Value {
+        json!({ "reference": self.full_url })
+    }
+}
+
+fn coding(system: &str, code: &str, display: &str) -> Value {
+    json!({
+        "coding": [{ "system": system, "code": code, "display": display }]
+    })
+}
+
+fn component(code: &str, display: &str, value_key: &str, value: Value) -> Value {
+    let mut result = json!({ "code": coding(LOINC, code, display) });
+    result[value_key] = value;
+    result
+}
+
+fn gene_component(hgnc_id: &str, symbol: &str) -> Value {
+    component(
+        "48018-6",
+        "Gene studied [ID]",
+        "valueCodeableConcept",
+        coding(HGNC, hgnc_id, symbol),
+    )
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.replace(',', ".").parse::<f64>().ok()
+}
+
+fn observation(profile: &str, code: Value, subject: &Value, specimen: &Value) -> Value {
+    let mut result = json!({
+        "resourceType": "Observation",
+        "meta": { "profile": [format!("{GENOMICS_REPORTING}/{profile}")] },
+        "status": "final",
+        "category": [{
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/observation-category",
+                "code": "laboratory"
+            }]
+        }],
+        "subject": subject,
+        "specimen": specimen
+    });
+    result["code"] = code;
+    result
+}
+
+fn variant_observation(subject: &Value, specimen: &Value, components: Vec<Value>) -> Value {
+    let mut resource = observation(
+        "variant",
+        coding(LOINC, "69548-6", "Genetic variant assessment"),
+        subject,
+        specimen,
+    );
+    resource["valueCodeableConcept"] = coding(LOINC, "LA9633-4", "Present");
+    resource["component"] = Value::Array(components);
+    resource
+}
+
+fn simple_variant_components(record: &SimpleVariantRecord) -> Vec<Value> {
+    let mut components = vec![gene_component(&record.hgnc_id, &record.gene)];
+
+    if !record.cdna.is_empty() {
+        components.push(component(
+            "48004-6",
+            "DNA change (c.HGVS)",
+            "valueCodeableConcept",
+            coding(HGVS, &record.cdna, &record.cdna),
+        ));
+    }
+    if !record.protein.is_empty() {
+        components.push(component(
+            "48005-3",
+            "Amino acid change (pHGVS)",
+            "valueCodeableConcept",
+            coding(HGVS, &record.protein, &record.protein),
+        ));
+    }
+    if !record.genomic_position.is_empty() {
+        components.push(component(
+            "81290-9",
+            "Genomic DNA change (gHGVS)",
+            "valueCodeableConcept",
+            coding(HGVS, &record.genomic_position, &record.genomic_position),
+        ));
+    }
+    if !record.dbsnp.is_empty() {
+        components.push(component(
+            "81252-9",
+            "Discrete genetic variant",
+            "valueCodeableConcept",
+            coding(
+                "http://www.ncbi.nlm.nih.gov/projects/SNP",
+                &record.dbsnp,
+                &record.dbsnp,
+            ),
+        ));
+    }
+    if !record.ref_allele.is_empty() {
+        components.push(component(
+            "69547-8",
+            "Genomic ref allele [ID]",
+            "valueString",
+            json!(record.ref_allele),
+        ));
+    }
+    if !record.alt_allele.is_empty() {
+        components.push(component(
+            "69551-0",
+            "Genomic alt allele [ID]",
+            "valueString",
+            json!(record.alt_allele),
+        ));
+    }
+    if let Some(start) = parse_number(&record.start) {
+        let end = parse_number(&record.end).unwrap_or(start);
+        components.push(component(
+            "81254-5",
+            "Genomic allele start-end",
+            "valueRange",
+            json!({ "low": { "value": start }, "high": { "value": end } }),
+        ));
+    }
+    if let Some(frequency) = decimal(record.allelic_frequency) {
+        components.push(component(
+            "81258-6",
+            "Sample variant allelic frequency [NFr]",
+            "valueQuantity",
+            json!({
+                "value": frequency / 100.0,
+                "system": UCUM,
+                "code": "1"
+            }),
+        ));
+    }
+    if let Some(read_depth) = record.read_depth {
+        components.push(component(
+            "82121-5",
+            "Allelic read depth",
+            "valueQuantity",
+            json!({
+                "value": read_depth,
+                "system": UCUM,
+                "code": "1"
+            }),
+        ));
+    }
+
+    components
+}
+
+fn copy_number_components(record: &CopyNumberRecord) -> Vec<Value> {
+    let mut components = vec![gene_component(&record.hgnc_id, &record.gene)];
+
+    if record.cnv_type == "loss" {
+        components.push(component(
+            "48019-4",
+            "DNA change type",
+            "valueCodeableConcept",
+            coding(SEQUENCE_ONTOLOGY, "SO:0001743", "copy_number_loss"),
+        ));
+    } else if record.cnv_type.contains("gain") {
+        components.push(component(
+            "48019-4",
+            "DNA change type",
+            "valueCodeableConcept",
+            coding(SEQUENCE_ONTOLOGY, "SO:0001742", "copy_number_gain"),
+        ));
+    }
+    if let Some(copy_number) = decimal(record.total_copy_number) {
+        components.push(component(
+            "82155-3",
+            "Genomic structural variant copy number",
+            "valueQuantity",
+            json!({
+                "value": copy_number,
+                "system": UCUM,
+                "code": "{copies}"
+            }),
+        ));
+    }
+
+    components
+}
+
+fn fusion_components(record: &FusionRecord) -> Vec<Value> {
+    vec![
+        gene_component(&record.hgnc_id_5, &record.gene),
+        gene_component(&record.hgnc_id_3, &record.fusion_gene),
+        component(
+            "48019-4",
+            "DNA change type",
+            "valueCodeableConcept",
+            coding(SEQUENCE_ONTOLOGY, "SO:0001565", "gene_fusion"),
+        ),
+    ]
+}
+
+fn biomarker_observations(
+    record: &BiomarkerRecord,
+    subject: &Value,
+    specimen: &Value,
+) -> Vec<Value> {
+    let mut result = vec![];
+
+    if let Some(tmb) = decimal(record.tmb) {
+        let mut resource = observation(
+            "tmb",
+            coding(LOINC, "94076-7", "Mutations/Megabase [# Ratio] in Tumor"),
+            subject,
+            specimen,
+        );
+        resource["valueQuantity"] = json!({
+            "value": tmb,
+            "system": UCUM,
+            "code": "1/1000000{Base}",
+            "unit": "mut/Mb"
+        });
+        result.push(resource);
+    }
+
+    // LOINC 81695-9 is a qualitative interpretation, MH Guide only contains the percentage of
+    // unstable microsatellites
+    if let Some(msi) = decimal(record.msi) {
+        let mut resource = observation(
+            "genomic-finding",
+            coding(
+                BIOMARKER_SCORE,
+                "msi-score",
+                "Microsatellite instability (MSI) score",
+            ),
+            subject,
+            specimen,
+        );
+        resource["valueQuantity"] = json!({
+            "value": msi,
+            "system": UCUM,
+            "code": "%"
+        });
+        result.push(resource);
+    }
+
+    if let Some(hrd) = decimal(record.hrd) {
+        let mut resource = observation(
+            "genomic-finding",
+            coding(
+                BIOMARKER_SCORE,
+                "hrd-score",
+                "Homologous recombination deficiency (HRD) score",
+            ),
+            subject,
+            specimen,
+        );
+        resource["valueQuantity"] = json!({ "value": hrd });
+        result.push(resource);
+    }
+
+    result
+}
+
+/// Creates a FHIR R4 Bundle following the HL7 Genomics Reporting IG.
+///
+/// Creates a `Provenance` resource for the targets with the conversion time, this tool as agent,
+/// the read file as source and all provenance entries as extension
+fn provenance_resource(provenance: &Provenance, targets: &[Value]) -> Value {
+    let mut resource = json!({
+        "resourceType": "Provenance",
+        "extension": [{
+            "url": PROVENANCE_ENTRIES,
+            "extension": provenance
+                .entries()
+                .into_iter()
+                .map(|(name, value)| json!({ "url": name, "valueString": value }))
+                .collect::<Vec<_>>()
+        }],
+        "target": targets,
+        "recorded": provenance.created,
+        "agent": [{
+            "who": { "display": format!("mhguide-to-csv {}", provenance.version) }
+        }]
+    });
+    if let Some(input_sha256) = &provenance.input_sha256 {
+        resource["entity"] = json!([{
+            "role": "source",
+            "what": { "identifier": { "system": INPUT_SHA256, "value": input_sha256 } }
+        }]);
+    }
+    resource
+}
+
+/// The bundle contains a `Patient` and a `Specimen` resource created from the patient identifier,
+/// a `DiagnosticReport` and one `Observation` per simple variant, copy number variant, fusion
+/// and biomarker (TMB, MSI and HRD). If the provenance is known, a `Provenance` resource targets
+/// the `DiagnosticReport` and all `Observation` resources.
+///
+/// If `transaction` is set, a transaction bundle will be created with each entry
+/// to be created on the receiving server using `POST`.
+pub(crate) fn bundle(
+    patient_identifier: &PatientIdentifier,
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    fusion_records: &[FusionRecord],
+    biomarker_records: &[BiomarkerRecord],
+    provenance: Option<&Provenance>,
+    transaction: bool,
+) -> Value {
+    let patient = Entry::new(json!({
+        "resourceType": "Patient",
+        "identifier": [{ "value": patient_identifier.pid }]
+    }));
+    let subject = patient.reference();
+
+    let specimen = Entry::new(json!({
+        "resourceType": "Specimen",
+        "identifier": [{ "value": patient_identifier.h_number }],
+        "subject": subject
+    }));
+    let specimen_reference = specimen.reference();
+
+    let mut observations = vec![];
+    observations.extend(simple_variant_records.iter().map(|record| {
+        variant_observation(
+            &subject,
+            &specimen_reference,
+            simple_variant_components(record),
+        )
+    }));
+    observations.extend(copy_number_records.iter().map(|record| {
+        variant_observation(
+            &subject,
+            &specimen_reference,
+            copy_number_components(record),
+        )
+    }));
+    observations.extend(fusion_records.iter().map(|record| {
+        variant_observation(&subject, &specimen_reference, fusion_components(record))
+    }));
+    observations.extend(
+        biomarker_records
+            .iter()
+            .flat_map(|record| biomarker_observations(record, &subject, &specimen_reference)),
+    );
+    let observations = observations.into_iter().map(Entry::new).collect::<Vec<_>>();
+
+    let diagnostic_report = Entry::new(json!({
+        "resourceType": "DiagnosticReport",
+        "meta": { "profile": [format!("{GENOMICS_REPORTING}/genomics-report")] },
+        "status": "final",
+        "category": [{
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/v2-0074",
+                "code": "GE",
+                "display": "Genetics"
+            }]
+        }],
+        "code": coding(LOINC, "51969-4", "Genetic analysis report"),
+        "subject": subject,
+        "specimen": [specimen_reference],
+        "result": observations.iter().map(Entry::reference).collect::<Vec<_>>()
+    }));
+
+    let provenance = provenance.map(|provenance| {
+        let targets = std::iter::once(&diagnostic_report)
+            .chain(&observations)
+            .map(Entry::reference)
+            .collect::<Vec<_>>();
+        Entry::new(provenance_resource(provenance, &targets))
+    });
+
+    let entries = [patient, specimen, diagnostic_report]
+        .into_iter()
+        .chain(observations)
+        .chain(provenance)
+        .map(|entry| {
+            let mut result = json!({
+                "fullUrl": entry.full_url,
+                "resource": entry.resource
+            });
+            if transaction {
+                result["request"] = json!({
+                    "method": "POST",
+                    "url": entry.resource["resourceType"]
+                });
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "resourceType": "Bundle",
+        "type": if transaction { "transaction" } else { "collection" },
+        "entry": entries
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{BiomarkerRecord, SimpleVariantRecord};
+    use crate::fhir::{biomarker_observations, bundle};
+    use crate::mhguide::MhGuide;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(false, "collection")]
+    #[case(true, "transaction")]
+    fn test_should_create_bundle(#[case] transaction: bool, #[case] bundle_type: &str) {
+        static SV_MHGUIDE: &str = include_str!("../testfiles/sv-mhguide.json");
+        let mhguide = serde_json::from_str::<MhGuide>(SV_MHGUIDE).unwrap();
+        let general = &mhguide.general;
+
+        let simple_variant_records = mhguide
+            .all_variants()
+            .iter()
+            .map(|variant| {
+                SimpleVariantRecord::from_variant(
+                    &general.patient_identifier.h_number,
+                    &general.ref_genome_version,
+                    variant,
+                )
+            })
+            .collect::<Vec<_>>();
+        let biomarker_records = vec![BiomarkerRecord::from_tmb(
+            &general.patient_identifier.h_number,
+            &general.ref_genome_version,
+            mhguide.tmb_value().unwrap(),
+        )];
+
+        let actual = bundle(
+            &general.patient_identifier,
+            &simple_variant_records,
+            &[],
+            &[],
+            &biomarker_records,
+            None,
+            transaction,
+        );
+
+        assert_eq!(actual["type"], bundle_type);
+        // Patient, Specimen, DiagnosticReport, one variant and TMB
+        assert_eq!(actual["entry"].as_array().unwrap().len(), 5);
+        assert_eq!(
+            actual["entry"][2]["resource"]["result"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            actual["entry"][3]["resource"]["component"][0]["valueCodeableConcept"]["coding"][0]["code"],
+            "HGNC:1097"
+        );
+        assert_eq!(
+            actual["entry"][4]["resource"]["valueQuantity"]["value"],
+            0.19
+        );
+        assert_eq!(actual["entry"][0].get("request").is_some(), transaction);
+    }
+
+    #[test]
+    fn test_should_add_provenance_resource() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = crate::read(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual =
+            crate::fhir_json(&mhguide.general.patient_identifier, &records, false).unwrap();
+        let actual = serde_json::from_str::<serde_json::Value>(&actual).unwrap();
+
+        let entries = actual["entry"].as_array().unwrap();
+        let provenance = &entries.last().unwrap()["resource"];
+        assert_eq!(provenance["resourceType"], "Provenance");
+        // DiagnosticReport and all Observations
+        assert_eq!(
+            provenance["target"].as_array().unwrap().len(),
+            entries.len() - 3
+        );
+        assert_eq!(
+            provenance["entity"][0]["what"]["identifier"]["value"],
+            crate::provenance::sha256(&content)
+        );
+        assert_eq!(
+            provenance["extension"][0]["extension"][1]["url"],
+            "caseUuid"
+        );
+    }
+
+    #[test]
+    fn test_should_code_msi_and_hrd_scores() {
+        let subject = serde_json::json!({ "reference": "urn:uuid:patient" });
+        let record = BiomarkerRecord {
+            msi: Some(12.5),
+            hrd: Some(42.0),
+            ..BiomarkerRecord::default()
+        };
+
+        let actual = biomarker_observations(&record, &subject, &subject);
+
+        assert_eq!(actual[0]["code"]["coding"][0]["code"], "msi-score");
+        assert_eq!(actual[0]["valueQuantity"]["code"], "%");
+        assert_eq!(actual[1]["code"]["coding"][0]["code"], "hrd-score");
+        assert_eq!(actual[1]["valueQuantity"]["value"], 42.0);
+    }
+}
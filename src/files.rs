@@ -1,16 +1,22 @@
-use crate::export_record::{BiomarkerRecord, CopyNumberRecord, FusionRecord, SimpleVariantRecord};
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, ExportRecord, FieldValue, FusionRecord, MafRecord, Records,
+    SimpleVariantRecord, ValueFormat, decimal,
+};
+use crate::messages::{Language, translate};
 use crate::mhguide::MhGuide;
-use itertools::Itertools;
+use crate::profile::{Profile, formatted_values, selected_values};
+use crate::provenance::{Provenance, sha256};
+use clap::ValueEnum;
 use mv64e_mtb_dto::{
     Chromosome, Cnv, CnvCoding, CnvCodingCode, Coding, NgsReportResults, Position, Reference, Snv,
     TranscriptId, TranscriptIdSystem,
 };
-use rust_xlsxwriter::{Format, Workbook};
-use serde::{Deserialize, Serialize};
+use rust_xlsxwriter::{DocProperties, Format, Workbook};
+use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::fs;
-use std::io::Read;
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
-use std::str::FromStr;
 
 fn map_chromosome(s: &str) -> Result<Chromosome, ()> {
     match s {
@@ -38,30 +44,53 @@ fn map_chromosome(s: &str) -> Result<Chromosome, ()> {
         "chr22" => Ok(Chromosome::Chr22),
         "chrX" => Ok(Chromosome::ChrX),
         "chrY" => Ok(Chromosome::ChrY),
+        "chrM" | "chrMT" => Ok(Chromosome::ChrMt),
         _ => Err(()),
     }
 }
 
-fn read_json_content(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+/// Returns true for the names of all chromosomes used in exports, e.g. `chr1` or `chrM`
+pub(crate) fn is_chromosome(name: &str) -> bool {
+    map_chromosome(name).is_ok()
+}
+
+/// Maximum size of the JSON file extracted from a ZIP archive, which limits the memory used by
+/// small archives with highly compressed content
+const MAX_ZIP_JSON_SIZE: u64 = 256 * 1024 * 1024;
+
+fn zip_json_content<R: Read + Seek>(
+    reader: R,
+    max_size: u64,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    if archive.len() != 1
+        || !Path::new(archive.by_index(0)?.name())
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    {
+        return Err(
+            "ZIP archive does not contain a single JSON file. Only JSON files and ZIP compressed JSON files are supported."
+                .into(),
+        );
+    }
+    let file = archive.by_index(0)?;
+    let mut result = String::new();
+    file.take(max_size + 1).read_to_string(&mut result)?;
+    if result.len() as u64 > max_size {
+        return Err(format!(
+            "JSON file in ZIP archive exceeds the maximum size of {} MiB",
+            max_size / 1024 / 1024
+        )
+        .into());
+    }
+    Ok(result)
+}
+
+/// Returns the JSON content of the read JSON or ZIP file
+fn read_json_content(path: &Path, content: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
     match path.extension() {
-        Some(ext) if ext == "json" => Ok(fs::read_to_string(path)?),
-        Some(ext) if ext == "zip" => {
-            let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
-            if archive.len() != 1
-                || !Path::new(archive.by_index(0)?.name())
-                    .extension()
-                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
-            {
-                return Err(
-                    "ZIP archive does not contain a single JSON file. Only JSON files and ZIP compressed JSON files are supported."
-                        .into(),
-                );
-            }
-            let mut file = archive.by_index(0)?;
-            let mut result = String::new();
-            file.read_to_string(&mut result)?;
-            Ok(result)
-        }
+        Some(ext) if ext == "json" => Ok(String::from_utf8(content.to_vec())?),
+        Some(ext) if ext == "zip" => zip_json_content(Cursor::new(content), MAX_ZIP_JSON_SIZE),
         _ => Err(
             "Unsupported file format. Only JSON files and ZIP compressed JSON files are supported."
                 .into(),
@@ -70,82 +99,442 @@ fn read_json_content(path: &Path) -> Result<String, Box<dyn std::error::Error>>
 }
 
 pub(crate) fn read_file(path: &Path) -> Result<MhGuide, Box<dyn std::error::Error>> {
-    let json = read_json_content(path)?;
-    Ok(serde_json::from_str::<MhGuide>(&json)?)
+    let content = fs::read(path)?;
+    let json = read_json_content(path, &content)?;
+    let mut mhguide = serde_json::from_str::<MhGuide>(&json)?;
+    mhguide.general.input_sha256 = Some(sha256(&content));
+    Ok(mhguide)
+}
+
+/// Reads the content of a JSON file or a ZIP compressed JSON file, e.g. an uploaded file
+pub(crate) fn read_content(content: &[u8]) -> Result<MhGuide, Box<dyn std::error::Error>> {
+    let mut mhguide = if content.starts_with(b"PK\x03\x04") {
+        let json = zip_json_content(Cursor::new(content), MAX_ZIP_JSON_SIZE)?;
+        serde_json::from_str::<MhGuide>(&json)?
+    } else {
+        serde_json::from_slice::<MhGuide>(content)?
+    };
+    mhguide.general.input_sha256 = Some(sha256(content));
+    Ok(mhguide)
+}
+
+/// Quoting of values in CSV files
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum QuoteStyle {
+    /// Nur wenn notwendig
+    Necessary,
+    /// Alle Werte
+    Always,
+    /// Alle Werte außer Zahlen
+    NonNumeric,
+    /// Keine Anführungszeichen
+    Never,
+}
+
+/// Line ending of CSV files
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum LineEnding {
+    /// Unix (LF)
+    Lf,
+    /// Windows (CRLF)
+    Crlf,
+}
+
+/// Character encoding of CSV files
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Encoding {
+    /// UTF-8
+    Utf8,
+    /// UTF-8 mit Byte Order Mark, z.B. für Excel
+    Utf8Bom,
+    /// Windows-1252 (Westeuropäisch)
+    #[value(name = "windows-1252")]
+    Windows1252,
+}
+
+/// Dialect of the written CSV file
+pub struct CsvDialect {
+    pub(crate) delimiter: u8,
+    pub(crate) quote_style: QuoteStyle,
+    pub(crate) value_format: ValueFormat,
+    pub(crate) terminator: csv::Terminator,
+    pub(crate) encoding: Encoding,
+    /// Append the provenance as comment lines, which are not supported by all CSV readers
+    pub(crate) provenance_comments: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: b';',
+            quote_style: QuoteStyle::Necessary,
+            value_format: ValueFormat::default(),
+            terminator: csv::Terminator::Any(b'\n'),
+            encoding: Encoding::Utf8,
+            provenance_comments: false,
+        }
+    }
+}
+
+impl CsvDialect {
+    /// Creates the dialect of CSV files written with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delimiter is not an ASCII character or the same as the decimal
+    /// separator.
+    pub fn new(
+        delimiter: char,
+        quote_style: QuoteStyle,
+        decimal_separator: char,
+        language: Language,
+        line_ending: LineEnding,
+        encoding: Encoding,
+        provenance_comments: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let Ok(delimiter_byte) = u8::try_from(delimiter) else {
+            return Err("Only ASCII characters are supported as CSV delimiter".into());
+        };
+        if decimal_separator == delimiter {
+            return Err("Decimal separator and CSV delimiter must not be the same".into());
+        }
+        Ok(CsvDialect {
+            delimiter: delimiter_byte,
+            quote_style,
+            value_format: ValueFormat {
+                decimal_separator,
+                language,
+            },
+            terminator: match line_ending {
+                LineEnding::Lf => csv::Terminator::Any(b'\n'),
+                LineEnding::Crlf => csv::Terminator::CRLF,
+            },
+            encoding,
+            provenance_comments,
+        })
+    }
+
+    /// Format of numbers and labels, e.g. to write other formats with the same decimal separator
+    #[must_use]
+    pub fn value_format(&self) -> ValueFormat {
+        self.value_format
+    }
 }
 
-pub(crate) fn write_csv_file(
-    path: &Path,
+impl CsvDialect {
+    fn encode(&self, content: Vec<u8>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self.encoding {
+            Encoding::Utf8 => Ok(content),
+            Encoding::Utf8Bom => Ok([b"\xEF\xBB\xBF".as_slice(), &content].concat()),
+            Encoding::Windows1252 => {
+                let content = String::from_utf8(content)?;
+                let (encoded, _, had_errors) = encoding_rs::WINDOWS_1252.encode(&content);
+                if had_errors {
+                    return Err("Content contains characters not available in Windows-1252".into());
+                }
+                Ok(encoded.into_owned())
+            }
+        }
+    }
+
+    /// Decodes the content of a CSV file written in this dialect
+    pub(crate) fn decode(&self, content: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+        match self.encoding {
+            Encoding::Utf8 | Encoding::Utf8Bom => Ok(String::from_utf8(
+                content
+                    .strip_prefix(b"\xEF\xBB\xBF")
+                    .unwrap_or(content)
+                    .to_vec(),
+            )?),
+            Encoding::Windows1252 => Ok(encoding_rs::WINDOWS_1252
+                .decode_without_bom_handling(content)
+                .0
+                .into_owned()),
+        }
+    }
+}
+
+fn write_csv_records<T: ExportRecord>(
+    writer: &mut csv::Writer<Vec<u8>>,
+    dialect: &CsvDialect,
+    records: &[T],
+    profile: &Profile,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let value_format = dialect.value_format;
+    let columns = profile.columns::<T>(value_format.language)?;
+    writer.write_record(dialect.fields(columns.iter().map(|column| column.header.clone())))?;
+    for record in records {
+        writer.write_record(dialect.fields(formatted_values(record, &columns, value_format)))?;
+    }
+    writer.write_record(dialect.fields([String::new()]))?;
+    Ok(())
+}
+
+impl CsvDialect {
+    /// Creates the CSV writer.
+    ///
+    /// Quoting of non-numeric values is done in `fields()`, since the CSV writer only
+    /// recognizes numbers with a decimal point.
+    fn writer(&self) -> csv::Writer<Vec<u8>> {
+        csv::WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .escape(b'"')
+            .delimiter(self.delimiter)
+            .quote_style(match self.quote_style {
+                QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                QuoteStyle::Always => csv::QuoteStyle::Always,
+                QuoteStyle::NonNumeric | QuoteStyle::Never => csv::QuoteStyle::Never,
+            })
+            .terminator(self.terminator)
+            .from_writer(vec![])
+    }
+
+    /// Returns the values of one row, with all non-numeric values quoted for `NonNumeric`
+    fn fields(&self, values: impl IntoIterator<Item = String>) -> Vec<String> {
+        let values = values.into_iter();
+        if self.quote_style != QuoteStyle::NonNumeric {
+            return values.collect();
+        }
+        values
+            .map(|value| {
+                if self.is_number(&value) {
+                    value
+                } else {
+                    format!("\"{}\"", value.replace('"', "\"\""))
+                }
+            })
+            .collect()
+    }
+
+    /// Numbers are written with the decimal separator of this dialect
+    fn is_number(&self, value: &str) -> bool {
+        let decimal_separator = self.value_format.decimal_separator;
+        value
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == decimal_separator)
+            && value.replace(decimal_separator, ".").parse::<f64>().is_ok()
+    }
+
+    /// Reads the content skipping comment lines like the appended provenance
+    pub(crate) fn reader<'a>(&self, content: &'a str) -> csv::Reader<&'a [u8]> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .comment(Some(b'#'))
+            .from_reader(content.as_bytes())
+    }
+}
+
+/// Appends the provenance as comment lines with the line ending of the dialect, if enabled
+fn write_csv_provenance(
+    content: &mut Vec<u8>,
+    dialect: &CsvDialect,
+    provenance: Option<&Provenance>,
+) -> Result<(), std::io::Error> {
+    if !dialect.provenance_comments {
+        return Ok(());
+    }
+    let line_ending = match dialect.terminator {
+        csv::Terminator::CRLF => "\r\n",
+        _ => "\n",
+    };
+    for (name, value) in provenance.map(Provenance::entries).unwrap_or_default() {
+        write!(content, "# {name}: {value}{line_ending}")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn csv_content(
+    dialect: &CsvDialect,
+    profile: &Profile,
     simple_variant_records: &[SimpleVariantRecord],
     copy_number_records: &[CopyNumberRecord],
     fusion_records: &[FusionRecord],
     biomarker_records: &[BiomarkerRecord],
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut writer = csv::WriterBuilder::new()
-        .has_headers(false)
-        .flexible(true)
-        .escape(b'"')
-        .delimiter(b';')
-        .from_writer(vec![]);
+    provenance: Option<&Provenance>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut writer = dialect.writer();
 
-    if !simple_variant_records.is_empty() {
-        let _ = writer.serialize(SimpleVariantRecord::csv_headlines());
-        for record in simple_variant_records {
-            let _ = writer.serialize(record);
+    write_csv_records(&mut writer, dialect, simple_variant_records, profile)?;
+    write_csv_records(&mut writer, dialect, copy_number_records, profile)?;
+    write_csv_records(&mut writer, dialect, fusion_records, profile)?;
+    write_csv_records(&mut writer, dialect, biomarker_records, profile)?;
+
+    let mut content = writer.into_inner()?;
+    write_csv_provenance(&mut content, dialect, provenance)?;
+    dialect.encode(content)
+}
+
+/// One rectangular table with the columns of all record types.
+///
+/// Each row starts with the record type and an ID made of H-number, record type and the
+/// row key of the record, e.g. the variant ID. IDs of records with the same key get a counter
+/// suffix. Columns with the same header are shared by all record types.
+struct FlatTable {
+    headers: Vec<String>,
+    rows: Vec<Vec<(usize, String)>>,
+}
+
+impl FlatTable {
+    fn new() -> Self {
+        FlatTable {
+            headers: vec!["record_type".to_string(), "row_id".to_string()],
+            rows: vec![],
         }
-        let _ = writer.serialize(vec![""]);
     }
 
-    if !copy_number_records.is_empty() {
-        let _ = writer.serialize(CopyNumberRecord::csv_headlines());
-        for record in copy_number_records {
-            let _ = writer.serialize(record);
+    fn add<T: ExportRecord>(
+        &mut self,
+        records: &[T],
+        profile: &Profile,
+        value_format: ValueFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if records.is_empty() {
+            return Ok(());
         }
-        let _ = writer.serialize(vec![""]);
-    }
 
-    if !fusion_records.is_empty() {
-        let _ = writer.serialize(FusionRecord::csv_headlines());
-        for record in copy_number_records {
-            let _ = writer.serialize(record);
+        let columns = profile.columns::<T>(value_format.language)?;
+        let positions = columns
+            .iter()
+            .map(|column| {
+                if let Some(position) = self
+                    .headers
+                    .iter()
+                    .position(|header| *header == column.header)
+                {
+                    position
+                } else {
+                    self.headers.push(column.header.clone());
+                    self.headers.len() - 1
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut row_ids = HashMap::<String, usize>::new();
+        for record in records {
+            let mut row_id = format!(
+                "{}-{}-{}",
+                record.h_number(),
+                T::RECORD_TYPE,
+                record.row_key()
+            );
+            let count = row_ids.entry(row_id.clone()).or_default();
+            *count += 1;
+            if *count > 1 {
+                row_id = format!("{row_id}-{count}");
+            }
+            let mut row = vec![(0, T::RECORD_TYPE.to_string()), (1, row_id)];
+            row.extend(positions.iter().copied().zip(formatted_values(
+                record,
+                &columns,
+                value_format,
+            )));
+            self.rows.push(row);
         }
-        let _ = writer.serialize(vec![""]);
+        Ok(())
     }
 
-    if !biomarker_records.is_empty() {
-        let _ = writer.serialize(BiomarkerRecord::csv_headlines());
-        for record in biomarker_records {
-            let _ = writer.serialize(record);
+    fn write(
+        self,
+        writer: &mut csv::Writer<Vec<u8>>,
+        dialect: &CsvDialect,
+    ) -> Result<(), csv::Error> {
+        writer.write_record(dialect.fields(self.headers.iter().cloned()))?;
+        for row in self.rows {
+            let mut values = vec![String::new(); self.headers.len()];
+            for (position, value) in row {
+                values[position] = value;
+            }
+            writer.write_record(dialect.fields(values))?;
         }
-        let _ = writer.serialize(vec![""]);
+        Ok(())
     }
+}
 
-    let mut output_file = path.to_path_buf();
-    output_file.set_extension("csv");
+pub(crate) fn flat_csv_content(
+    dialect: &CsvDialect,
+    profile: &Profile,
+    records: &Records,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let value_format = dialect.value_format;
+    let mut table = FlatTable::new();
+    table.add(&records.simple_variants, profile, value_format)?;
+    table.add(&records.copy_numbers, profile, value_format)?;
+    table.add(&records.fusions, profile, value_format)?;
+    table.add(&records.biomarkers, profile, value_format)?;
 
-    fs::write(output_file, writer.into_inner()?).map_err(Into::into)
+    let mut writer = dialect.writer();
+    table.write(&mut writer, dialect)?;
+
+    let mut content = writer.into_inner()?;
+    write_csv_provenance(&mut content, dialect, records.provenance.as_ref())?;
+    dialect.encode(content)
 }
 
-pub(crate) fn write_xlsx_file(
-    path: &Path,
+pub(crate) fn xlsx_workbook(
+    profile: &Profile,
+    value_format: ValueFormat,
     simple_variant_records: &[SimpleVariantRecord],
     copy_number_records: &[CopyNumberRecord],
     fusion_records: &[FusionRecord],
     biomarker_records: &[BiomarkerRecord],
-) -> Result<(), Box<dyn std::error::Error>> {
-    fn write_worksheet<T>(
+    provenance: Option<&Provenance>,
+) -> Result<Workbook, Box<dyn std::error::Error>> {
+    fn write_worksheet<T: ExportRecord>(
         workbook: &mut Workbook,
         name: &str,
+        profile: &Profile,
+        value_format: ValueFormat,
         records: &[T],
-    ) -> Result<(), Box<dyn std::error::Error>>
-    where
-        T: Serialize + for<'de> Deserialize<'de>,
-    {
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let columns = profile.columns::<T>(value_format.language)?;
         let worksheet = workbook.add_worksheet();
-        worksheet.set_name(name)?;
+        worksheet.set_name(translate(value_format.language, name))?;
 
-        worksheet.deserialize_headers_with_format::<T>(0, 0, &Format::new().set_bold())?;
-        worksheet.serialize(&records)?;
+        let header_format = Format::new().set_bold();
+        for (column_index, column) in columns.iter().enumerate() {
+            let column_index = u16::try_from(column_index)?;
+            worksheet.write_string_with_format(0, column_index, &column.header, &header_format)?;
+        }
+
+        for (row_index, record) in records.iter().enumerate() {
+            let row_index = u32::try_from(row_index + 1)?;
+            let values = record.values();
+            for (column_index, value) in selected_values(&values, &columns).into_iter().enumerate()
+            {
+                let column_index = u16::try_from(column_index)?;
+                match value {
+                    FieldValue::Text(value) => {
+                        worksheet.write_string(row_index, column_index, *value)?;
+                    }
+                    FieldValue::Label(_) => {
+                        worksheet.write_string(
+                            row_index,
+                            column_index,
+                            value_format.format(value),
+                        )?;
+                    }
+                    FieldValue::Integer(Some(value)) => {
+                        worksheet.write_number(row_index, column_index, *value)?;
+                    }
+                    FieldValue::Decimal(value @ Some(_)) => {
+                        if let Some(value) = decimal(*value) {
+                            worksheet.write_number(row_index, column_index, value)?;
+                        }
+                    }
+                    FieldValue::Integer(None) | FieldValue::Decimal(None) => {}
+                }
+            }
+        }
 
         worksheet.autofit();
 
@@ -154,133 +543,492 @@ pub(crate) fn write_xlsx_file(
 
     let mut workbook = Workbook::new();
 
+    write_worksheet(
+        &mut workbook,
+        "Einfache Varianten",
+        profile,
+        value_format,
+        simple_variant_records,
+    )?;
+    write_worksheet(
+        &mut workbook,
+        "Copy Number Varianten",
+        profile,
+        value_format,
+        copy_number_records,
+    )?;
+    write_worksheet(
+        &mut workbook,
+        "Fusionen",
+        profile,
+        value_format,
+        fusion_records,
+    )?;
+    write_worksheet(
+        &mut workbook,
+        "Biomarker",
+        profile,
+        value_format,
+        biomarker_records,
+    )?;
+
+    if let Some(provenance) = provenance {
+        write_xlsx_provenance(&mut workbook, value_format.language, provenance)?;
+    }
+
+    Ok(workbook)
+}
+
+/// Name of the worksheet containing the provenance, translated with the language of the file
+pub(crate) const METADATA_SHEET: &str = "Metadaten";
+
+/// Writes the provenance into custom document properties and a metadata sheet
+fn write_xlsx_provenance(
+    workbook: &mut Workbook,
+    language: Language,
+    provenance: &Provenance,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let entries = provenance.entries();
+
+    let properties = entries
+        .iter()
+        .fold(DocProperties::new(), |properties, (name, value)| {
+            properties.set_custom_property(*name, value.as_str())
+        });
+    workbook.set_properties(&properties);
+
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(translate(language, METADATA_SHEET))?;
+    let header_format = Format::new().set_bold();
+    worksheet.write_string_with_format(0, 0, translate(language, "Eigenschaft"), &header_format)?;
+    worksheet.write_string_with_format(0, 1, translate(language, "Wert"), &header_format)?;
+    for (row_index, (name, value)) in entries.iter().enumerate() {
+        let row_index = u32::try_from(row_index + 1)?;
+        worksheet.write_string(row_index, 0, *name)?;
+        worksheet.write_string(row_index, 1, value.as_str())?;
+    }
+    worksheet.autofit();
+
+    Ok(())
+}
+
+pub(crate) fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+const ODS_MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const ODS_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.3">
+  <manifest:file-entry manifest:full-path="/" manifest:version="1.3" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+  <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+  <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+/// Writes the document metadata with the provenance as user-defined properties
+fn ods_meta(provenance: Option<&Provenance>) -> Result<String, std::fmt::Error> {
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" office:version="1.3">"#
+    )?;
+    writeln!(xml, "<office:meta>")?;
+    writeln!(
+        xml,
+        "<meta:generator>mhguide-to-csv/{}</meta:generator>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    for (name, value) in provenance.map(Provenance::entries).unwrap_or_default() {
+        writeln!(
+            xml,
+            r#"<meta:user-defined meta:name="{name}">{}</meta:user-defined>"#,
+            escape_xml(&value)
+        )?;
+    }
+    writeln!(xml, "</office:meta>")?;
+    writeln!(xml, "</office:document-meta>")?;
+
+    Ok(xml)
+}
+
+/// Content of one ODS table: name, headlines and the formatted rows of all records
+struct OdsTable {
+    name: String,
+    rows: Vec<Vec<OdsCell>>,
+}
+
+/// Formatted value of one ODS cell, numbers are also written as float value like in XLSX files
+struct OdsCell {
+    text: String,
+    number: Option<f64>,
+}
+
+impl OdsCell {
+    fn text(text: impl Into<String>) -> Self {
+        OdsCell {
+            text: text.into(),
+            number: None,
+        }
+    }
+
+    fn new(value: &FieldValue, value_format: ValueFormat) -> Self {
+        OdsCell {
+            text: value_format.format(value),
+            number: match value {
+                FieldValue::Integer(value) => value.map(f64::from),
+                FieldValue::Decimal(value) => decimal(*value),
+                FieldValue::Text(_) | FieldValue::Label(_) => None,
+            },
+        }
+    }
+}
+
+impl OdsTable {
+    fn new<T: ExportRecord>(
+        name: &str,
+        profile: &Profile,
+        value_format: ValueFormat,
+        records: &[T],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let columns = profile.columns::<T>(value_format.language)?;
+        let mut rows = vec![
+            columns
+                .iter()
+                .map(|column| OdsCell::text(column.header.clone()))
+                .collect(),
+        ];
+        rows.extend(records.iter().map(|record| {
+            let values = record.values();
+            selected_values(&values, &columns)
+                .into_iter()
+                .map(|value| OdsCell::new(value, value_format))
+                .collect()
+        }));
+        Ok(OdsTable {
+            name: translate(value_format.language, name),
+            rows,
+        })
+    }
+
+    /// Table with the name and value of each provenance entry
+    fn provenance(language: Language, provenance: &Provenance) -> Self {
+        let mut rows = vec![vec![
+            OdsCell::text(translate(language, "Eigenschaft")),
+            OdsCell::text(translate(language, "Wert")),
+        ]];
+        rows.extend(
+            provenance
+                .entries()
+                .into_iter()
+                .map(|(name, value)| vec![OdsCell::text(name), OdsCell::text(value)]),
+        );
+        OdsTable {
+            name: translate(language, METADATA_SHEET),
+            rows,
+        }
+    }
+
+    /// Column widths in millimeters based on the longest value in each column
+    fn column_widths(&self) -> Vec<usize> {
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or_default();
+        (0..columns)
+            .map(|column| {
+                let chars = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cell.text.chars().count())
+                    .max()
+                    .unwrap_or_default();
+                4 + 2 * chars
+            })
+            .collect()
+    }
+}
+
+fn ods_content(tables: &[OdsTable]) -> Result<String, std::fmt::Error> {
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.3">"#
+    )?;
+
+    writeln!(xml, "<office:automatic-styles>")?;
+    writeln!(
+        xml,
+        r#"<style:style style:name="header" style:family="table-cell"><style:text-properties fo:font-weight="bold"/></style:style>"#
+    )?;
+    for (table_index, table) in tables.iter().enumerate() {
+        for (column_index, width) in table.column_widths().iter().enumerate() {
+            writeln!(
+                xml,
+                r#"<style:style style:name="t{table_index}c{column_index}" style:family="table-column"><style:table-column-properties style:column-width="{width}mm"/></style:style>"#
+            )?;
+        }
+    }
+    writeln!(xml, "</office:automatic-styles>")?;
+
+    writeln!(xml, "<office:body><office:spreadsheet>")?;
+    for (table_index, table) in tables.iter().enumerate() {
+        writeln!(
+            xml,
+            r#"<table:table table:name="{}">"#,
+            escape_xml(&table.name)
+        )?;
+        for column_index in 0..table.column_widths().len() {
+            writeln!(
+                xml,
+                r#"<table:table-column table:style-name="t{table_index}c{column_index}"/>"#
+            )?;
+        }
+        for (row_index, row) in table.rows.iter().enumerate() {
+            write!(xml, "<table:table-row>")?;
+            for cell in row {
+                if row_index == 0 {
+                    write!(xml, r#"<table:table-cell table:style-name="header" "#)?;
+                } else {
+                    write!(xml, "<table:table-cell ")?;
+                }
+                if let Some(number) = cell.number {
+                    write!(xml, r#"office:value-type="float" office:value="{number}">"#)?;
+                } else {
+                    write!(xml, r#"office:value-type="string">"#)?;
+                }
+                write!(
+                    xml,
+                    "<text:p>{}</text:p></table:table-cell>",
+                    escape_xml(&cell.text)
+                )?;
+            }
+            writeln!(xml, "</table:table-row>")?;
+        }
+        writeln!(xml, "</table:table>")?;
+    }
+    writeln!(xml, "</office:spreadsheet></office:body>")?;
+    writeln!(xml, "</office:document-content>")?;
+
+    Ok(xml)
+}
+
+pub(crate) fn ods_document(
+    profile: &Profile,
+    value_format: ValueFormat,
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    fusion_records: &[FusionRecord],
+    biomarker_records: &[BiomarkerRecord],
+    provenance: Option<&Provenance>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut tables = vec![];
+
     if !simple_variant_records.is_empty() {
-        write_worksheet(&mut workbook, "Einfache Varianten", simple_variant_records)?;
+        tables.push(OdsTable::new(
+            "Einfache Varianten",
+            profile,
+            value_format,
+            simple_variant_records,
+        )?);
     }
 
     if !copy_number_records.is_empty() {
-        write_worksheet(&mut workbook, "Copy Number Varianten", copy_number_records)?;
+        tables.push(OdsTable::new(
+            "Copy Number Varianten",
+            profile,
+            value_format,
+            copy_number_records,
+        )?);
     }
 
     if !fusion_records.is_empty() {
-        write_worksheet(&mut workbook, "Fusionen", fusion_records)?;
+        tables.push(OdsTable::new(
+            "Fusionen",
+            profile,
+            value_format,
+            fusion_records,
+        )?);
     }
 
     if !biomarker_records.is_empty() {
-        write_worksheet(&mut workbook, "Biomarker", biomarker_records)?;
+        tables.push(OdsTable::new(
+            "Biomarker",
+            profile,
+            value_format,
+            biomarker_records,
+        )?);
+    }
+
+    // A spreadsheet document requires at least one table
+    if tables.is_empty() {
+        tables.push(OdsTable::new(
+            "Einfache Varianten",
+            profile,
+            value_format,
+            simple_variant_records,
+        )?);
+    }
+
+    if let Some(provenance) = provenance {
+        tables.push(OdsTable::provenance(value_format.language, provenance));
+    }
+
+    // The mimetype has to be the first and uncompressed entry of the archive
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = zip::write::SimpleFileOptions::default();
+
+    let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+    archive.start_file("mimetype", stored)?;
+    archive.write_all(ODS_MIMETYPE.as_bytes())?;
+    archive.start_file("META-INF/manifest.xml", deflated)?;
+    archive.write_all(ODS_MANIFEST.as_bytes())?;
+    archive.start_file("content.xml", deflated)?;
+    archive.write_all(ods_content(&tables)?.as_bytes())?;
+    archive.start_file("meta.xml", deflated)?;
+    archive.write_all(ods_meta(provenance)?.as_bytes())?;
+
+    Ok(archive.finish()?.into_inner())
+}
+
+/// Writes the MAF records after the version and the provenance as header comment lines like
+/// `#inputSha256 <hash>`
+pub(crate) fn maf_content(
+    maf_records: &[MafRecord],
+    provenance: Option<&Provenance>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut header = b"#version 2.4\n".to_vec();
+    for (name, value) in provenance.map(Provenance::entries).unwrap_or_default() {
+        writeln!(header, "#{name} {value}")?;
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(header);
+
+    for record in maf_records {
+        writer.serialize(record)?;
     }
 
-    let mut output_file = path.to_path_buf();
-    output_file.set_extension("xlsx");
-    workbook.save(output_file).map_err(Into::into)
+    Ok(writer.into_inner()?)
+}
+
+/// Returns the DNPM chromosome of a record or an error for an unknown or missing chromosome
+fn dnpm_chromosome(chromosome: &str, gene: &str) -> Result<Chromosome, Box<dyn std::error::Error>> {
+    map_chromosome(chromosome)
+        .map_err(|()| format!("Unknown chromosome '{chromosome}' of gene '{gene}'").into())
 }
 
-pub(crate) fn write_json_file(
-    path: &Path,
+pub(crate) fn dnpm_json_content(
     simple_variant_records: &[SimpleVariantRecord],
     copy_number_records: &[CopyNumberRecord],
-    fusion_records: &[FusionRecord],
-    biomarker_records: &[BiomarkerRecord],
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<String, Box<dyn std::error::Error>> {
     let simple_variants = simple_variant_records
         .iter()
-        .map(|record| Snv {
-            allelic_frequency: f64::from_str(&record.allelic_frequency.replace(',', "."))
-                .unwrap_or(0.0),
-            alt_allele: if record.alt_allele.is_empty() {
-                "-".to_string()
-            } else {
-                record.alt_allele.clone()
-            },
-            // Use Chromosome::ChrMt as placeholder for non present value
-            chromosome: map_chromosome(&record.chromosome).unwrap_or(Chromosome::ChrMt),
-            dna_change: record.cdna.clone(),
-            exon_id: None,
-            external_ids: None,
-            gene: Coding {
-                code: record.gene.clone(),
-                display: Some(record.hgnc_name.clone()),
-                system: None,
-                version: None,
-            },
-            id: String::new(),
-            interpretation: None,
-            localization: None,
-            patient: Reference {
-                display: None,
+        .map(|record| {
+            Ok(Snv {
+                allelic_frequency: decimal(record.allelic_frequency).unwrap_or(0.0),
+                alt_allele: if record.alt_allele.is_empty() {
+                    "-".to_string()
+                } else {
+                    record.alt_allele.clone()
+                },
+                chromosome: dnpm_chromosome(&record.chromosome, &record.gene)?,
+                dna_change: record.cdna.clone(),
+                exon_id: None,
+                external_ids: None,
+                gene: Coding {
+                    code: record.gene.clone(),
+                    display: Some(record.hgnc_name.clone()),
+                    system: None,
+                    version: None,
+                },
                 id: String::new(),
-                reference_type: None,
-                system: None,
-            },
-            position: Position {
-                start: record.start.parse().unwrap_or(0.0),
-                end: record.end.parse().ok(),
-            },
-            protein_change: if record.protein.clone().is_empty() {
-                None
-            } else {
-                Some(record.protein.clone())
-            },
-            read_depth: record.read_depth.parse().unwrap_or(0), // To be interpreted as "not present"
-            ref_allele: if record.ref_allele.is_empty() {
-                "-".to_string()
-            } else {
-                record.ref_allele.clone()
-            },
-            transcript_id: TranscriptId {
-                system: TranscriptIdSystem::EnsemblOrg,
-                value: record.ensembl_id.clone(),
-            },
+                interpretation: None,
+                localization: None,
+                patient: Reference {
+                    display: None,
+                    id: String::new(),
+                    reference_type: None,
+                    system: None,
+                },
+                position: Position {
+                    start: record.start.parse().unwrap_or(0.0),
+                    end: record.end.parse().ok(),
+                },
+                protein_change: if record.protein.clone().is_empty() {
+                    None
+                } else {
+                    Some(record.protein.clone())
+                },
+                read_depth: record.read_depth.map_or(0, i64::from), // To be interpreted as "not present"
+                ref_allele: if record.ref_allele.is_empty() {
+                    "-".to_string()
+                } else {
+                    record.ref_allele.clone()
+                },
+                transcript_id: TranscriptId {
+                    system: TranscriptIdSystem::EnsemblOrg,
+                    value: record.ensembl_id.clone(),
+                },
+            })
         })
-        .collect_vec();
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
 
     let copy_number_variants = copy_number_records
         .iter()
-        .map(|record| Cnv {
-            // Use Chromosome::ChrMt as placeholder for non present value
-            chromosome: map_chromosome(&record.chromosome).unwrap_or(Chromosome::ChrMt),
-            cn_a: None,
-            cn_b: None,
-            cnv_type: CnvCoding {
-                code: if record.cnv_type.contains("loss") {
-                    CnvCodingCode::Loss
-                } else if record
-                    .total_copy_number
-                    .replace(',', ".")
-                    .parse()
-                    .unwrap_or(0.0)
-                    < 3.0
-                {
-                    CnvCodingCode::LowLevelGain
-                } else {
-                    CnvCodingCode::HighLevelGain
+        .map(|record| {
+            Ok(Cnv {
+                chromosome: dnpm_chromosome(&record.chromosome, &record.gene)?,
+                cn_a: None,
+                cn_b: None,
+                cnv_type: CnvCoding {
+                    code: if record.cnv_type.contains("loss") {
+                        CnvCodingCode::Loss
+                    } else if record.total_copy_number.unwrap_or(0.0) < 3.0 {
+                        CnvCodingCode::LowLevelGain
+                    } else {
+                        CnvCodingCode::HighLevelGain
+                    },
+                    display: Some(record.cnv_type.clone()),
+                    system: None,
+                    version: None,
                 },
-                display: Some(record.cnv_type.clone()),
-                system: None,
-                version: None,
-            },
-            copy_number_neutral_lo_h: None,
-            end_range: None,
-            external_ids: None,
-            id: String::new(),
-            localization: None,
-            patient: Reference {
-                display: None,
+                copy_number_neutral_lo_h: None,
+                end_range: None,
+                external_ids: None,
                 id: String::new(),
-                reference_type: None,
-                system: None,
-            },
-            relative_copy_number: None,
-            reported_affected_genes: None,
-            reported_focality: None,
-            start_range: None,
-            total_copy_number: record
-                .total_copy_number
-                .replace(',', ".")
-                .parse::<i64>()
-                .ok(),
+                localization: None,
+                patient: Reference {
+                    display: None,
+                    id: String::new(),
+                    reference_type: None,
+                    system: None,
+                },
+                relative_copy_number: None,
+                reported_affected_genes: if record.gene.is_empty() {
+                    None
+                } else {
+                    Some(vec![Coding {
+                        code: record.gene.clone(),
+                        display: Some(record.hgnc_name.clone()),
+                        system: None,
+                        version: None,
+                    }])
+                },
+                reported_focality: None,
+                start_range: None,
+                total_copy_number: record
+                    .total_copy_number
+                    .and_then(|value| format!("{value:.0}").parse::<i64>().ok()),
+            })
         })
-        .collect_vec();
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
 
     let ngs_report_results = NgsReportResults {
         brcaness: None,
@@ -301,16 +1049,58 @@ pub(crate) fn write_json_file(
         tmb: None,
         tumor_cell_content: None,
     };
-    let json_content = serde_json::to_string_pretty(&ngs_report_results)?;
-    let mut output_file = path.to_path_buf();
-    output_file.set_extension("dnpm.json");
-    fs::write(output_file, json_content).map_err(Into::into)
+    serde_json::to_string_pretty(&ngs_report_results).map_err(Into::into)
+}
+
+/// Serializes the metadata written next to DNPM JSON NGS report results: the reference genome
+/// and pipeline as in the `metadata` of an NGS report and the provenance of the records
+pub(crate) fn dnpm_metadata_content(
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    provenance: Option<&Provenance>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let ref_genome = simple_variant_records
+        .iter()
+        .map(|record| record.ref_genome.as_str())
+        .chain(
+            copy_number_records
+                .iter()
+                .map(|record| record.ref_genome.as_str()),
+        )
+        .find(|ref_genome| !ref_genome.is_empty())
+        .unwrap_or_default();
+    let content = serde_json::json!({
+        "metadata": [{
+            "referenceGenome": ref_genome,
+            "pipeline": format!("mhguide-to-csv {}", env!("CARGO_PKG_VERSION")),
+        }],
+        "provenance": provenance
+            .map(Provenance::entries)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), serde_json::Value::from(value)))
+            .collect::<serde_json::Map<_, _>>(),
+    });
+    serde_json::to_string_pretty(&content).map_err(Into::into)
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
-    use crate::files::read_json_content;
+    use crate::export_record::BiomarkerRecord;
+    use crate::export_record::ValueFormat;
+    use crate::export_record::{FusionRecord, Records, SimpleVariantRecord};
+    use crate::files::{
+        CsvDialect, Encoding, LineEnding, OdsTable, QuoteStyle, csv_content, flat_csv_content,
+        ods_content, ods_document, read_content, read_json_content, zip_json_content,
+    };
+    use crate::messages::Language;
+    use crate::mhguide::MhGuide;
+    use crate::mhguide::RefGenomeVersion;
+    use crate::profile::Profile;
+    use crate::provenance::sha256;
+    use rstest::rstest;
+    use std::io::{Cursor, Read};
     use std::path::PathBuf;
     use std::str::FromStr;
 
@@ -318,16 +1108,346 @@ mod tests {
 
     #[test]
     fn test_should_read_json_content() {
-        let actual =
-            read_json_content(&PathBuf::from_str("./testfiles/sv-mhguide.json").unwrap()).unwrap();
+        let path = PathBuf::from_str("./testfiles/sv-mhguide.json").unwrap();
+        let actual = read_json_content(&path, &std::fs::read(&path).unwrap()).unwrap();
         assert_eq!(actual, TEST_CONTENT);
     }
 
+    #[test]
+    fn test_should_read_uploaded_content() {
+        let json = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let zip = std::fs::read("./testfiles/sv-mhguide.json.zip").unwrap();
+
+        let mut from_json = read_content(&json).unwrap();
+        let mut from_zip = read_content(&zip).unwrap();
+
+        assert_eq!(from_json.general.input_sha256, Some(sha256(&json)));
+        assert_eq!(from_zip.general.input_sha256, Some(sha256(&zip)));
+        from_json.general.input_sha256 = None;
+        from_zip.general.input_sha256 = None;
+        assert_eq!(from_json, from_zip);
+        assert!(read_content(b"invalid").is_err());
+    }
+
     #[test]
     fn test_should_read_zip_content() {
-        let actual =
-            read_json_content(&PathBuf::from_str("./testfiles/sv-mhguide.json.zip").unwrap())
-                .unwrap();
+        let path = PathBuf::from_str("./testfiles/sv-mhguide.json.zip").unwrap();
+        let actual = read_json_content(&path, &std::fs::read(&path).unwrap()).unwrap();
         assert_eq!(actual, TEST_CONTENT);
     }
+
+    #[test]
+    fn test_should_reject_too_large_zip_content() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json.zip").unwrap();
+        let actual = zip_json_content(Cursor::new(content), 1024);
+        assert!(actual.is_err_and(|err| err.to_string().contains("maximum size")));
+    }
+
+    #[test]
+    fn test_should_create_ods_content() {
+        let biomarker_records = vec![BiomarkerRecord::from_tmb(
+            "H/2025/12345",
+            &RefGenomeVersion::Hg19,
+            5.2,
+        )];
+        let table = OdsTable::new(
+            "Biomarker",
+            &Profile::default(),
+            ValueFormat::default(),
+            &biomarker_records,
+        )
+        .unwrap();
+
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[1][0].text, "H/2025/12345");
+
+        let actual = ods_content(&[table]).unwrap();
+
+        assert!(actual.contains(r#"<table:table table:name="Biomarker">"#));
+        assert!(actual.contains(r#"<table:table-cell table:style-name="header" office:value-type="string"><text:p>H-Nummer</text:p></table:table-cell>"#));
+        assert!(actual.contains(
+            r#"<table:table-cell office:value-type="float" office:value="5.2"><text:p>5,20</text:p></table:table-cell>"#
+        ));
+        assert!(actual.contains(r#"<table:table-cell office:value-type="string"><text:p>H/2025/12345</text:p></table:table-cell>"#));
+    }
+
+    #[test]
+    fn test_should_write_numeric_ods_cells() {
+        let simple_variant_records = vec![SimpleVariantRecord {
+            h_nummer: "H/2025/12345".to_string(),
+            read_depth: Some(567),
+            allelic_frequency: Some(42.424),
+            ..SimpleVariantRecord::default()
+        }];
+        let table = OdsTable::new(
+            "Einfache Varianten",
+            &Profile::default(),
+            ValueFormat::default(),
+            &simple_variant_records,
+        )
+        .unwrap();
+
+        let actual = ods_content(&[table]).unwrap();
+
+        assert!(
+            actual.contains(r#"office:value-type="float" office:value="567"><text:p>567</text:p>"#)
+        );
+        assert!(
+            actual.contains(
+                r#"office:value-type="float" office:value="42.42"><text:p>42,42</text:p>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_should_create_ods_document_without_records() {
+        let document = ods_document(
+            &Profile::default(),
+            ValueFormat::default(),
+            &[],
+            &[],
+            &[],
+            &[],
+            None,
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(document)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert!(content.contains(r#"<table:table table:name="Einfache Varianten">"#));
+    }
+
+    #[test]
+    fn test_should_write_provenance_into_maf_header() {
+        let input = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read_content(&input).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+        let maf_records = crate::maf_records(&mhguide, crate::VariantSelection::All);
+
+        let actual = crate::maf(&maf_records.records, records.provenance.as_ref()).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+
+        assert!(actual.starts_with("#version 2.4\n#inputSha256 "));
+        assert!(actual.contains("\n#variantSelection all-variants\n#created "));
+        assert!(actual.contains("\nHugo_Symbol\t"));
+    }
+
+    #[test]
+    fn test_should_write_provenance_into_ods_document() {
+        let input = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read_content(&input).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let document = crate::ods(&records, ValueFormat::default(), &Profile::default()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(document)).unwrap();
+        let mut meta = String::new();
+        archive
+            .by_name("meta.xml")
+            .unwrap()
+            .read_to_string(&mut meta)
+            .unwrap();
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert!(meta.contains(&format!(
+            r#"<meta:user-defined meta:name="inputSha256">{}</meta:user-defined>"#,
+            sha256(&input)
+        )));
+        assert!(content.contains(r#"<table:table table:name="Metadaten">"#));
+    }
+
+    #[rstest]
+    #[case(CsvDialect::default(), b"H-Nummer;Referenz-Genom;Ergebnis;HRD - Score/Ergebnis;MSI - Prozentwert;TMB - Tumor Mutational Burden\nH/2025/12345;HG19;TMB;;;5,20\n\"\"\n".to_vec())]
+    #[case(
+        CsvDialect::new(',', QuoteStyle::Always, '.', Language::De, LineEnding::Crlf, Encoding::Utf8Bom, false).unwrap(),
+        b"\xEF\xBB\xBF\"H-Nummer\",\"Referenz-Genom\",\"Ergebnis\",\"HRD - Score/Ergebnis\",\"MSI - Prozentwert\",\"TMB - Tumor Mutational Burden\"\r\n\"H/2025/12345\",\"HG19\",\"TMB\",\"\",\"\",\"5.20\"\r\n\"\"\r\n".to_vec()
+    )]
+    #[case(
+        CsvDialect::new(';', QuoteStyle::NonNumeric, ',', Language::De, LineEnding::Lf, Encoding::Utf8, false).unwrap(),
+        b"\"H-Nummer\";\"Referenz-Genom\";\"Ergebnis\";\"HRD - Score/Ergebnis\";\"MSI - Prozentwert\";\"TMB - Tumor Mutational Burden\"\n\"H/2025/12345\";\"HG19\";\"TMB\";\"\";\"\";5,20\n\"\"\n".to_vec()
+    )]
+    #[case(
+        CsvDialect::new(',', QuoteStyle::NonNumeric, '.', Language::De, LineEnding::Lf, Encoding::Utf8, false).unwrap(),
+        b"\"H-Nummer\",\"Referenz-Genom\",\"Ergebnis\",\"HRD - Score/Ergebnis\",\"MSI - Prozentwert\",\"TMB - Tumor Mutational Burden\"\n\"H/2025/12345\",\"HG19\",\"TMB\",\"\",\"\",5.20\n\"\"\n".to_vec()
+    )]
+    fn test_should_create_csv_content(#[case] dialect: CsvDialect, #[case] expected: Vec<u8>) {
+        let biomarker_records = vec![BiomarkerRecord::from_tmb(
+            "H/2025/12345",
+            &RefGenomeVersion::Hg19,
+            5.2,
+        )];
+
+        let actual = csv_content(
+            &dialect,
+            &Profile::default(),
+            &[],
+            &[],
+            &[],
+            &biomarker_records,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_should_encode_csv_content_as_windows_1252() {
+        let dialect = CsvDialect {
+            encoding: Encoding::Windows1252,
+            ..CsvDialect::default()
+        };
+
+        assert_eq!(
+            dialect.encode("Größe".as_bytes().to_vec()).unwrap(),
+            b"Gr\xF6\xDFe"
+        );
+        assert!(dialect.encode("α".as_bytes().to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_should_use_variant_id_as_flat_csv_row_id() {
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual =
+            flat_csv_content(&CsvDialect::default(), &Profile::default(), &records).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+
+        assert!(actual.contains(&format!(
+            "simple_variants;{}-simple_variants-12345678;",
+            mhguide.general.patient_identifier.h_number
+        )));
+    }
+
+    #[test]
+    fn test_should_create_flat_csv_content() {
+        let content = std::fs::read_to_string("./testfiles/rnafusion-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        let h_number = &mhguide.general.patient_identifier.h_number;
+
+        let records = Records {
+            simple_variants: vec![],
+            copy_numbers: vec![],
+            fusions: mhguide
+                .fusions()
+                .iter()
+                .map(|fusion| {
+                    FusionRecord::from_fusion(h_number, &mhguide.general.ref_genome_version, fusion)
+                })
+                .collect(),
+            biomarkers: vec![BiomarkerRecord::from_tmb(
+                h_number,
+                &mhguide.general.ref_genome_version,
+                5.2,
+            )],
+            provenance: None,
+        };
+
+        let actual =
+            flat_csv_content(&CsvDialect::default(), &Profile::default(), &records).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+        let lines = actual.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), records.fusions.len() + 2);
+        assert!(lines[0].starts_with("record_type;row_id;H-Nummer;Referenz-Genom;Ergebnis;Gen;"));
+        assert!(
+            lines[0]
+                .ends_with(";HRD - Score/Ergebnis;MSI - Prozentwert;TMB - Tumor Mutational Burden")
+        );
+
+        let columns = lines[0].split(';').count();
+        assert!(lines.iter().all(|line| line.split(';').count() == columns));
+        let fusion = &records.fusions[0];
+        assert!(lines[1].starts_with(&format!(
+            "fusions;{h_number}-fusions-{}::{};{h_number};",
+            fusion.gene, fusion.fusion_gene
+        )));
+        assert!(
+            lines
+                .last()
+                .unwrap()
+                .starts_with(&format!("biomarkers;{h_number}-biomarkers-TMB;{h_number};"))
+        );
+        assert!(lines.last().unwrap().ends_with(";;;5,20"));
+    }
+    #[test]
+    fn test_should_append_provenance_to_csv_content() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read_content(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let dialect = CsvDialect {
+            provenance_comments: true,
+            ..CsvDialect::default()
+        };
+
+        let actual = crate::csv(&records, &dialect, &Profile::default()).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+
+        assert!(actual.contains(&format!("\n# inputSha256: {}\n", sha256(&content))));
+        assert!(actual.contains("\n# caseUuid: 00000000-0001-9999-ffff-000000000001\n"));
+        assert!(actual.contains("\n# variantSelection: all-variants\n"));
+    }
+
+    #[test]
+    fn test_should_use_decimal_separator_of_dialect_for_other_formats() {
+        let dialect = CsvDialect::new(
+            ';',
+            QuoteStyle::Necessary,
+            '.',
+            Language::En,
+            LineEnding::Lf,
+            Encoding::Utf8,
+            false,
+        )
+        .unwrap();
+
+        let value_format = dialect.value_format();
+
+        assert_eq!(value_format.decimal_separator, '.');
+        assert_eq!(value_format.decimal(12.345), "12.35");
+    }
+
+    #[test]
+    fn test_should_not_append_provenance_to_csv_content_by_default() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read_content(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual = crate::csv(&records, &CsvDialect::default(), &Profile::default()).unwrap();
+        let actual = String::from_utf8(actual).unwrap();
+
+        assert!(!actual.contains("\n# "));
+    }
+
+    #[test]
+    fn test_should_write_provenance_into_dnpm_metadata() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read_content(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let results = crate::dnpm_json(&records).unwrap();
+        let results = serde_json::from_str::<serde_json::Value>(&results).unwrap();
+        let actual = crate::dnpm_metadata_json(&records).unwrap();
+        let actual = serde_json::from_str::<serde_json::Value>(&actual).unwrap();
+
+        assert!(results["simpleVariants"].is_array());
+        assert!(results.get("metadata").is_none());
+        assert_eq!(actual["metadata"][0]["referenceGenome"], "HG19");
+        assert_eq!(actual["provenance"]["inputSha256"], sha256(&content));
+        assert_eq!(actual["provenance"]["reportVersion"], "1");
+    }
 }
@@ -13,8 +13,16 @@ pub(crate) struct Gene {
     pub(crate) name: String,
     #[serde(rename = "Ensembl ID(supplied by Ensembl)")]
     pub(crate) ensembl_id: Option<String>,
+    #[serde(rename = "NCBI Gene ID(supplied by NCBI)")]
+    pub(crate) entrez_id: Option<String>,
 }
 
+/// Tab separated list of all genes, see `Genes::new`
+pub(crate) static GENE_LIST: &str = include_str!("../resources/genes.csv");
+
+/// Download date of the gene list, to be updated together with `resources/genes.csv`
+pub(crate) const GENE_LIST_DATE: &str = "2026-04-02";
+
 pub(crate) struct Genes {
     genes: Vec<Gene>,
 }
@@ -29,6 +37,7 @@ impl Genes {
     /// - Column 2: Previous symbols (comma-separated list)
     /// - Column 3: Gene name
     /// - Column 5: Ensembl ID
+    /// - Column 6: NCBI (Entrez) gene ID, optional
     ///
     /// Each record from the CSV is mapped to a `Gene` struct, which includes:
     /// - `hgnc_id` (String): The HGNC ID of the gene.
@@ -36,6 +45,7 @@ impl Genes {
     /// - `previous_symbols` (Vec<String>): A list of previous symbols for the gene.
     /// - `name` (String): The name of the gene.
     /// - `ensembl_id` (Option<String>): The Ensembl ID, if provided.
+    /// - `entrez_id` (Option<String>): The NCBI gene ID, if provided.
     ///
     /// # Returns
     /// A `Genes` instance containing a vector of all parsed `Gene` records.
@@ -46,7 +56,7 @@ impl Genes {
     /// if there are issues with the compile-time inclusion of the resource file.
     ///
     /// # Example
-    /// ```rust
+    /// ```ignore
     /// let genes = Genes::new();
     /// println!("{:?}", genes);
     /// ```
@@ -59,7 +69,6 @@ impl Genes {
     /// # Dependencies
     /// - The `csv` crate is used to parse the CSV file.
     pub(crate) fn new() -> Genes {
-        static GENE_LIST: &str = include_str!("../resources/genes.csv");
         let mut reader = ReaderBuilder::default()
             .delimiter(b'\t')
             .from_reader(GENE_LIST.as_bytes());
@@ -83,6 +92,10 @@ impl Genes {
                     .collect(),
                 name: record.get(3).unwrap_or_default().to_string(),
                 ensembl_id: record.get(5).map(ToString::to_string),
+                entrez_id: record
+                    .get(6)
+                    .filter(|value| !value.is_empty())
+                    .map(ToString::to_string),
             })
             .collect::<Vec<_>>();
 
@@ -107,7 +120,7 @@ impl Genes {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let genes = Genes::new();
     ///
     /// if let Some(gene) = genes.find_by_symbol("BRCA1") {
@@ -139,7 +152,7 @@ impl Genes {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let genes = Genes::new();
     /// if let Some(gene) = genes.find_by_previous_symbol("OLD1") {
     ///     println!("Found gene: {:?}", gene);
@@ -0,0 +1,310 @@
+use crate::export_record::{BiomarkerRecord, ExportRecord, Records, ValueFormat};
+use crate::files::escape_xml;
+use crate::input::InputSource;
+use crate::mhguide::Fusion;
+use std::fmt::Write;
+use std::str::FromStr;
+
+const STYLE: &str = "
+body { font-family: sans-serif; font-size: 14px; margin: 2em; color: #222; }
+h1 { font-size: 1.6em; }
+h2 { font-size: 1.2em; margin-top: 2em; }
+dl { display: grid; grid-template-columns: max-content auto; gap: .25em 1em; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+table { border-collapse: collapse; margin-top: .5em; }
+th, td { border: 1px solid #ccc; padding: .25em .5em; text-align: left; white-space: nowrap; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: ' \\25B2'; }
+th[data-order=desc]::after { content: ' \\25BC'; }
+tbody tr:nth-child(even) { background: #f8f8f8; }
+pre { white-space: pre-wrap; background: #f8f8f8; border: 1px solid #ccc; padding: 1em; }
+mark.variant { background: #ffe083; }
+mark.copy-number { background: #a8d8ff; }
+mark.fusion { background: #b8f0b0; }
+";
+
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(table => {
+  table.querySelectorAll('th').forEach((th, column) => th.addEventListener('click', () => {
+    const ascending = th.dataset.order !== 'asc';
+    table.querySelectorAll('th').forEach(header => delete header.dataset.order);
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const tbody = table.tBodies[0];
+    Array.from(tbody.rows)
+      .sort((a, b) => a.cells[column].textContent.localeCompare(b.cells[column].textContent, 'de', { numeric: true }) * (ascending ? 1 : -1))
+      .forEach(row => tbody.appendChild(row));
+  }));
+});
+";
+
+fn write_table<T: ExportRecord>(
+    html: &mut String,
+    title: &str,
+    records: &[T],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(html, "<h2>{}</h2>", escape_xml(title))?;
+    writeln!(html, r#"<table class="sortable"><thead><tr>"#)?;
+    for headline in T::csv_headlines() {
+        write!(html, "<th>{}</th>", escape_xml(&headline))?;
+    }
+    writeln!(html, "</tr></thead><tbody>")?;
+    let value_format = ValueFormat::default();
+    for record in records {
+        write!(html, "<tr>")?;
+        for value in record.formatted_values(value_format) {
+            write!(html, "<td>{}</td>", escape_xml(&value))?;
+        }
+        writeln!(html, "</tr>")?;
+    }
+    writeln!(html, "</tbody></table>")?;
+    Ok(())
+}
+
+fn biomarker_summary(biomarker_records: &[BiomarkerRecord]) -> Vec<(&'static str, String)> {
+    biomarker_records
+        .iter()
+        .filter_map(|record| {
+            let value_format = ValueFormat::default();
+            if let Some(hrd) = record.hrd {
+                Some(("HRD - Score", value_format.decimal(hrd)))
+            } else if let Some(msi) = record.msi {
+                Some(("MSI - Prozentwert", value_format.decimal(msi)))
+            } else {
+                record
+                    .tmb
+                    .map(|tmb| ("TMB", format!("{} Mut/Mb", value_format.decimal(tmb))))
+            }
+        })
+        .collect()
+}
+
+/// Marks all recognised variants and fusions in the report narrative.
+///
+/// Simple variants are matched by gene and protein or cDNA change, copy number variants by gene
+/// in lines with a gene copy number (GCN) and fusions by the whole line.
+fn highlight_narrative(report_narrative: &str, records: &Records) -> String {
+    let simple_variant_terms = records
+        .simple_variants
+        .iter()
+        .flat_map(|record| [&record.protein_orig, &record.cdna].map(|change| (record, change)))
+        .filter(|(_, change)| !change.is_empty())
+        .map(|(record, change)| format!("{} {change}", record.gene))
+        .collect::<Vec<_>>();
+
+    report_narrative
+        .split('\n')
+        .map(|line| {
+            if Fusion::from_str(line).is_ok() {
+                return format!(r#"<mark class="fusion">{}</mark>"#, escape_xml(line));
+            }
+
+            let mut matches = simple_variant_terms
+                .iter()
+                .flat_map(|term| line.match_indices(term.as_str()))
+                .map(|(start, term)| (start, start + term.len(), "variant"))
+                .collect::<Vec<_>>();
+            if line.contains("GCN") {
+                matches.extend(
+                    records
+                        .copy_numbers
+                        .iter()
+                        .flat_map(|record| line.match_indices(record.gene.as_str()))
+                        .map(|(start, term)| (start, start + term.len(), "copy-number")),
+                );
+            }
+            matches.sort_unstable();
+
+            let mut result = String::new();
+            let mut position = 0;
+            for (start, end, class) in matches {
+                // Skip overlapping matches
+                if start < position {
+                    continue;
+                }
+                result.push_str(&escape_xml(&line[position..start]));
+                let _ = write!(
+                    result,
+                    r#"<mark class="{class}">{}</mark>"#,
+                    escape_xml(&line[start..end])
+                );
+                position = end;
+            }
+            result.push_str(&escape_xml(&line[position..]));
+            result
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Creates a self-contained HTML report with the case data, all exported records,
+/// the report narrative with highlighted variants and fusions and the provenance.
+pub(crate) fn html_report(
+    source: &dyn InputSource,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let general = source.general();
+    let h_number = escape_xml(&general.patient_identifier.h_number);
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, r#"<html lang="de">"#)?;
+    writeln!(
+        html,
+        r#"<head><meta charset="utf-8"><title>{h_number}</title>"#
+    )?;
+    writeln!(
+        html,
+        r#"<meta name="generator" content="mhguide-to-csv {}">"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(html, "<style>{STYLE}</style></head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(html, "<h1>MH Guide Befund {h_number}</h1>")?;
+
+    writeln!(html, "<dl>")?;
+    for (name, value) in [
+        ("H-Nummer", general.patient_identifier.h_number.clone()),
+        ("Patienten-ID", general.patient_identifier.pid.clone()),
+        ("Auftragsdatum", general.order_date.clone()),
+        ("Referenz-Genom", general.ref_genome_version.to_string()),
+        ("Diagnose", general.disease_name.clone().unwrap_or_default()),
+        (
+            "MeSH-ID",
+            general.indication_mesh_id.clone().unwrap_or_default(),
+        ),
+        (
+            "Befundversion",
+            general
+                .report_version
+                .map(|version| version.to_string())
+                .unwrap_or_default(),
+        ),
+    ] {
+        writeln!(
+            html,
+            "<dt>{}</dt><dd>{}</dd>",
+            escape_xml(name),
+            escape_xml(&value)
+        )?;
+    }
+    writeln!(html, "</dl>")?;
+
+    let biomarkers = biomarker_summary(&records.biomarkers);
+    if !biomarkers.is_empty() {
+        writeln!(html, "<h2>Biomarker</h2>")?;
+        writeln!(html, "<dl>")?;
+        for (name, value) in biomarkers {
+            writeln!(html, "<dt>{name}</dt><dd>{}</dd>", escape_xml(&value))?;
+        }
+        writeln!(html, "</dl>")?;
+    }
+
+    write_table(&mut html, "Einfache Varianten", &records.simple_variants)?;
+    write_table(&mut html, "Copy Number Varianten", &records.copy_numbers)?;
+    write_table(&mut html, "Fusionen", &records.fusions)?;
+
+    writeln!(html, "<h2>Report Narrative</h2>")?;
+    writeln!(
+        html,
+        "<pre>{}</pre>",
+        highlight_narrative(source.report_narrative(), records)
+    )?;
+
+    if let Some(provenance) = &records.provenance {
+        writeln!(html, "<h2>Herkunftsangaben</h2>")?;
+        writeln!(html, r#"<dl class="provenance">"#)?;
+        for (name, value) in provenance.entries() {
+            writeln!(html, "<dt>{name}</dt><dd>{}</dd>", escape_xml(&value))?;
+        }
+        writeln!(html, "</dl>")?;
+    }
+
+    writeln!(html, "<script>{SCRIPT}</script>")?;
+    writeln!(html, "</body>")?;
+    writeln!(html, "</html>")?;
+
+    Ok(html)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{FusionRecord, Records, SimpleVariantRecord};
+    use crate::html::{highlight_narrative, html_report};
+    use crate::mhguide::MhGuide;
+
+    #[test]
+    fn test_should_highlight_fusion_in_report_narrative() {
+        let content = std::fs::read_to_string("./testfiles/rnafusion-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+
+        let records = Records {
+            simple_variants: vec![],
+            copy_numbers: vec![],
+            fusions: mhguide
+                .fusions()
+                .iter()
+                .map(|fusion| {
+                    FusionRecord::from_fusion(
+                        &mhguide.general.patient_identifier.h_number,
+                        &mhguide.general.ref_genome_version,
+                        fusion,
+                    )
+                })
+                .collect(),
+            biomarkers: vec![],
+            provenance: None,
+        };
+
+        let narrative = highlight_narrative(mhguide.report_narrative(), &records);
+        assert!(narrative.contains(r#"<mark class="fusion">"#));
+
+        let actual = html_report(&mhguide, &records).unwrap();
+        assert!(actual.contains("<h2>Fusionen</h2>"));
+        assert!(!actual.contains("<h2>Einfache Varianten</h2>"));
+        assert!(!actual.contains("src="));
+    }
+
+    #[test]
+    fn test_should_highlight_simple_variant_in_report_narrative() {
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+
+        let record = SimpleVariantRecord::from_variant(
+            &mhguide.general.patient_identifier.h_number,
+            &mhguide.general.ref_genome_version,
+            mhguide.all_variants()[0],
+        );
+        let narrative = format!("Nachweis {} {} & weitere", record.gene, record.cdna);
+        let expected = r#"Nachweis <mark class="variant">BRAF c.123C&gt;T</mark> &amp; weitere"#;
+
+        let records = Records {
+            simple_variants: vec![record],
+            copy_numbers: vec![],
+            fusions: vec![],
+            biomarkers: vec![],
+            provenance: None,
+        };
+
+        assert_eq!(highlight_narrative(&narrative, &records), expected);
+    }
+    #[test]
+    fn test_should_write_provenance() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = crate::read(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual = html_report(&mhguide, &records).unwrap();
+
+        assert!(actual.contains("<h2>Herkunftsangaben</h2>"));
+        assert!(actual.contains(&format!(
+            "<dt>inputSha256</dt><dd>{}</dd>",
+            crate::provenance::sha256(&content)
+        )));
+    }
+}
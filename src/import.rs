@@ -0,0 +1,382 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, ExportRecord, FieldValue, FusionRecord, Records,
+    SimpleVariantRecord, ValueFormat,
+};
+use crate::files::{CsvDialect, METADATA_SHEET, is_chromosome};
+use crate::messages::{Language, translate, untranslate};
+use crate::profile::Profile;
+use calamine::{Reader, Xlsx};
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use std::io::Cursor;
+
+/// Reference genomes written by all exports
+const REF_GENOMES: [&str; 2] = ["HG19", "HG38"];
+
+/// Row of an imported table with its location used in error messages
+struct Row {
+    location: String,
+    values: Vec<String>,
+}
+
+/// Table with a header row, e.g. one section of a CSV file or one worksheet
+struct Table {
+    header: Vec<String>,
+    rows: Vec<Row>,
+}
+
+/// Reads the rows of the table if the header matches the exported columns of the record type.
+///
+/// Columns not contained in the profile are left empty, decimal numbers and translated labels
+/// are converted back before the row is deserialized.
+fn read_records<T: ExportRecord + Default + DeserializeOwned>(
+    table: &Table,
+    profile: &Profile,
+    value_format: ValueFormat,
+) -> Result<Option<Vec<T>>, Box<dyn std::error::Error>> {
+    let columns = profile.columns::<T>(value_format.language)?;
+    if columns.len() != table.header.len()
+        || columns
+            .iter()
+            .zip(&table.header)
+            .any(|(column, header)| column.header != header.trim())
+    {
+        return Ok(None);
+    }
+
+    let headlines = csv::StringRecord::from(T::csv_headlines());
+    let default = T::default();
+    let kinds = default.values();
+
+    table
+        .rows
+        .iter()
+        .map(|row| {
+            if row.values.len() != columns.len() {
+                return Err(format!(
+                    "Invalid record in {}: expected {} values, found {}",
+                    row.location,
+                    columns.len(),
+                    row.values.len()
+                )
+                .into());
+            }
+            let mut values = vec![String::new(); headlines.len()];
+            for (column, value) in columns.iter().zip(&row.values) {
+                let value = value.trim();
+                values[column.index] = match kinds[column.index] {
+                    FieldValue::Decimal(_) => value.replace(value_format.decimal_separator, "."),
+                    FieldValue::Label(_) => untranslate(value_format.language, value),
+                    FieldValue::Text(_) | FieldValue::Integer(_) => value.to_string(),
+                };
+            }
+            let record = csv::StringRecord::from(values)
+                .deserialize::<T>(Some(&headlines))
+                .map_err(|err| format!("Invalid record in {}: {err}", row.location))?;
+            if record.h_number().is_empty() {
+                return Err(format!("Invalid record in {}: missing H-number", row.location).into());
+            }
+            if !REF_GENOMES.contains(&record.ref_genome()) {
+                return Err(format!(
+                    "Invalid record in {}: unknown reference genome '{}'",
+                    row.location,
+                    record.ref_genome()
+                )
+                .into());
+            }
+            if let Some(chromosome) = record.chromosome()
+                && !chromosome.is_empty()
+                && !is_chromosome(chromosome)
+            {
+                return Err(format!(
+                    "Invalid record in {}: unknown chromosome '{chromosome}'",
+                    row.location
+                )
+                .into());
+            }
+            Ok(record)
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()
+        .map(Some)
+}
+
+/// Reads the records of all tables, each table must contain exactly one record type
+fn read_tables(
+    tables: &[Table],
+    profile: &Profile,
+    value_format: ValueFormat,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let mut records = Records::default();
+    for table in tables {
+        if let Some(mut simple_variants) =
+            read_records::<SimpleVariantRecord>(table, profile, value_format)?
+        {
+            records.simple_variants.append(&mut simple_variants);
+        } else if let Some(mut copy_numbers) =
+            read_records::<CopyNumberRecord>(table, profile, value_format)?
+        {
+            records.copy_numbers.append(&mut copy_numbers);
+        } else if let Some(mut fusions) =
+            read_records::<FusionRecord>(table, profile, value_format)?
+        {
+            records.fusions.append(&mut fusions);
+        } else if let Some(mut biomarkers) =
+            read_records::<BiomarkerRecord>(table, profile, value_format)?
+        {
+            records.biomarkers.append(&mut biomarkers);
+        } else {
+            let location = table
+                .rows
+                .first()
+                .map_or_else(String::new, |row| format!(" before {}", row.location));
+            return Err(format!(
+                "Unknown table header{location}: '{}'",
+                table.header.join("', '")
+            )
+            .into());
+        }
+    }
+    validate(&records)?;
+    Ok(records)
+}
+
+/// Checks that all records belong to the same case and use the same reference genome
+fn validate(records: &Records) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = records
+        .simple_variants
+        .iter()
+        .map(|record| (record.h_number(), record.ref_genome()))
+        .chain(
+            records
+                .copy_numbers
+                .iter()
+                .map(|record| (record.h_number(), record.ref_genome())),
+        )
+        .chain(
+            records
+                .fusions
+                .iter()
+                .map(|record| (record.h_number(), record.ref_genome())),
+        )
+        .chain(
+            records
+                .biomarkers
+                .iter()
+                .map(|record| (record.h_number(), record.ref_genome())),
+        )
+        .collect::<Vec<_>>();
+    validate_same("H-number", fields.iter().map(|(h_number, _)| *h_number))?;
+    validate_same(
+        "reference genome",
+        fields.iter().map(|(_, ref_genome)| *ref_genome),
+    )
+}
+
+/// Checks that all values of the field are the same
+fn validate_same<'a>(
+    name: &str,
+    values: impl Iterator<Item = &'a str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let values = values.sorted_unstable().dedup().collect::<Vec<_>>();
+    if values.len() > 1 {
+        return Err(format!(
+            "All records must have the same {name}, found '{}'",
+            values.join("', '")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Reads the sections of a CSV file written with the dialect, separated by an empty line
+pub(crate) fn csv_records(
+    content: &[u8],
+    dialect: &CsvDialect,
+    profile: &Profile,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let content = dialect.decode(content)?;
+    let mut tables = vec![];
+    let mut table: Option<Table> = None;
+    for (index, record) in dialect.reader(&content).records().enumerate() {
+        let values = record?.iter().map(str::to_string).collect::<Vec<_>>();
+        if values.iter().all(String::is_empty) {
+            tables.extend(table.take());
+            continue;
+        }
+        match &mut table {
+            Some(table) => table.rows.push(Row {
+                location: format!("line {}", index + 1),
+                values,
+            }),
+            None => {
+                table = Some(Table {
+                    header: values,
+                    rows: vec![],
+                });
+            }
+        }
+    }
+    tables.extend(table);
+
+    read_tables(&tables, profile, dialect.value_format)
+}
+
+/// Reads the worksheets of an XLSX file, the first row of each worksheet contains the headers.
+/// The metadata worksheet with the provenance is skipped.
+pub(crate) fn xlsx_records(
+    content: &[u8],
+    language: Language,
+    profile: &Profile,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let mut workbook = Xlsx::new(Cursor::new(content))?;
+    let mut tables = vec![];
+    for (name, range) in workbook.worksheets() {
+        if name == translate(language, METADATA_SHEET) {
+            continue;
+        }
+        let mut rows = range
+            .rows()
+            .map(|row| row.iter().map(ToString::to_string).collect::<Vec<_>>())
+            .enumerate()
+            .filter(|(_, values)| !values.iter().all(String::is_empty));
+        let Some((_, header)) = rows.next() else {
+            continue;
+        };
+        tables.push(Table {
+            header,
+            rows: rows
+                .map(|(index, values)| Row {
+                    location: format!("worksheet '{name}', row {}", index + 1),
+                    values,
+                })
+                .collect(),
+        });
+    }
+
+    read_tables(
+        &tables,
+        profile,
+        ValueFormat {
+            decimal_separator: '.',
+            language,
+        },
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::files::CsvDialect;
+    use crate::import::{csv_records, xlsx_records};
+    use crate::profile::Profile;
+    use crate::{ValueFormat, VariantSelection, csv, read, records, xlsx};
+    use rstest::rstest;
+
+    #[test]
+    fn test_should_import_exported_csv() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let exported = records(&mhguide, VariantSelection::All);
+        let content = csv(&exported, &CsvDialect::default(), &Profile::default()).unwrap();
+
+        let imported = csv_records(&content, &CsvDialect::default(), &Profile::default()).unwrap();
+
+        assert_eq!(imported.simple_variants.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&imported.simple_variants[0]).unwrap(),
+            serde_json::to_value(&exported.simple_variants[0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_should_import_exported_xlsx() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        // Contains the metadata worksheet with the provenance
+        let exported = records(&mhguide, VariantSelection::All);
+        let content = xlsx(&exported, ValueFormat::default(), &Profile::default()).unwrap();
+
+        let imported = xlsx_records(
+            &content,
+            ValueFormat::default().language,
+            &Profile::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&imported.simple_variants).unwrap(),
+            serde_json::to_value(&exported.simple_variants).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&imported.biomarkers).unwrap(),
+            serde_json::to_value(&exported.biomarkers).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_should_import_edited_csv() {
+        let content = "H-Nummer;Referenz-Genom;Ergebnis;HRD - Score/Ergebnis;MSI - Prozentwert;TMB - Tumor Mutational Burden\n\
+            H/2025/12345;HG19;TMB;;;5,20\n\
+            \"\"\n";
+
+        let imported = csv_records(
+            content.as_bytes(),
+            &CsvDialect::default(),
+            &Profile::default(),
+        )
+        .unwrap();
+
+        assert_eq!(imported.biomarkers.len(), 1);
+        assert_eq!(imported.biomarkers[0].tmb, Some(5.2));
+    }
+
+    #[test]
+    fn test_should_reject_invalid_csv() {
+        let content = "H-Nummer;Referenz-Genom;Ergebnis;HRD - Score/Ergebnis;MSI - Prozentwert;TMB - Tumor Mutational Burden\n\
+            H/2025/12345;HG19;TMB;;;viel\n";
+
+        let err = csv_records(
+            content.as_bytes(),
+            &CsvDialect::default(),
+            &Profile::default(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(err.to_string().starts_with("Invalid record in line 2"));
+    }
+
+    #[rstest]
+    #[case("H/2025/12345;HG37;BRAF;chr7\n", "unknown reference genome 'HG37'")]
+    #[case("H/2025/12345;HG19;BRAF;7\n", "unknown chromosome '7'")]
+    #[case(
+        "H/2025/12345;HG19;BRAF;chr7\nH/2025/12345;HG38;KRAS;chr12\n",
+        "same reference genome"
+    )]
+    fn test_should_reject_invalid_location(#[case] rows: &str, #[case] message: &str) {
+        let profile = serde_json::from_str::<Profile>(
+            r#"{ "copy_number_variants": ["H-Nummer", "Referenz-Genom", "Gen", "Chromosom"] }"#,
+        )
+        .unwrap();
+        let content = format!("H-Nummer;Referenz-Genom;Gen;Chromosom\n{rows}");
+
+        let err = csv_records(content.as_bytes(), &CsvDialect::default(), &profile)
+            .err()
+            .unwrap();
+
+        assert!(err.to_string().contains(message), "{err}");
+    }
+
+    #[test]
+    fn test_should_reject_unknown_table() {
+        let content = "Spalte A;Spalte B\n1;2\n";
+        assert!(
+            csv_records(
+                content.as_bytes(),
+                &CsvDialect::default(),
+                &Profile::default()
+            )
+            .is_err()
+        );
+    }
+}
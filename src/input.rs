@@ -0,0 +1,64 @@
+use crate::VariantSelection;
+use crate::mhguide::{Fusion, General, MhGuide, Variant};
+
+/// Source of the variants and biomarkers of one case, e.g. an MH Guide file or an annotated
+/// VCF file. All sources are exported with the same record builders.
+pub trait InputSource: Sync {
+    /// General data of the case like the patient identifier and the reference genome
+    fn general(&self) -> &General;
+
+    /// Returns the selected variants
+    fn variants(&self, selection: VariantSelection) -> Vec<&Variant>;
+
+    /// Returns all RNA fusions
+    fn fusions(&self) -> Vec<Fusion> {
+        vec![]
+    }
+
+    fn hrd_score(&self) -> Option<f32>;
+
+    fn msi_score(&self) -> Option<f32>;
+
+    fn tmb_value(&self) -> Option<f32>;
+
+    /// Free text report of the case, empty if the source does not contain one
+    #[allow(clippy::unnecessary_literal_bound)]
+    fn report_narrative(&self) -> &str {
+        ""
+    }
+}
+
+impl InputSource for MhGuide {
+    fn general(&self) -> &General {
+        &self.general
+    }
+
+    fn variants(&self, selection: VariantSelection) -> Vec<&Variant> {
+        match selection {
+            VariantSelection::Relevant => self.relevant_variants(false),
+            VariantSelection::RelevantWithoutArtifacts => self.relevant_variants(true),
+            VariantSelection::Oncogenic => self.oncogenic_variants(),
+            VariantSelection::All => self.all_variants(),
+        }
+    }
+
+    fn fusions(&self) -> Vec<Fusion> {
+        self.fusions()
+    }
+
+    fn hrd_score(&self) -> Option<f32> {
+        self.hrd_score()
+    }
+
+    fn msi_score(&self) -> Option<f32> {
+        self.msi_score()
+    }
+
+    fn tmb_value(&self) -> Option<f32> {
+        self.tmb_value()
+    }
+
+    fn report_narrative(&self) -> &str {
+        self.report_narrative()
+    }
+}
@@ -0,0 +1,650 @@
+//! Conversion of MH Guide JSON files into CSV, XLSX, ODS and other formats used for tumor
+//! documentation.
+//!
+//! An MH Guide file is read from its content, the variants are selected and converted into
+//! records, which can then be serialized into each of the supported formats.
+//!
+//! ```no_run
+//! use mhguide_to_csv::{CsvDialect, Profile, VariantSelection};
+//!
+//! let content = std::fs::read("H12345-25.json")?;
+//! let mhguide = mhguide_to_csv::read(&content)?;
+//! let records = mhguide_to_csv::records(&mhguide, VariantSelection::default());
+//! let csv = mhguide_to_csv::csv(&records, &CsvDialect::default(), &Profile::default())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::mhguide::ResultType;
+use rayon::prelude::*;
+use std::path::Path;
+
+mod bwhc;
+mod cbioportal;
+mod columnar;
+mod diff;
+mod dnpm;
+mod export_record;
+mod exporter;
+mod fhir;
+mod files;
+mod hgnc;
+mod html;
+mod import;
+mod input;
+mod messages;
+mod mhguide;
+mod obds;
+mod phenopacket;
+mod profile;
+mod provenance;
+mod pseudonym;
+mod sqlite;
+mod template;
+mod vcf;
+
+pub use crate::diff::{Change, ChangeKind, Diff};
+pub use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, ExportRecord, FieldValue, FusionRecord, MafRecord,
+    MafRecords, Records, SimpleVariantRecord, SkippedVariant, ValueFormat,
+};
+pub use crate::exporter::{
+    Conversion, ExportOptions, Exporter, OutputTarget, TemplateExporter, exporter, exporters,
+};
+pub use crate::files::{CsvDialect, Encoding, LineEnding, QuoteStyle};
+pub use crate::input::InputSource;
+pub use crate::messages::Language;
+pub use crate::mhguide::{Fusion, General, MhGuide, PatientIdentifier, RefGenomeVersion, Variant};
+pub use crate::profile::Profile;
+pub use crate::provenance::Provenance;
+pub use crate::pseudonym::{KEY_VARIABLE, Pseudonymizer};
+pub use crate::vcf::Vcf;
+
+/// Selection of the variants to be exported
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum VariantSelection {
+    /// Variants shown in the report
+    #[default]
+    Relevant,
+    /// Variants shown in the report without variants classified as artifact
+    RelevantWithoutArtifacts,
+    /// Variants classified as oncogenic or likely oncogenic
+    Oncogenic,
+    /// All variants contained in the file
+    All,
+}
+
+/// Reads the content of an MH Guide JSON file or a ZIP compressed JSON file.
+///
+/// # Errors
+///
+/// Returns an error if the content is neither a valid MH Guide JSON file nor a ZIP file
+/// containing one.
+pub fn read(content: &[u8]) -> Result<MhGuide, Box<dyn std::error::Error>> {
+    files::read_content(content)
+}
+
+/// Reads an MH Guide JSON file or a ZIP compressed JSON file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, does not have the extension `json` or `zip` or
+/// does not contain a valid MH Guide file.
+pub fn read_file(path: &Path) -> Result<MhGuide, Box<dyn std::error::Error>> {
+    files::read_file(path)
+}
+
+/// Reads the content of an annotated, optionally gzip compressed, VCF file or else of an
+/// MH Guide JSON or ZIP file.
+///
+/// # Errors
+///
+/// Returns an error if the content is neither a valid VCF file nor a valid MH Guide file.
+pub fn read_source(content: &[u8]) -> Result<Box<dyn InputSource>, Box<dyn std::error::Error>> {
+    if content.starts_with(b"##fileformat=VCF") || vcf::is_gzip(content) {
+        return Ok(Box::new(Vcf::read(content)?));
+    }
+    Ok(Box::new(read(content)?))
+}
+
+/// Reads an annotated VCF file with the extension `vcf` or `vcf.gz` or else an MH Guide JSON or
+/// ZIP file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or does not contain a valid VCF or MH Guide file.
+pub fn read_source_file(path: &Path) -> Result<Box<dyn InputSource>, Box<dyn std::error::Error>> {
+    if is_vcf_file(path) {
+        return Ok(Box::new(Vcf::read(&std::fs::read(path)?)?));
+    }
+    Ok(Box::new(read_file(path)?))
+}
+
+/// Returns true if the file has the extension `vcf` or `vcf.gz`
+#[must_use]
+pub fn is_vcf_file(path: &Path) -> bool {
+    let has_extension = |path: &Path, expected: &str| {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(expected))
+    };
+    has_extension(path, "vcf")
+        || (has_extension(path, "gz") && has_extension(&path.with_extension(""), "vcf"))
+}
+
+/// Returns the selected variants of the input source
+#[must_use]
+pub fn variants(source: &dyn InputSource, selection: VariantSelection) -> Vec<&Variant> {
+    source.variants(selection)
+}
+
+/// Returns the simple variants (SNV, indel) with a gene symbol
+#[must_use]
+pub fn simple_variants<'a>(variants: &[&'a Variant]) -> Vec<&'a Variant> {
+    variants
+        .par_iter()
+        .filter(|variant| variant.gene_symbol.is_some())
+        .filter(|variant| match &variant.display_variant_type {
+            Some(ResultType::SimpleVariant(_)) => true,
+            Some(_) => false,
+            None => matches!(
+                &variant.protein_variant_type,
+                Some(ResultType::SimpleVariant(_))
+            ),
+        })
+        .copied()
+        .collect::<Vec<_>>()
+}
+
+/// Returns the copy number variants with a gene symbol
+#[must_use]
+pub fn copy_number_variants<'a>(variants: &[&'a Variant]) -> Vec<&'a Variant> {
+    variants
+        .par_iter()
+        .filter(|variant| variant.gene_symbol.is_some())
+        .filter(|variant| match &variant.display_variant_type {
+            Some(ResultType::CopyNumberVariant) => true,
+            Some(_) => false,
+            None => matches!(
+                &variant.protein_variant_type,
+                Some(ResultType::CopyNumberVariant)
+            ),
+        })
+        .copied()
+        .collect::<Vec<_>>()
+}
+
+/// Builds the records of all selected variants, fusions and biomarkers of the input source
+#[must_use]
+pub fn records(source: &dyn InputSource, selection: VariantSelection) -> Records {
+    let variants = variants(source, selection);
+    let h_number = &source.general().patient_identifier.h_number;
+    let ref_genome_version = &source.general().ref_genome_version;
+
+    let simple_variant_records = simple_variants(&variants)
+        .par_iter()
+        .map(|variant| SimpleVariantRecord::from_variant(h_number, ref_genome_version, variant))
+        .collect::<Vec<_>>();
+
+    let copy_number_records = copy_number_variants(&variants)
+        .par_iter()
+        .map(|variant| CopyNumberRecord::from_variant(h_number, ref_genome_version, variant))
+        .collect::<Vec<_>>();
+
+    let fusion_records = source
+        .fusions()
+        .par_iter()
+        .map(|fusion| FusionRecord::from_fusion(h_number, ref_genome_version, fusion))
+        .collect::<Vec<_>>();
+
+    let mut biomarker_records = vec![];
+    if let Some(value) = source.hrd_score() {
+        biomarker_records.push(BiomarkerRecord::from_hrd(
+            h_number,
+            ref_genome_version,
+            value,
+        ));
+    }
+    if let Some(value) = source.msi_score() {
+        biomarker_records.push(BiomarkerRecord::from_msi(
+            h_number,
+            ref_genome_version,
+            value,
+        ));
+    }
+    if let Some(value) = source.tmb_value() {
+        biomarker_records.push(BiomarkerRecord::from_tmb(
+            h_number,
+            ref_genome_version,
+            value,
+        ));
+    }
+
+    Records {
+        simple_variants: simple_variant_records,
+        copy_numbers: copy_number_records,
+        fusions: fusion_records,
+        biomarkers: biomarker_records,
+        provenance: Some(Provenance::new(source.general(), selection)),
+    }
+}
+
+/// Builds the MAF records of all selected simple variants of the input source.
+///
+/// Variants without alleles in their DNA change, e.g. deletions without the deleted bases,
+/// are skipped and returned with the reason.
+#[must_use]
+pub fn maf_records(source: &dyn InputSource, selection: VariantSelection) -> MafRecords {
+    let variants = variants(source, selection);
+    export_record::maf_records(
+        &source.general().patient_identifier.h_number,
+        &source.general().ref_genome_version,
+        &simple_variants(&variants),
+    )
+}
+
+/// Reads the records of a CSV file with one section for each record type, e.g. a file written
+/// by [`csv`] and edited afterwards. Dialect and profile must match the written file.
+///
+/// # Errors
+///
+/// Returns an error if a section header does not match the columns of a record type, a value
+/// is invalid or the records belong to different H-numbers.
+pub fn import_csv(
+    content: &[u8],
+    dialect: &CsvDialect,
+    profile: &Profile,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let mut records = import::csv_records(content, dialect, profile)?;
+    records.provenance = Some(Provenance::from_content(content));
+    Ok(records)
+}
+
+/// Reads the records of an XLSX file with one worksheet for each record type, e.g. a file
+/// written by [`xlsx`] and edited afterwards. Language and profile must match the written file.
+///
+/// # Errors
+///
+/// Returns an error if the workbook cannot be read, a header row does not match the columns of
+/// a record type, a value is invalid or the records belong to different H-numbers.
+pub fn import_xlsx(
+    content: &[u8],
+    language: Language,
+    profile: &Profile,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let mut records = import::xlsx_records(content, language, profile)?;
+    records.provenance = Some(Provenance::from_content(content));
+    Ok(records)
+}
+
+/// Reads the records of a DNPM MTB file or of NGS report results as written by [`dnpm_json`].
+///
+/// Simple and copy number variants, DNA and RNA fusions, TMB and HRD score are read. The ID of
+/// the patient in an MTB file is used as H-number, otherwise the given H-number is used. The
+/// reference genome of NGS report results is read from the metadata written by
+/// [`dnpm_metadata_json`], if given.
+///
+/// # Errors
+///
+/// Returns an error if the content or the metadata is not valid DNPM JSON.
+pub fn read_dnpm_json(
+    content: &[u8],
+    metadata: Option<&[u8]>,
+    h_number: &str,
+) -> Result<Records, Box<dyn std::error::Error>> {
+    let mut records = dnpm::dnpm_records(content, metadata, h_number)?;
+    records.provenance = Some(Provenance::from_content(content));
+    Ok(records)
+}
+
+/// Compares the selected variants, fusions and biomarkers of an older and a newer file of the
+/// same case.
+///
+/// Variants are matched by their `DETECTED_VAR_ID` or, e.g. for VCF files, by gene and HGVS
+/// nomenclature. Values of changed fields are formatted with the value format.
+///
+/// # Errors
+///
+/// Returns an error if the files belong to different cases, i.e. have different H-numbers.
+pub fn diff(
+    old: &dyn InputSource,
+    new: &dyn InputSource,
+    selection: VariantSelection,
+    value_format: ValueFormat,
+) -> Result<Diff, Box<dyn std::error::Error>> {
+    diff::diff(old, new, selection, value_format)
+}
+
+/// Serializes the records into a CSV file with one section for each record type.
+///
+/// If enabled in the dialect, the provenance of the records is appended as comment lines starting
+/// with `#`.
+///
+/// # Errors
+///
+/// Returns an error if the profile contains unknown columns or the content cannot be encoded.
+pub fn csv(
+    records: &Records,
+    dialect: &CsvDialect,
+    profile: &Profile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    files::csv_content(
+        dialect,
+        profile,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+        records.provenance.as_ref(),
+    )
+}
+
+/// Serializes the records into a CSV file with one table for all record types.
+///
+/// If enabled in the dialect, the provenance of the records is appended as comment lines starting
+/// with `#`.
+///
+/// # Errors
+///
+/// Returns an error if the profile contains unknown columns or the content cannot be encoded.
+pub fn flat_csv(
+    records: &Records,
+    dialect: &CsvDialect,
+    profile: &Profile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    files::flat_csv_content(dialect, profile, records)
+}
+
+/// Serializes the records into an XLSX file with one worksheet for each record type.
+///
+/// The provenance of the records is written into the document properties and a metadata sheet.
+///
+/// # Errors
+///
+/// Returns an error if the profile contains unknown columns or the workbook cannot be written.
+pub fn xlsx(
+    records: &Records,
+    value_format: ValueFormat,
+    profile: &Profile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut workbook = files::xlsx_workbook(
+        profile,
+        value_format,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+        records.provenance.as_ref(),
+    )?;
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Serializes the records into an ODS file with one table for each record type.
+///
+/// The provenance of the records is written into the document metadata and a metadata table.
+///
+/// # Errors
+///
+/// Returns an error if the profile contains unknown columns or the document cannot be written.
+pub fn ods(
+    records: &Records,
+    value_format: ValueFormat,
+    profile: &Profile,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    files::ods_document(
+        profile,
+        value_format,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+        records.provenance.as_ref(),
+    )
+}
+
+/// Serializes the MAF records into a file in the Mutation Annotation Format.
+///
+/// The provenance is written as header comment lines after the version.
+///
+/// # Errors
+///
+/// Returns an error if the records cannot be serialized.
+pub fn maf(
+    maf_records: &[MafRecord],
+    provenance: Option<&Provenance>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    files::maf_content(maf_records, provenance)
+}
+
+/// Serializes the simple and copy number variants into DNPM JSON NGS report results.
+///
+/// # Errors
+///
+/// Returns an error if a record contains an unknown chromosome or cannot be serialized.
+pub fn dnpm_json(records: &Records) -> Result<String, Box<dyn std::error::Error>> {
+    files::dnpm_json_content(&records.simple_variants, &records.copy_numbers)
+}
+
+/// Serializes the metadata to be written next to the NGS report results of [`dnpm_json`]:
+/// the `metadata` of an NGS report with reference genome and pipeline and the `provenance` of
+/// the records. NGS report results have no field for either.
+///
+/// # Errors
+///
+/// Returns an error if the metadata cannot be serialized.
+pub fn dnpm_metadata_json(records: &Records) -> Result<String, Box<dyn std::error::Error>> {
+    files::dnpm_metadata_content(
+        &records.simple_variants,
+        &records.copy_numbers,
+        records.provenance.as_ref(),
+    )
+}
+
+/// Serializes the records into a bwHC MTB file (DNPM 1.x).
+///
+/// The provenance of the records is written into the `metadata` of the NGS report.
+///
+/// # Errors
+///
+/// Returns an error if the MTB file cannot be serialized.
+pub fn bwhc_json(
+    general: &General,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mtb_file = bwhc::mtb_file(
+        general,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+        records.provenance.as_ref(),
+    );
+    Ok(serde_json::to_string_pretty(&mtb_file)?)
+}
+
+/// Serializes the records into an HL7 FHIR R4 bundle of type `collection` or `transaction`.
+///
+/// The provenance of the records is written into a `Provenance` resource of the bundle.
+///
+/// # Errors
+///
+/// Returns an error if the bundle cannot be serialized.
+pub fn fhir_json(
+    patient_identifier: &PatientIdentifier,
+    records: &Records,
+    transaction: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let bundle = fhir::bundle(
+        patient_identifier,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+        records.provenance.as_ref(),
+        transaction,
+    );
+    Ok(serde_json::to_string_pretty(&bundle)?)
+}
+
+/// Serializes the simple and copy number variants into a GA4GH Phenopacket.
+///
+/// The provenance of the records is written into the `metaData` of the phenopacket.
+///
+/// # Errors
+///
+/// Returns an error if the phenopacket cannot be serialized.
+pub fn phenopacket_json(
+    general: &General,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let phenopacket = phenopacket::phenopacket(
+        general,
+        &records.simple_variants,
+        &records.copy_numbers,
+        records.provenance.as_ref(),
+    );
+    Ok(serde_json::to_string_pretty(&phenopacket)?)
+}
+
+/// Serializes the records into oBDS XML elements for molecular genetic findings.
+///
+/// # Errors
+///
+/// Returns an error if the XML cannot be written.
+pub fn obds_xml(
+    patient_identifier: &PatientIdentifier,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(obds::obds_xml(
+        patient_identifier,
+        &records.simple_variants,
+        &records.copy_numbers,
+        &records.fusions,
+        &records.biomarkers,
+    )?)
+}
+
+/// Creates a standalone HTML report of the case for review before the tumor board.
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be written.
+pub fn html(
+    source: &dyn InputSource,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    html::html_report(source, records)
+}
+
+/// Renders the Tera template with the general data, records and report narrative of the case.
+///
+/// # Errors
+///
+/// Returns an error if the template is invalid or cannot be rendered.
+pub fn render_template(
+    template: &str,
+    source: &dyn InputSource,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    template::render(template, source, records)
+}
+
+/// Returns the extension of files rendered with the template file,
+/// e.g. `befund.md` for `befund.md.tera` or `befund.md`.
+#[must_use]
+pub fn template_extension(template_path: &Path) -> String {
+    template::output_extension(template_path)
+}
+
+/// Builds the conversions of all input sources for exporters writing multiple cases
+fn conversions<'a>(
+    sources: &[&'a dyn InputSource],
+    selection: VariantSelection,
+) -> Vec<Conversion<'a>> {
+    sources
+        .iter()
+        .map(|source| Conversion::new(*source, selection))
+        .collect()
+}
+
+/// Writes a cBioPortal study with the selected variants and the fusions of all input sources
+/// into the directory. Input sources of the same sample are merged into one sample.
+///
+/// # Errors
+///
+/// Returns an error if the directory or a file of the study cannot be written.
+pub fn cbioportal_study(
+    path: &Path,
+    sources: &[&dyn InputSource],
+    selection: VariantSelection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    exporter::CbioportalExporter
+        .export_all(
+            &conversions(sources, selection),
+            &ExportOptions::default(),
+            path,
+        )
+        .map(|_| ())
+}
+
+/// Writes the records of all input sources into one Parquet file for each record type
+/// into the directory.
+///
+/// # Errors
+///
+/// Returns an error if a Parquet file cannot be written.
+pub fn parquet_files(
+    path: &Path,
+    sources: &[&dyn InputSource],
+    selection: VariantSelection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    exporter::ParquetExporter
+        .export_all(
+            &conversions(sources, selection),
+            &ExportOptions::default(),
+            path,
+        )
+        .map(|_| ())
+}
+
+/// Inserts or replaces the cases of all input sources in the database file, which is created
+/// if it does not exist.
+///
+/// Returns for each input source, if the case was written. A case is not written, if the
+/// database already contains a newer report version.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be opened or a case cannot be written.
+pub fn sqlite_upsert(
+    path: &Path,
+    sources: &[&dyn InputSource],
+    selection: VariantSelection,
+) -> Result<Vec<bool>, Box<dyn std::error::Error>> {
+    exporter::SqliteExporter.export_all(
+        &conversions(sources, selection),
+        &ExportOptions::default(),
+        path,
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::{CsvDialect, Profile, VariantSelection, csv, read, records, variants};
+
+    #[test]
+    fn test_should_convert_content_into_csv() {
+        let content = std::fs::read("./testfiles/cnv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+
+        assert!(
+            variants(&mhguide, VariantSelection::All).len()
+                >= variants(&mhguide, VariantSelection::Relevant).len()
+        );
+
+        let records = records(&mhguide, VariantSelection::All);
+        assert_eq!(records.copy_numbers.len(), 1);
+
+        let actual = csv(&records, &CsvDialect::default(), &Profile::default()).unwrap();
+        assert!(String::from_utf8(actual).unwrap().contains(";BRAF;chr1;"));
+    }
+}
@@ -1,127 +1,432 @@
-use crate::export_record::{BiomarkerRecord, CopyNumberRecord, FusionRecord, SimpleVariantRecord};
-use crate::files::read_file;
-use crate::mhguide::ResultType;
 use clap::Parser;
-use rayon::prelude::*;
+use mhguide_to_csv::{
+    Conversion, CsvDialect, ExportOptions, Exporter, InputSource, OutputTarget, Profile,
+    Pseudonymizer, Records, TemplateExporter, ValueFormat, VariantSelection, is_vcf_file,
+    read_source_file,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod cli;
-mod export_record;
-mod files;
-mod hgnc;
-mod mhguide;
+mod serve;
+mod watch;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = cli::Cli::parse();
-    let mhguide = read_file(&cli.input_file)?;
+    if let Err(err) = cli.validate() {
+        err.exit();
+    }
+
+    if let Some(cli::Command::Watch {
+        directory,
+        interval,
+    }) = &cli.command
+    {
+        // Outputs of these formats are written once for all input files and not next to them
+        if cli.template.is_none()
+            && let Some(exporter) = mhguide_to_csv::exporter(cli.format_name())
+            && exporter.target() != OutputTarget::File
+        {
+            return Err(format!(
+                "Format {} cannot be used to watch a directory",
+                exporter.name()
+            )
+            .into());
+        }
+        return watch::watch_directory(directory, Duration::from_secs(*interval), |input_file| {
+            export_file(&cli, input_file)
+        });
+    }
+
+    if let Some(cli::Command::Serve { address, max_size }) = &cli.command {
+        let max_size = max_size
+            .checked_mul(1024 * 1024)
+            .ok_or("Maximum size is too large")?;
+        return serve::serve(address, max_size, |source, exporter| {
+            convert_content(&cli, pseudonymize(&cli, source)?.as_ref(), exporter)
+        });
+    }
+
+    if let Some(cli::Command::Import { input_files }) = &cli.command {
+        for input_file in input_files {
+            import_file(&cli, input_file)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(cli::Command::FromDnpm { input_files }) = &cli.command {
+        for input_file in input_files {
+            convert_dnpm_file(&cli, input_file)?;
+        }
+        return Ok(());
+    }
 
-    let variants = if cli.all_variants {
-        mhguide.all_variants()
+    if let Some(cli::Command::Diff {
+        old_file,
+        new_file,
+        output,
+    }) = &cli.command
+    {
+        return diff_files(&cli, old_file, new_file, *output);
+    }
+
+    if cli.template.is_none()
+        && let Some(exporter) = mhguide_to_csv::exporter(cli.format_name())
+        && exporter.target() != OutputTarget::File
+    {
+        return export_all(&cli, exporter);
+    }
+
+    for input_file in &cli.input_files {
+        export_file(&cli, input_file)?;
+    }
+
+    Ok(())
+}
+
+fn variant_selection(cli: &cli::Cli) -> VariantSelection {
+    if cli.all_variants {
+        VariantSelection::All
     } else if cli.oncogenic {
-        mhguide.oncogenic_variants()
+        VariantSelection::Oncogenic
+    } else if cli.no_artifacts {
+        VariantSelection::RelevantWithoutArtifacts
     } else {
-        mhguide.relevant_variants(cli.no_artifacts)
+        VariantSelection::Relevant
+    }
+}
+
+/// Returns the selected pseudonymizer, the key is read from the environment if no key file
+/// or mapping file is given
+fn pseudonymizer(cli: &cli::Cli) -> Result<Option<Pseudonymizer>, Box<dyn std::error::Error>> {
+    if !cli.pseudonymize {
+        return Ok(None);
+    }
+    let pseudonymizer = match (&cli.pseudonym_mapping, &cli.pseudonym_key_file) {
+        (Some(mapping_path), _) => Pseudonymizer::read_mapping(mapping_path)?,
+        (None, Some(key_path)) => Pseudonymizer::read_key_file(key_path)?,
+        (None, None) => Pseudonymizer::from_env()?,
     };
+    Ok(Some(pseudonymizer))
+}
 
-    let simple_variant_records = variants
-        .par_iter()
-        .filter(|variant| variant.gene_symbol.is_some())
-        .filter(|variant| match &variant.display_variant_type {
-            Some(ResultType::SimpleVariant(_)) => true,
-            Some(_) => false,
-            None => matches!(
-                &variant.protein_variant_type,
-                Some(ResultType::SimpleVariant(_))
-            ),
-        })
-        .map(|variant| {
-            SimpleVariantRecord::from_variant(
-                &mhguide.general.patient_identifier.h_number,
-                &mhguide.general.ref_genome_version,
-                variant,
-            )
-        })
-        .collect::<Vec<_>>();
+fn pseudonymize(
+    cli: &cli::Cli,
+    source: Box<dyn InputSource>,
+) -> Result<Box<dyn InputSource>, Box<dyn std::error::Error>> {
+    match pseudonymizer(cli)? {
+        Some(pseudonymizer) => pseudonymizer.pseudonymize(source),
+        None => Ok(source),
+    }
+}
 
-    let copy_number_records = variants
-        .par_iter()
-        .filter(|variant| variant.gene_symbol.is_some())
-        .filter(|variant| match &variant.display_variant_type {
-            Some(ResultType::CopyNumberVariant) => true,
-            Some(_) => false,
-            None => matches!(
-                &variant.protein_variant_type,
-                Some(ResultType::CopyNumberVariant)
-            ),
-        })
-        .map(|variant| {
-            CopyNumberRecord::from_variant(
-                &mhguide.general.patient_identifier.h_number,
-                &mhguide.general.ref_genome_version,
-                variant,
-            )
-        })
-        .collect::<Vec<_>>();
+fn read_input(
+    cli: &cli::Cli,
+    input_file: &Path,
+) -> Result<Box<dyn InputSource>, Box<dyn std::error::Error>> {
+    pseudonymize(cli, read_source_file(input_file)?)
+}
 
-    let fusion_records = mhguide
-        .fusions()
-        .par_iter()
-        .map(|fusion| {
-            FusionRecord::from_fusion(
-                &mhguide.general.patient_identifier.h_number,
-                &mhguide.general.ref_genome_version,
-                fusion,
-            )
-        })
-        .collect::<Vec<_>>();
+fn export_options(cli: &cli::Cli) -> Result<ExportOptions, Box<dyn std::error::Error>> {
+    let dialect = CsvDialect::new(
+        cli.delimiter,
+        cli.quote_style,
+        cli.decimal_separator,
+        cli.lang,
+        cli.line_ending,
+        cli.encoding,
+        cli.csv_provenance,
+    )?;
+    Ok(ExportOptions {
+        profile: match &cli.profile {
+            Some(profile_path) => Profile::read(profile_path)?,
+            None => Profile::default(),
+        },
+        // Same language and decimal separator for CSV, ODS, HTML and template output
+        value_format: dialect.value_format(),
+        dialect,
+        flat: cli.flat,
+        fhir_transaction: cli.fhir_transaction,
+    })
+}
 
-    let mut biomarker_records = vec![];
-    if let Some(value) = mhguide.hrd_score() {
-        biomarker_records.push(BiomarkerRecord::from_hrd(
-            &mhguide.general.patient_identifier.h_number,
-            &mhguide.general.ref_genome_version,
-            value,
-        ));
-    }
-    if let Some(value) = mhguide.msi_score() {
-        biomarker_records.push(BiomarkerRecord::from_msi(
-            &mhguide.general.patient_identifier.h_number,
-            &mhguide.general.ref_genome_version,
-            value,
-        ));
-    }
-    if let Some(value) = mhguide.tmb_value() {
-        biomarker_records.push(BiomarkerRecord::from_tmb(
-            &mhguide.general.patient_identifier.h_number,
-            &mhguide.general.ref_genome_version,
-            value,
-        ));
-    }
-
-    if cli.xlsx {
-        return files::write_xlsx_file(
-            &cli.input_file,
-            &simple_variant_records,
-            &copy_number_records,
-            &fusion_records,
-            &biomarker_records,
+fn convert_content(
+    cli: &cli::Cli,
+    source: &dyn InputSource,
+    exporter: &dyn Exporter,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let conversion = Conversion::new(source, variant_selection(cli));
+    let mut content = vec![];
+    exporter.export(&conversion, &export_options(cli)?, &mut content)?;
+    report_skipped_variants(&conversion);
+    Ok(content)
+}
+
+/// Reports simple variants an exporter could not write, e.g. MAF records without alleles
+fn report_skipped_variants(conversion: &Conversion) {
+    for skipped in conversion.skipped_variants() {
+        eprintln!(
+            "{}: Variante {} {} wird im MAF-Export übersprungen: {}",
+            conversion.source.general().patient_identifier.h_number,
+            skipped.gene,
+            skipped.dna_change,
+            skipped.error
         );
     }
+}
+
+fn export_file(cli: &cli::Cli, input_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let source = read_input(cli, input_file)?;
+
+    let template_exporter;
+    let exporter: &dyn Exporter = match &cli.template {
+        Some(template_path) => {
+            template_exporter = TemplateExporter::read(template_path)?;
+            &template_exporter
+        }
+        None => mhguide_to_csv::exporter(cli.format_name())
+            .ok_or_else(|| format!("Unknown format '{}'", cli.format_name()))?,
+    };
+
+    let content = convert_content(cli, source.as_ref(), exporter)?;
+
+    // The input file name may contain the H-number
+    let output_file = if cli.pseudonymize {
+        let general = source.general();
+        pseudonymized_file(
+            input_file,
+            &general.patient_identifier.h_number,
+            general.report_version,
+            exporter.extension(),
+        )?
+    } else {
+        let mut output_file = input_file.to_path_buf();
+        if is_vcf_file(&output_file)
+            && output_file
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+        {
+            // Replaces `vcf.gz` as a whole
+            output_file.set_extension("");
+        }
+        output_file.set_extension(exporter.extension());
+        output_file
+    };
+    if output_file == input_file {
+        return Err(format!(
+            "Output file '{}' would replace the input file",
+            output_file.display()
+        )
+        .into());
+    }
+    if exporter.name() == "json" {
+        let records = mhguide_to_csv::records(source.as_ref(), variant_selection(cli));
+        fs::write(
+            dnpm_metadata_file(&output_file),
+            mhguide_to_csv::dnpm_metadata_json(&records)?,
+        )?;
+    }
+    fs::write(output_file, content).map_err(Into::into)
+}
 
-    if cli.json {
-        return files::write_json_file(
-            &cli.input_file,
-            &simple_variant_records,
-            &copy_number_records,
-            &fusion_records,
-            &biomarker_records,
+/// Returns the output file next to the input file named after the pseudonym and, if known, the
+/// report version, e.g. `P1234-v2.csv`.
+///
+/// Existing files are not replaced, instead a counter is appended, e.g. `P1234-v2-2.csv`, so
+/// outputs of other reports of the same patient are kept.
+fn pseudonymized_file(
+    input_file: &Path,
+    pseudonym: &str,
+    report_version: Option<u32>,
+    extension: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if pseudonym.is_empty() || pseudonym.contains(['/', '\\']) {
+        return Err(format!("Pseudonym '{pseudonym}' cannot be used as file name").into());
+    }
+    let stem = match report_version {
+        Some(report_version) => format!("{pseudonym}-v{report_version}"),
+        None => pseudonym.to_string(),
+    };
+    let mut output_file = input_file.with_file_name(format!("{stem}.{extension}"));
+    let mut counter = 1;
+    while output_file.exists() {
+        counter += 1;
+        output_file = input_file.with_file_name(format!("{stem}-{counter}.{extension}"));
+    }
+    Ok(output_file)
+}
+
+/// Returns the file with the metadata next to DNPM JSON results, e.g. `x.dnpm.metadata.json`
+fn dnpm_metadata_file(dnpm_file: &Path) -> PathBuf {
+    dnpm_file.with_extension("metadata.json")
+}
+
+/// Writes all input files into the directory or database of the exporter
+fn export_all(cli: &cli::Cli, exporter: &dyn Exporter) -> Result<(), Box<dyn std::error::Error>> {
+    let path = cli.output_path().ok_or_else(|| {
+        format!(
+            "Format '{}' requires an output path, e.g. --{} <PATH>",
+            exporter.name(),
+            exporter.name()
+        )
+    })?;
+    let sources = cli
+        .input_files
+        .iter()
+        .map(|input_file| read_input(cli, input_file))
+        .collect::<Result<Vec<_>, _>>()?;
+    let conversions = sources
+        .iter()
+        .map(|source| Conversion::new(source.as_ref(), variant_selection(cli)))
+        .collect::<Vec<_>>();
+
+    let written = exporter.export_all(&conversions, &export_options(cli)?, path)?;
+    conversions.iter().for_each(report_skipped_variants);
+    for (input_file, _) in cli
+        .input_files
+        .iter()
+        .zip(written)
+        .filter(|(_, written)| !written)
+    {
+        eprintln!(
+            "{}: Neuere Befundversion bereits vorhanden, Datei wird übersprungen",
+            input_file.display()
         );
     }
+    Ok(())
+}
+
+fn import_file(cli: &cli::Cli, input_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let content = fs::read(input_file)?;
+    let options = export_options(cli)?;
+    let mut records = if input_file
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xlsx"))
+    {
+        mhguide_to_csv::import_xlsx(&content, cli.lang, &options.profile)?
+    } else {
+        mhguide_to_csv::import_csv(&content, &options.dialect, &options.profile)?
+    };
+    // The input file name may contain the H-number
+    let output_file = if let Some(pseudonymizer) = pseudonymizer(cli)? {
+        pseudonymizer.pseudonymize_records(&mut records)?;
+        pseudonymized_file(input_file, h_number(&records)?, None, "dnpm.json")?
+    } else {
+        input_file.with_extension("dnpm.json")
+    };
+    fs::write(
+        dnpm_metadata_file(&output_file),
+        mhguide_to_csv::dnpm_metadata_json(&records)?,
+    )?;
+    fs::write(output_file, mhguide_to_csv::dnpm_json(&records)?).map_err(Into::into)
+}
+
+/// Returns the H-number of the records, e.g. to name output files after the pseudonym
+fn h_number(records: &Records) -> Result<&str, Box<dyn std::error::Error>> {
+    records
+        .simple_variants
+        .iter()
+        .map(|record| record.h_nummer.as_str())
+        .chain(
+            records
+                .copy_numbers
+                .iter()
+                .map(|record| record.h_nummer.as_str()),
+        )
+        .chain(
+            records
+                .fusions
+                .iter()
+                .map(|record| record.h_nummer.as_str()),
+        )
+        .chain(
+            records
+                .biomarkers
+                .iter()
+                .map(|record| record.h_nummer.as_str()),
+        )
+        .next()
+        .ok_or_else(|| "No records to name the pseudonymized output file".into())
+}
+
+fn convert_dnpm_file(cli: &cli::Cli, input_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let file_name = input_file
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or("Invalid file name")?;
+    let h_number = file_name
+        .strip_suffix(".dnpm.json")
+        .or_else(|| file_name.strip_suffix(".json"))
+        .unwrap_or(file_name);
+    let metadata_file = dnpm_metadata_file(input_file);
+    let metadata = if metadata_file.exists() {
+        Some(fs::read(metadata_file)?)
+    } else {
+        None
+    };
+    let mut records =
+        mhguide_to_csv::read_dnpm_json(&fs::read(input_file)?, metadata.as_deref(), h_number)?;
+    let pseudonymizer = pseudonymizer(cli)?;
+    if let Some(pseudonymizer) = &pseudonymizer {
+        pseudonymizer.pseudonymize_records(&mut records)?;
+    }
+
+    let options = export_options(cli)?;
+    let (content, extension) = match cli.format_name() {
+        "csv" if options.flat => (
+            mhguide_to_csv::flat_csv(&records, &options.dialect, &options.profile)?,
+            "csv",
+        ),
+        "csv" => (
+            mhguide_to_csv::csv(&records, &options.dialect, &options.profile)?,
+            "csv",
+        ),
+        "xlsx" => (
+            mhguide_to_csv::xlsx(&records, options.value_format, &options.profile)?,
+            "xlsx",
+        ),
+        "ods" => (
+            mhguide_to_csv::ods(&records, options.value_format, &options.profile)?,
+            "ods",
+        ),
+        format => return Err(format!("Format '{format}' is not supported for DNPM JSON").into()),
+    };
 
-    files::write_csv_file(
-        &cli.input_file,
-        &simple_variant_records,
-        &copy_number_records,
-        &fusion_records,
-        &biomarker_records,
-    )
+    // Distinct suffix to not replace the CSV file the DNPM JSON may have been imported from
+    let extension = format!("from-dnpm.{extension}");
+    let output_file = if pseudonymizer.is_some() {
+        pseudonymized_file(input_file, self::h_number(&records)?, None, &extension)?
+    } else {
+        input_file.with_file_name(format!("{h_number}.{extension}"))
+    };
+    fs::write(output_file, content).map_err(Into::into)
+}
+
+fn diff_files(
+    cli: &cli::Cli,
+    old_file: &Path,
+    new_file: &Path,
+    output: cli::DiffFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let old = read_input(cli, old_file)?;
+    let new = read_input(cli, new_file)?;
+    let decimal_separator = match output {
+        cli::DiffFormat::Table => cli.decimal_separator,
+        cli::DiffFormat::Json => '.',
+    };
+    let diff = mhguide_to_csv::diff(
+        old.as_ref(),
+        new.as_ref(),
+        variant_selection(cli),
+        ValueFormat {
+            decimal_separator,
+            language: cli.lang,
+        },
+    )?;
+    match output {
+        cli::DiffFormat::Table => print!("{}", diff.table(cli.lang)),
+        cli::DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+    Ok(())
 }
@@ -0,0 +1,143 @@
+use clap::ValueEnum;
+
+/// Language of headlines, labels and sheet names in written output
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Language {
+    /// Deutsch
+    De,
+    /// Englisch
+    En,
+}
+
+/// English translations of German column headlines, result types and sheet names.
+///
+/// Texts without an entry, e.g. headlines like "HGNC ID" or the CNV types "loss",
+/// "low level gain" and "high level gain", are the same in both languages.
+const ENGLISH: &[(&str, &str)] = &[
+    // Sheet names
+    ("Einfache Varianten", "Simple Variants"),
+    ("Copy Number Varianten", "Copy Number Variants"),
+    ("Fusionen", "Fusions"),
+    ("Biomarker", "Biomarkers"),
+    ("Metadaten", "Metadata"),
+    // Column headlines
+    ("H-Nummer", "H-Number"),
+    ("Referenz-Genom", "Reference Genome"),
+    ("Ergebnis", "Result"),
+    ("Gen", "Gene"),
+    ("Genomposition (g.)", "Genomic Position (g.)"),
+    ("cDNA Nomenklatur (c.)", "cDNA Nomenclature (c.)"),
+    ("Proteinebene (original)", "Protein Level (original)"),
+    (
+        "Proteinebene Nomenklatur (p.)",
+        "Protein Level Nomenclature (p.)",
+    ),
+    ("Chromosom", "Chromosome"),
+    ("Ende", "End"),
+    ("Allelfrequenz (%)", "Allelic Frequency (%)"),
+    ("Pathogenitätsklasse", "Pathogenicity Class"),
+    ("Fusioniertes Gen", "Fused Gene"),
+    ("HRD - Score/Ergebnis", "HRD - Score/Result"),
+    ("MSI - Prozentwert", "MSI - Percentage"),
+    // Metadata sheet
+    ("Eigenschaft", "Property"),
+    ("Wert", "Value"),
+    // Diff tables
+    ("Änderung", "Change"),
+    ("Datensatz", "Record"),
+    ("Variante", "Variant"),
+    ("Feld", "Field"),
+    ("Alter Wert", "Old Value"),
+    ("Neuer Wert", "New Value"),
+    ("hinzugefügt", "added"),
+    ("entfernt", "removed"),
+    ("geändert", "changed"),
+    // Result types
+    ("Einfache Variante", "Simple Variant"),
+    ("Anderes Ergebnis", "Other Result"),
+    // Result type details
+    ("nicht angegeben", "not specified"),
+];
+
+/// Translates the German text into the language.
+///
+/// Result types with details in parentheses, e.g. "Einfache Variante (SNV)", are translated
+/// by the text before the details. Details are translated only if there is an entry,
+/// e.g. for "Anderes Ergebnis (nicht angegeben)".
+pub(crate) fn translate(language: Language, text: &str) -> String {
+    match language {
+        Language::De => text.to_string(),
+        Language::En => replace_text(text, |text| {
+            ENGLISH
+                .iter()
+                .find(|(german, _)| *german == text)
+                .map(|(_, translation)| *translation)
+        }),
+    }
+}
+
+/// Translates the text of the language back into German, e.g. for imported files
+pub(crate) fn untranslate(language: Language, text: &str) -> String {
+    match language {
+        Language::De => text.to_string(),
+        Language::En => replace_text(text, |text| {
+            ENGLISH
+                .iter()
+                .find(|(_, translation)| *translation == text)
+                .map(|(german, _)| *german)
+        }),
+    }
+}
+
+fn replace_text(text: &str, lookup: impl Fn(&str) -> Option<&'static str>) -> String {
+    if let Some(replacement) = lookup(text) {
+        return replacement.to_string();
+    }
+    if let Some((label, details)) = text.split_once(" (")
+        && let Some(replacement) = lookup(label)
+    {
+        let details = details
+            .strip_suffix(')')
+            .and_then(&lookup)
+            .map_or_else(|| details.to_string(), |details| format!("{details})"));
+        return format!("{replacement} ({details}");
+    }
+    text.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::messages::{Language, translate, untranslate};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Language::De, "Einfache Variante (SNV)", "Einfache Variante (SNV)")]
+    #[case(Language::En, "Einfache Variante (SNV)", "Simple Variant (SNV)")]
+    #[case(Language::En, "Anderes Ergebnis (XYZ)", "Other Result (XYZ)")]
+    #[case(
+        Language::En,
+        "Anderes Ergebnis (nicht angegeben)",
+        "Other Result (not specified)"
+    )]
+    #[case(Language::En, "Copy Number Variation", "Copy Number Variation")]
+    #[case(Language::En, "Pathogenitätsklasse", "Pathogenicity Class")]
+    #[case(Language::En, "Einfache Varianten", "Simple Variants")]
+    #[case(Language::En, "low level gain", "low level gain")]
+    fn test_should_translate(
+        #[case] language: Language,
+        #[case] text: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(translate(language, text), expected);
+    }
+
+    #[rstest]
+    #[case(Language::De, "Einfache Variante (SNV)")]
+    #[case(Language::En, "Einfache Variante (SNV)")]
+    #[case(Language::En, "Pathogenitätsklasse")]
+    #[case(Language::En, "Anderes Ergebnis (nicht angegeben)")]
+    #[case(Language::En, "high level gain")]
+    fn test_should_untranslate(#[case] language: Language, #[case] text: &str) {
+        assert_eq!(untranslate(language, &translate(language, text)), text);
+    }
+}
@@ -6,9 +6,9 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 #[derive(Debug, Deserialize, PartialEq)]
-pub(crate) struct MhGuide {
+pub struct MhGuide {
     #[serde(rename = "GENERAL")]
-    pub(crate) general: General,
+    pub general: General,
     #[serde(rename = "VARIANT_LONG_LIST")]
     variants: Vec<Variant>,
     #[serde(rename = "BIOMARKERS")]
@@ -27,7 +27,7 @@ impl MhGuide {
     /// A `Vec` containing references to all the `Variant` instances in `self.variants`.
     ///
     /// # Example
-    /// ```rust
+    /// ```ignore
     /// let result = mh_guide.all_variants();
     /// for variant in result {
     ///     println!("{:?}", variant);
@@ -37,7 +37,7 @@ impl MhGuide {
         self.variants.par_iter().collect()
     }
 
-    /// ```rust
+    /// ```ignore
     /// Retrieves a list of relevant genetic variants based on oncogenic properties and matching criteria.
     ///
     /// This function collects the relevant variants for a report by:
@@ -52,7 +52,7 @@ impl MhGuide {
     /// A vector of references to relevant `Variant` objects.
     ///
     /// # Examples
-    /// ```rust
+    /// ```ignore
     /// let variants = mh_guide.relevant_variants();
     /// for variant in variants {
     ///     println!("{:?}", variant);
@@ -170,7 +170,7 @@ impl MhGuide {
         result
     }
 
-    /// ```rust
+    /// ```ignore
     /// Filters and retrieves all oncogenic variants
     ///
     /// This function searches through the `variants` field and identifies those
@@ -184,7 +184,7 @@ impl MhGuide {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let variants = my_struct.oncogenic_variants();
     ///
     /// for variant in variants {
@@ -214,7 +214,7 @@ impl MhGuide {
     ///   or its value cannot be parsed into an `f32`.
     ///
     /// ### Example
-    /// ```rust
+    /// ```ignore
     /// let tmb_value = some_instance.tmb_value();
     /// match tmb_value {
     ///     Some(value) => println!("TMB Value: {}", value),
@@ -252,7 +252,7 @@ impl MhGuide {
     ///   or its value cannot be parsed into an `f32`.
     ///
     /// ### Example
-    /// ```rust
+    /// ```ignore
     /// let hrd_score = some_instance.hrd_score();
     /// match hrd_score {
     ///     Some(value) => println!("HRD Score: {}", value),
@@ -276,7 +276,7 @@ impl MhGuide {
     ///   or its value cannot be parsed into an `f32`.
     ///
     /// ### Example
-    /// ```rust
+    /// ```ignore
     /// let msi_score = some_instance.msi_score();
     /// match msi_score {
     ///     Some(value) => println!("MSI Score: {}", value),
@@ -300,7 +300,7 @@ impl MhGuide {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let result = instance.fusions();
     /// for fusion in result {
     ///     println!("{:?}", fusion);
@@ -313,6 +313,10 @@ impl MhGuide {
             .collect::<Vec<_>>()
     }
 
+    pub(crate) fn report_narrative(&self) -> &str {
+        &self.report_narrative
+    }
+
     fn biomarker_score_value(&self, variant_type: &ResultType) -> Option<f32> {
         for notable_biomarker in &self.biomarkers.notable_biomarkers {
             for biomarker in &notable_biomarker.biomarkers {
@@ -417,7 +421,7 @@ impl MhGuide {
 }
 
 #[derive(Debug, PartialEq)]
-pub(crate) enum Fusion {
+pub enum Fusion {
     RnaFusion {
         partner_3: String,
         partner_5: String,
@@ -518,8 +522,8 @@ impl FromStr for Fusion {
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum RefGenomeVersion {
+#[derive(Clone, Debug, PartialEq)]
+pub enum RefGenomeVersion {
     Hg19,
     Hg38,
 }
@@ -549,10 +553,90 @@ impl Display for RefGenomeVersion {
     }
 }
 
+impl RefGenomeVersion {
+    /// Returns the NCBI build name of the reference genome, e.g. `GRCh37` for `HG19`.
+    pub(crate) fn ncbi_build(&self) -> &'static str {
+        match self {
+            RefGenomeVersion::Hg19 => "GRCh37",
+            RefGenomeVersion::Hg38 => "GRCh38",
+        }
+    }
+
+    /// Returns the NCBI reference sequence accession of the given chromosome (e.g. `chr1`)
+    /// in this reference genome.
+    pub(crate) fn refseq_chromosome_accession(&self, chromosome: &str) -> Option<&'static str> {
+        let accessions = match self {
+            RefGenomeVersion::Hg19 => [
+                "NC_000001.10",
+                "NC_000002.11",
+                "NC_000003.11",
+                "NC_000004.11",
+                "NC_000005.9",
+                "NC_000006.11",
+                "NC_000007.13",
+                "NC_000008.10",
+                "NC_000009.11",
+                "NC_000010.10",
+                "NC_000011.9",
+                "NC_000012.11",
+                "NC_000013.10",
+                "NC_000014.8",
+                "NC_000015.9",
+                "NC_000016.9",
+                "NC_000017.10",
+                "NC_000018.9",
+                "NC_000019.9",
+                "NC_000020.10",
+                "NC_000021.8",
+                "NC_000022.10",
+                "NC_000023.10",
+                "NC_000024.9",
+            ],
+            RefGenomeVersion::Hg38 => [
+                "NC_000001.11",
+                "NC_000002.12",
+                "NC_000003.12",
+                "NC_000004.12",
+                "NC_000005.10",
+                "NC_000006.12",
+                "NC_000007.14",
+                "NC_000008.11",
+                "NC_000009.12",
+                "NC_000010.11",
+                "NC_000011.10",
+                "NC_000012.12",
+                "NC_000013.11",
+                "NC_000014.9",
+                "NC_000015.10",
+                "NC_000016.10",
+                "NC_000017.11",
+                "NC_000018.10",
+                "NC_000019.10",
+                "NC_000020.11",
+                "NC_000021.9",
+                "NC_000022.11",
+                "NC_000023.11",
+                "NC_000024.10",
+            ],
+        };
+
+        let index = match chromosome.trim_start_matches("chr") {
+            "X" => 23,
+            "Y" => 24,
+            value => value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=22).contains(n))?,
+        };
+
+        Some(accessions[index - 1])
+    }
+}
+
 #[derive(Debug, PartialEq)]
-pub(crate) struct PatientIdentifier {
-    pub(crate) h_number: String,
-    pub(crate) pid: String,
+pub struct PatientIdentifier {
+    pub h_number: String,
+    pub pid: String,
 }
 
 impl<'de> Deserialize<'de> for PatientIdentifier {
@@ -572,13 +656,24 @@ impl<'de> Deserialize<'de> for PatientIdentifier {
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
-pub(crate) struct General {
+pub struct General {
     #[serde(rename = "ORDER_DATE")]
-    pub(crate) order_date: String,
+    pub order_date: String,
     #[serde(rename = "REF_GENOME_VERSION")]
-    pub(crate) ref_genome_version: RefGenomeVersion,
+    pub ref_genome_version: RefGenomeVersion,
     #[serde(rename = "PATIENT_IDENTIFIER")]
-    pub(crate) patient_identifier: PatientIdentifier,
+    pub patient_identifier: PatientIdentifier,
+    #[serde(rename = "PT_DISEASE_NAME")]
+    pub disease_name: Option<String>,
+    #[serde(rename = "PT_INDICATION_MESHID")]
+    pub indication_mesh_id: Option<String>,
+    #[serde(rename = "REPORT_VERSION")]
+    pub report_version: Option<u32>,
+    #[serde(rename = "CASE_UUID")]
+    pub case_uuid: Option<String>,
+    /// SHA-256 of the read file, not contained in the MH Guide file itself
+    #[serde(skip)]
+    pub input_sha256: Option<String>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -655,9 +750,9 @@ impl Display for ResultType {
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
-pub(crate) struct Variant {
+pub struct Variant {
     #[serde(rename = "DETECTED_VAR_ID")]
-    id: u32,
+    pub(crate) id: u32,
     #[serde(rename = "GENE_SYMBOL")]
     pub(crate) gene_symbol: Option<String>,
     #[serde(rename = "PROTEIN_MODIFICATION")]
@@ -683,10 +778,86 @@ pub(crate) struct Variant {
     #[serde(rename = "CLASSIFICATION_NAME")]
     pub(crate) classification_name: Option<String>,
     #[serde(rename = "ONCOGENIC_CLASSIFICATION_NAME")]
-    oncogenic_classification_name: Option<String>,
+    pub(crate) oncogenic_classification_name: Option<String>,
+    #[serde(rename = "VARIANT_EFFECT")]
+    pub(crate) variant_effect: Option<VariantEffect>,
 }
 
 impl Variant {
+    /// The `DETECTED_VAR_ID`, `0` for variants read from VCF files
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    #[must_use]
+    pub fn gene_symbol(&self) -> Option<&str> {
+        self.gene_symbol.as_deref()
+    }
+
+    /// The result type as exported, e.g. "Einfache Variante (SNV)" or "Copy Number Variation"
+    #[must_use]
+    pub fn result_type(&self) -> String {
+        self.display_variant_type
+            .as_ref()
+            .or(self.protein_variant_type.as_ref())
+            .map_or_else(|| ResultType::default().to_string(), ToString::to_string)
+    }
+
+    /// Protein change, e.g. "p.V600E"
+    #[must_use]
+    pub fn protein_modification(&self) -> Option<&str> {
+        self.protein_modification.as_deref()
+    }
+
+    #[must_use]
+    pub fn chromosome(&self) -> Option<&str> {
+        self.chromosome.as_deref()
+    }
+
+    /// Genomic change, e.g. "g.140753336A>T"
+    #[must_use]
+    pub fn chromosome_modification(&self) -> Option<&str> {
+        self.chromosome_modification.as_deref()
+    }
+
+    /// Transcript change, e.g. "c.1799T>A"
+    #[must_use]
+    pub fn transcript_hgvs_modified_object(&self) -> Option<&str> {
+        self.transcript_hgvs_modified_object.as_deref()
+    }
+
+    #[must_use]
+    pub fn total_reads_in_tumor(&self) -> Option<u32> {
+        self.total_reads_in_tumor
+    }
+
+    /// Variant allele frequency in percent
+    #[must_use]
+    pub fn variant_allele_frequency_in_tumor(&self) -> Option<f32> {
+        self.variant_allele_frequency_in_tumor
+    }
+
+    #[must_use]
+    pub fn db_snp(&self) -> Option<&str> {
+        self.db_snp.as_deref()
+    }
+
+    #[must_use]
+    pub fn copy_number(&self) -> Option<f32> {
+        self.copy_number
+    }
+
+    #[must_use]
+    pub fn classification_name(&self) -> Option<&str> {
+        self.classification_name.as_deref()
+    }
+
+    #[must_use]
+    pub fn oncogenic_classification_name(&self) -> Option<&str> {
+        self.oncogenic_classification_name.as_deref()
+    }
+
     pub(crate) fn dna_change(&self) -> DnaChange {
         DnaChange::from_str(
             self.chromosome_modification
@@ -782,6 +953,46 @@ pub(crate) fn three_letter_protein_modification(short: &str) -> String {
     short.to_string()
 }
 
+/// Converts protein changes like `p.Ala123Val` into `p.A123V` as used in MH Guide files
+#[allow(clippy::expect_used)]
+pub(crate) fn one_letter_protein_modification(three_letter: &str) -> String {
+    let regex = Regex::new(r"[A-Z][a-z]{2}").expect("Invalid regex");
+    regex
+        .replace_all(
+            &three_letter.replace("%3D", "="),
+            |captures: &regex::Captures| {
+                match &captures[0] {
+                    "Phe" => "F",
+                    "Leu" => "L",
+                    "Ser" => "S",
+                    "Tyr" => "Y",
+                    "Cys" => "C",
+                    "Trp" => "W",
+                    "Pro" => "P",
+                    "His" => "H",
+                    "Gln" => "Q",
+                    "Arg" => "R",
+                    "Ile" => "I",
+                    "Met" => "M",
+                    "Thr" => "T",
+                    "Asn" => "N",
+                    "Lys" => "K",
+                    "Val" => "V",
+                    "Ala" => "A",
+                    "Asp" => "D",
+                    "Glu" => "E",
+                    "Gly" => "G",
+                    "Ter" => "*",
+                    other => other,
+                }
+                .to_string()
+            },
+        )
+        .to_string()
+}
+
+/// Positions and alleles of a DNA change. Deleted bases are kept as reference allele,
+/// duplicated bases as alternative allele, if contained in the HGVS nomenclature.
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct DnaChange {
     pub(crate) start: String,
@@ -799,10 +1010,14 @@ impl FromStr for DnaChange {
         let regexes = [
             Regex::new(r"(?P<type>[cg])\.(?P<start>-?\d+)(?P<ref>[ACGT])>(?P<alt>[ACGT])$")
                 .expect("Invalid regex"),
-            Regex::new(r"(?P<type>[cg])\.(?P<start>-?\d+)(?:_(?P<end>-?\d+))?del$")
-                .expect("Invalid regex"),
-            Regex::new(r"(?P<type>[cg])\.(?P<start>-?\d+)(?:_(?P<end>-?\d+))?dup$")
-                .expect("Invalid regex"),
+            Regex::new(
+                r"(?P<type>[cg])\.(?P<start>-?\d+)(?:_(?P<end>-?\d+))?del(?P<ref>[ACGT]+)?$",
+            )
+            .expect("Invalid regex"),
+            Regex::new(
+                r"(?P<type>[cg])\.(?P<start>-?\d+)(?:_(?P<end>-?\d+))?dup(?P<alt>[ACGT]+)?$",
+            )
+            .expect("Invalid regex"),
             Regex::new(r"(?P<type>[cg])\.(?P<start>-?\d+)_-?(?P<end>-?\d+)ins(?P<alt>[ACGT]+)$")
                 .expect("Invalid regex"),
             Regex::new(r"(?P<type>[cg])\.(?P<start>-?\d+)_-?(?P<end>-?\d+)delins(?P<alt>[ACGT]+)$")
@@ -861,7 +1076,7 @@ pub(crate) struct NotableBiomarker {
 #[derive(Debug, Deserialize, PartialEq)]
 pub(crate) struct Biomarker {
     #[serde(rename = "DETECTED_VAR_ID")]
-    id: u32,
+    pub(crate) id: u32,
     #[serde(rename = "DISPLAY_MODIFIED_OBJECT")]
     pub(crate) display_modified_object: Option<String>,
     #[serde(rename = "DISPLAY_VARIANT_TYPE")]
@@ -898,7 +1113,12 @@ mod tests {
                     patient_identifier: PatientIdentifier {
                         h_number: "H10000-26".to_string(),
                         pid: "PID0123456".to_string()
-                    }
+                    },
+                    disease_name: Some("Pancreatic Neoplasms".to_string()),
+                    indication_mesh_id: Some("D010190".to_string()),
+                    report_version: Some(1),
+                    case_uuid: Some("00000000-0001-9999-ffff-000000000001".to_string()),
+                    input_sha256: None,
                 },
                 variants: vec![Variant {
                     id: 12345678,
@@ -914,7 +1134,8 @@ mod tests {
                     db_snp: Some("rs202602111".to_string()),
                     copy_number: None,
                     classification_name: Some("Likely benign".to_string()),
-                    oncogenic_classification_name: None
+                    oncogenic_classification_name: None,
+                    variant_effect: Some(VariantEffect::Other("Missense".to_string()))
                 }],
                 biomarkers: Biomarkers {
                     notable_biomarkers: vec![NotableBiomarker {
@@ -949,7 +1170,12 @@ mod tests {
                     patient_identifier: PatientIdentifier {
                         h_number: "H10000-26".to_string(),
                         pid: "PID0123456".to_string()
-                    }
+                    },
+                    disease_name: Some("Pancreatic Neoplasms".to_string()),
+                    indication_mesh_id: Some("D010190".to_string()),
+                    report_version: Some(1),
+                    case_uuid: Some("00000000-0001-9999-ffff-000000000001".to_string()),
+                    input_sha256: None,
                 },
                 variants: vec![Variant {
                     id: 12345678,
@@ -965,7 +1191,8 @@ mod tests {
                     db_snp: Some("rs202602111".to_string()),
                     copy_number: None,
                     classification_name: Some("Likely benign".to_string()),
-                    oncogenic_classification_name: None
+                    oncogenic_classification_name: None,
+                    variant_effect: Some(VariantEffect::Other("Missense".to_string()))
                 }],
                 biomarkers: Biomarkers {
                     notable_biomarkers: vec![NotableBiomarker {
@@ -1000,7 +1227,12 @@ mod tests {
                     patient_identifier: PatientIdentifier {
                         h_number: "H10000-26".to_string(),
                         pid: "PID0123456".to_string()
-                    }
+                    },
+                    disease_name: Some("Pancreatic Neoplasms".to_string()),
+                    indication_mesh_id: Some("D010190".to_string()),
+                    report_version: Some(1),
+                    case_uuid: Some("00000000-0001-9999-ffff-000000000001".to_string()),
+                    input_sha256: None,
                 },
                 variants: vec![Variant {
                     id: 12345678,
@@ -1016,7 +1248,8 @@ mod tests {
                     db_snp: None,
                     copy_number: Some(12.34),
                     classification_name: None,
-                    oncogenic_classification_name: None
+                    oncogenic_classification_name: None,
+                    variant_effect: Some(VariantEffect::Other("copy gain".to_string()))
                 }],
                 biomarkers: Biomarkers {
                     notable_biomarkers: vec![NotableBiomarker {
@@ -1084,6 +1317,12 @@ mod tests {
     #[case("g.41149933A>G",
         DnaChange{ start: "41149933".to_string(), end: String::new(), ref_allele: "A".to_string(), alt_allele: "G".to_string() }
     )]
+    #[case("c.123_124delAG",
+        DnaChange{ start: "123".to_string(), end: "124".to_string(), ref_allele: "AG".to_string(), alt_allele: String::new() }
+    )]
+    #[case("c.123dupA",
+        DnaChange{ start: "123".to_string(), end: String::new(), ref_allele: String::new(), alt_allele: "A".to_string() }
+    )]
     #[case("g.41149933_41150000dup",
         DnaChange{ start: "41149933".to_string(), end: "41150000".to_string(), ref_allele: String::new(), alt_allele: String::new() }
     )]
@@ -1130,6 +1369,15 @@ mod tests {
         assert_eq!(three_letter_protein_modification(short), long);
     }
 
+    #[rstest]
+    #[case("p.Ala123Val", "p.A123V")]
+    #[case("p.Arg248Ter", "p.R248*")]
+    #[case("p.Leu858%3D", "p.L858=")]
+    #[case("p.Glu746_Ala750del", "p.E746_A750del")]
+    fn test_one_letter_protein_modification(#[case] long: &str, #[case] short: &str) {
+        assert_eq!(one_letter_protein_modification(long), short);
+    }
+
     #[rstest]
     #[case("", 1)]
     #[case("KMT2C p.K1234fs laut XYZ oncogenic", 2)]
@@ -1150,6 +1398,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1167,6 +1420,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1183,6 +1437,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1199,6 +1454,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers: Biomarkers {
@@ -1232,6 +1488,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1249,6 +1510,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1265,6 +1527,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1281,6 +1544,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers: Biomarkers {
@@ -1311,6 +1575,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1328,6 +1597,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1344,6 +1614,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("benign".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers: Biomarkers {
@@ -1411,6 +1682,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1428,6 +1704,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1444,6 +1721,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers: Biomarkers {
@@ -1472,6 +1750,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1489,6 +1772,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1505,6 +1789,7 @@ mod tests {
                     copy_number: Some(0.00),
                     classification_name: None,
                     oncogenic_classification_name: Some("Unclassified".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1521,6 +1806,7 @@ mod tests {
                     copy_number: None,
                     classification_name: None,
                     oncogenic_classification_name: Some("Unclassified".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers: Biomarkers {
@@ -1562,6 +1848,11 @@ mod tests {
                     h_number: "H10000-26".to_string(),
                     pid: "PID0123456".to_string(),
                 },
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: None,
             },
             variants: vec![
                 Variant {
@@ -1579,6 +1870,7 @@ mod tests {
                     copy_number: Some(12.34),
                     classification_name: None,
                     oncogenic_classification_name: Some("oncogenic".to_string()),
+                    variant_effect: None,
                 },
                 Variant {
                     id: 12345678,
@@ -1595,6 +1887,7 @@ mod tests {
                     copy_number: Some(87.),
                     classification_name: Some("Unclassified".to_string()),
                     oncogenic_classification_name: Some("Unclassified".to_string()),
+                    variant_effect: None,
                 },
             ],
             biomarkers,
@@ -1621,7 +1914,12 @@ mod tests {
                     patient_identifier: PatientIdentifier {
                         h_number: "H10000-26".to_string(),
                         pid: "PID0123456".to_string()
-                    }
+                    },
+                    disease_name: Some("Pancreatic Neoplasms".to_string()),
+                    indication_mesh_id: Some("D010190".to_string()),
+                    report_version: Some(1),
+                    case_uuid: Some("00000000-0001-9999-ffff-000000000001".to_string()),
+                    input_sha256: None,
                 },
                 variants: vec![],
                 biomarkers: Biomarkers {
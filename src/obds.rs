@@ -0,0 +1,222 @@
+use crate::export_record::{
+    BiomarkerRecord, CopyNumberRecord, FusionRecord, SimpleVariantRecord, ValueFormat,
+};
+use crate::files::escape_xml;
+use crate::mhguide::PatientIdentifier;
+use std::fmt::Write;
+
+const OBDS_NAMESPACE: &str = "http://www.basisdatensatz.de/oBDS/XML";
+
+/// Genetic variant in the oBDS module "Genetik"
+struct GeneticVariant {
+    name: String,
+    /// Code "P" for a detected pathogenic alteration, "S" for other findings
+    characteristic: &'static str,
+    other_characteristic: String,
+}
+
+/// Returns the code "P" for pathogenic and likely pathogenic variants and "S" for all other
+/// classifications, e.g. benign variants or VUS exported with `--all-variants`
+fn characteristic(classification: &str) -> &'static str {
+    let classification = classification.to_ascii_lowercase();
+    if classification.contains("pathogenic") && !classification.contains("benign") {
+        "P"
+    } else {
+        "S"
+    }
+}
+
+/// Variant class and, if known, classification, e.g. "Einfache Variante (Likely benign)"
+fn other_characteristic(variant_class: &str, classification: &str) -> String {
+    if classification.is_empty() {
+        variant_class.to_string()
+    } else {
+        format!("{variant_class} ({classification})")
+    }
+}
+
+impl GeneticVariant {
+    fn from_simple_variant(record: &SimpleVariantRecord) -> Self {
+        let change = if record.protein.is_empty() {
+            &record.cdna
+        } else {
+            &record.protein
+        };
+        Self {
+            name: format!("{} {}", record.gene, change).trim().to_string(),
+            characteristic: characteristic(&record.classification),
+            other_characteristic: other_characteristic("Einfache Variante", &record.classification),
+        }
+    }
+
+    fn from_copy_number(record: &CopyNumberRecord) -> Self {
+        Self {
+            name: format!("{} {}", record.gene, record.cnv_type)
+                .trim()
+                .to_string(),
+            characteristic: characteristic(&record.classification),
+            other_characteristic: other_characteristic("CNV", &record.classification),
+        }
+    }
+
+    fn from_fusion(record: &FusionRecord) -> Self {
+        Self {
+            name: format!("{}::{}", record.gene, record.fusion_gene),
+            characteristic: characteristic(&record.classification),
+            other_characteristic: other_characteristic("Fusion", &record.classification),
+        }
+    }
+
+    fn from_biomarker(record: &BiomarkerRecord) -> Option<Self> {
+        let value_format = ValueFormat::default();
+        let (name, value) = if let Some(tmb) = record.tmb {
+            ("TMB", format!("{} Mut/Mb", value_format.decimal(tmb)))
+        } else if let Some(msi) = record.msi {
+            ("MSI", value_format.decimal(msi))
+        } else if let Some(hrd) = record.hrd {
+            ("HRD", value_format.decimal(hrd))
+        } else {
+            return None;
+        };
+        Some(Self {
+            name: name.to_string(),
+            characteristic: "S",
+            other_characteristic: format!("Biomarker {value}"),
+        })
+    }
+}
+
+/// Creates the oBDS 3 XML fragment with the module "Genetik" for the patient.
+///
+/// The root element is `Genetik`, which is meant to be embedded into the `Diagnose` or
+/// `Verlauf` of a registry message of the patient identified by `PatientIdentifier.pid`.
+/// Master data of the patient and the tumor required for a complete message are not contained
+/// in MH Guide files, the patient ID is therefore only written as a comment.
+pub(crate) fn obds_xml(
+    patient_identifier: &PatientIdentifier,
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    fusion_records: &[FusionRecord],
+    biomarker_records: &[BiomarkerRecord],
+) -> Result<String, std::fmt::Error> {
+    let genetic_variants = simple_variant_records
+        .iter()
+        .map(GeneticVariant::from_simple_variant)
+        .chain(
+            copy_number_records
+                .iter()
+                .map(GeneticVariant::from_copy_number),
+        )
+        .chain(fusion_records.iter().map(GeneticVariant::from_fusion))
+        .chain(
+            biomarker_records
+                .iter()
+                .filter_map(GeneticVariant::from_biomarker),
+        );
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        xml,
+        "<!-- Patient_ID: {} -->",
+        escape_xml(&patient_identifier.pid).replace("--", "")
+    )?;
+    writeln!(xml, r#"<Genetik xmlns="{OBDS_NAMESPACE}">"#)?;
+    for genetic_variant in genetic_variants {
+        writeln!(xml, "  <Genetische_Variante>")?;
+        writeln!(
+            xml,
+            "    <Bezeichnung>{}</Bezeichnung>",
+            escape_xml(&genetic_variant.name)
+        )?;
+        writeln!(
+            xml,
+            "    <Auspraegung>{}</Auspraegung>",
+            genetic_variant.characteristic
+        )?;
+        writeln!(
+            xml,
+            "    <Sonstige_Auspraegung>{}</Sonstige_Auspraegung>",
+            escape_xml(&genetic_variant.other_characteristic)
+        )?;
+        writeln!(xml, "  </Genetische_Variante>")?;
+    }
+    writeln!(xml, "</Genetik>")?;
+    Ok(xml)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{BiomarkerRecord, FusionRecord, SimpleVariantRecord};
+    use crate::mhguide::MhGuide;
+    use crate::obds::{GeneticVariant, characteristic, obds_xml};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Pathogenic", "P")]
+    #[case("Likely pathogenic", "P")]
+    #[case("VUS", "S")]
+    #[case("Likely benign", "S")]
+    #[case("", "S")]
+    fn test_should_map_classification_to_characteristic(
+        #[case] classification: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(characteristic(classification), expected);
+    }
+
+    #[test]
+    fn test_should_name_fusion_with_both_partners() {
+        let record = FusionRecord {
+            gene: "EML4".to_string(),
+            fusion_gene: "ALK".to_string(),
+            ..FusionRecord::default()
+        };
+
+        assert_eq!(GeneticVariant::from_fusion(&record).name, "EML4::ALK");
+    }
+
+    #[test]
+    fn test_should_create_obds_xml() {
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        let general = &mhguide.general;
+
+        let simple_variant_records = mhguide
+            .all_variants()
+            .iter()
+            .map(|variant| {
+                SimpleVariantRecord::from_variant(
+                    &general.patient_identifier.h_number,
+                    &general.ref_genome_version,
+                    variant,
+                )
+            })
+            .collect::<Vec<_>>();
+        let biomarker_records = vec![BiomarkerRecord::from_tmb(
+            &general.patient_identifier.h_number,
+            &general.ref_genome_version,
+            5.2,
+        )];
+
+        let actual = obds_xml(
+            &general.patient_identifier,
+            &simple_variant_records,
+            &[],
+            &[],
+            &biomarker_records,
+        )
+        .unwrap();
+
+        assert!(actual.contains("<!-- Patient_ID: PID0123456 -->"));
+        assert!(actual.contains(r#"<Genetik xmlns="http://www.basisdatensatz.de/oBDS/XML">"#));
+        assert!(actual.contains(
+            "<Auspraegung>S</Auspraegung>\n    \
+             <Sonstige_Auspraegung>Einfache Variante (Likely benign)</Sonstige_Auspraegung>"
+        ));
+        assert!(actual.contains("<Bezeichnung>TMB</Bezeichnung>"));
+        assert_eq!(actual.matches("<Genetische_Variante>").count(), 2);
+        assert!(actual.trim_end().ends_with("</Genetik>"));
+    }
+}
@@ -0,0 +1,389 @@
+use crate::export_record::{CopyNumberRecord, SimpleVariantRecord};
+use crate::hgnc::GENE_LIST_DATE;
+use crate::mhguide::{General, RefGenomeVersion};
+use crate::provenance::Provenance;
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+
+fn ontology_class(id: &str, label: &str) -> Value {
+    json!({ "id": id, "label": label })
+}
+
+fn acmg_pathogenicity_classification(classification: &str) -> &'static str {
+    match classification.to_ascii_lowercase().as_str() {
+        "pathogenic" => "PATHOGENIC",
+        "likely pathogenic" => "LIKELY_PATHOGENIC",
+        "uncertain significance" | "vus" => "UNCERTAIN_SIGNIFICANCE",
+        "likely benign" => "LIKELY_BENIGN",
+        "benign" => "BENIGN",
+        _ => "NOT_PROVIDED",
+    }
+}
+
+/// Creates a VRS allele from the genomic position of the record.
+///
+/// VRS uses interbase coordinates, so the start position is decremented by one,
+/// except for insertions, which are located between the two given positions.
+/// Returns `None` if the position, the chromosome or the resulting sequence is unknown, e.g. for duplications.
+fn vrs_allele(
+    record: &SimpleVariantRecord,
+    ref_genome_version: &RefGenomeVersion,
+) -> Option<Value> {
+    let sequence_id = ref_genome_version.refseq_chromosome_accession(&record.chromosome)?;
+    let start = record.start.parse::<u64>().ok()?;
+    let end = record.end.parse::<u64>().unwrap_or(start);
+
+    let (start, end) = match record.genomic_position.as_str() {
+        position if position.ends_with("dup") => return None,
+        position if position.contains("delins") || position.ends_with("del") => (start - 1, end),
+        position if position.contains("ins") => (start, start),
+        _ => (start - 1, end),
+    };
+
+    Some(json!({
+        "allele": {
+            "sequenceLocation": {
+                "sequenceId": format!("refseq:{sequence_id}"),
+                "sequenceInterval": {
+                    "startNumber": { "value": start },
+                    "endNumber": { "value": end }
+                }
+            },
+            "literalSequenceExpression": {
+                "sequence": record.alt_allele
+            }
+        }
+    }))
+}
+
+fn simple_variant_interpretation(
+    record: &SimpleVariantRecord,
+    biosample_id: &str,
+    ref_genome_version: &RefGenomeVersion,
+    index: usize,
+) -> Value {
+    let mut expressions = vec![];
+    if !record.genomic_position.is_empty()
+        && let Some(sequence_id) =
+            ref_genome_version.refseq_chromosome_accession(&record.chromosome)
+    {
+        expressions.push(json!({
+            "syntax": "hgvs.g",
+            "value": format!("{sequence_id}:{}", record.genomic_position)
+        }));
+    }
+    if !record.cdna.is_empty() {
+        expressions.push(json!({ "syntax": "hgvs.c", "value": record.cdna }));
+    }
+    if !record.protein.is_empty() {
+        expressions.push(json!({ "syntax": "hgvs.p", "value": record.protein }));
+    }
+
+    let mut variation_descriptor = json!({
+        "id": format!("{biosample_id}-variant-{index}"),
+        "label": format!("{} {}", record.gene, record.cdna).trim().to_string(),
+        "geneContext": { "valueId": record.hgnc_id, "symbol": record.gene },
+        "expressions": expressions,
+        "moleculeContext": "genomic"
+    });
+
+    if let Some(allele) = vrs_allele(record, ref_genome_version) {
+        variation_descriptor["variation"] = allele;
+    }
+
+    if !record.ref_allele.is_empty()
+        && !record.alt_allele.is_empty()
+        && let Ok(position) = record.start.parse::<u64>()
+    {
+        variation_descriptor["vcfRecord"] = json!({
+            "genomeAssembly": ref_genome_version.ncbi_build(),
+            "chrom": record.chromosome,
+            "pos": position,
+            "ref": record.ref_allele,
+            "alt": record.alt_allele
+        });
+    }
+
+    json!({
+        "subjectOrBiosampleId": biosample_id,
+        "interpretationStatus": "UNKNOWN_STATUS",
+        "variantInterpretation": {
+            "acmgPathogenicityClassification": acmg_pathogenicity_classification(&record.classification),
+            "therapeuticActionability": "UNKNOWN_ACTIONABILITY",
+            "variationDescriptor": variation_descriptor
+        }
+    })
+}
+
+fn copy_number_interpretation(
+    record: &CopyNumberRecord,
+    biosample_id: &str,
+    index: usize,
+) -> Value {
+    let mut variation_descriptor = json!({
+        "id": format!("{biosample_id}-cnv-{index}"),
+        "label": format!("{} {}", record.gene, record.cnv_type).trim().to_string(),
+        "geneContext": { "valueId": record.hgnc_id, "symbol": record.gene },
+        "moleculeContext": "genomic"
+    });
+    // The structural type is omitted if MH Guide does not specify gain or loss
+    if record.cnv_type == "loss" {
+        variation_descriptor["structuralType"] = ontology_class("SO:0001743", "copy_number_loss");
+    } else if record.cnv_type.contains("gain") {
+        variation_descriptor["structuralType"] = ontology_class("SO:0001742", "copy_number_gain");
+    }
+
+    json!({
+        "subjectOrBiosampleId": biosample_id,
+        "interpretationStatus": "UNKNOWN_STATUS",
+        "variantInterpretation": {
+            "acmgPathogenicityClassification": "NOT_PROVIDED",
+            "therapeuticActionability": "UNKNOWN_ACTIONABILITY",
+            "variationDescriptor": variation_descriptor
+        }
+    })
+}
+
+/// Creates a GA4GH Phenopacket (schema version 2.0) for the case.
+///
+/// The phenopacket contains the diagnosis (`PT_DISEASE_NAME` and `PT_INDICATION_MESHID`), one biosample
+/// identified by the H-number and an interpretation with one genomic interpretation for each
+/// simple variant and copy number variant. The diagnosis is omitted if the `MeSH` ID or the disease
+/// name is missing, since an ontology class requires both. The provenance is written into the
+/// metadata as external references.
+pub(crate) fn phenopacket(
+    general: &General,
+    simple_variant_records: &[SimpleVariantRecord],
+    copy_number_records: &[CopyNumberRecord],
+    provenance: Option<&Provenance>,
+) -> Value {
+    let patient_identifier = &general.patient_identifier;
+    let biosample_id = patient_identifier.h_number.as_str();
+
+    let disease = match (&general.indication_mesh_id, &general.disease_name) {
+        (Some(id), Some(name)) if !id.is_empty() && !name.is_empty() => {
+            Some(ontology_class(&format!("MESH:{id}"), name))
+        }
+        _ => None,
+    };
+
+    let genomic_interpretations = simple_variant_records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            simple_variant_interpretation(
+                record,
+                biosample_id,
+                &general.ref_genome_version,
+                index + 1,
+            )
+        })
+        .chain(
+            copy_number_records
+                .iter()
+                .enumerate()
+                .map(|(index, record)| copy_number_interpretation(record, biosample_id, index + 1)),
+        )
+        .collect::<Vec<_>>();
+
+    let mut biosample = json!({
+        "id": biosample_id,
+        "individualId": patient_identifier.pid
+    });
+    let mut diagnosis = json!({ "genomicInterpretations": genomic_interpretations });
+    if let Some(disease) = &disease {
+        biosample["histologicalDiagnosis"] = disease.clone();
+        diagnosis["disease"] = disease.clone();
+    }
+
+    let mut phenopacket = json!({
+        "id": biosample_id,
+        "subject": { "id": patient_identifier.pid },
+        "biosamples": [biosample],
+        "interpretations": [{
+            "id": format!("{biosample_id}-interpretation"),
+            "progressStatus": "UNKNOWN_PROGRESS",
+            "diagnosis": diagnosis
+        }],
+        "metaData": {
+            "created": provenance.map_or_else(
+                || Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                |provenance| provenance.created.clone()
+            ),
+            "createdBy": format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            "resources": [
+                {
+                    "id": "mesh",
+                    "name": "Medical Subject Headings",
+                    "url": "https://www.nlm.nih.gov/mesh/",
+                    "version": "unknown",
+                    "namespacePrefix": "MESH",
+                    "iriPrefix": "http://id.nlm.nih.gov/mesh/"
+                },
+                {
+                    "id": "hgnc",
+                    "name": "HUGO Gene Nomenclature Committee",
+                    "url": "https://www.genenames.org",
+                    "version": GENE_LIST_DATE,
+                    "namespacePrefix": "HGNC",
+                    "iriPrefix": "https://www.genenames.org/data/gene-symbol-report/#!/hgnc_id/"
+                },
+                {
+                    "id": "so",
+                    "name": "Sequence types and features ontology",
+                    "url": "http://purl.obolibrary.org/obo/so.owl",
+                    "version": "unknown",
+                    "namespacePrefix": "SO",
+                    "iriPrefix": "http://purl.obolibrary.org/obo/SO_"
+                }
+            ],
+            "phenopacketSchemaVersion": "2.0",
+            "externalReferences": provenance
+                .map(Provenance::entries)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(name, value)| json!({ "id": value, "description": name }))
+                .collect::<Vec<_>>()
+        }
+    });
+    if let Some(disease) = disease {
+        phenopacket["diseases"] = json!([{ "term": disease }]);
+    }
+    phenopacket
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{CopyNumberRecord, SimpleVariantRecord};
+    use crate::mhguide::MhGuide;
+    use crate::phenopacket::phenopacket;
+    use rstest::rstest;
+    use serde_json::{Value, json};
+
+    /// Asserts that all ontology classes have an ID and a label and all resources contain the
+    /// fields required by the Phenopacket schema
+    fn assert_valid(value: &Value) {
+        match value {
+            Value::Object(object) => {
+                if object.contains_key("label") && !object.contains_key("geneContext") {
+                    assert_ne!(object["id"], "");
+                    assert_ne!(object["label"], "");
+                }
+                object.values().for_each(assert_valid);
+            }
+            Value::Array(array) => array.iter().for_each(assert_valid),
+            _ => {}
+        }
+    }
+
+    fn read_mhguide(file: &str) -> MhGuide {
+        let content = std::fs::read_to_string(format!("./testfiles/{file}")).unwrap();
+        serde_json::from_str::<MhGuide>(&content).unwrap()
+    }
+
+    #[rstest]
+    #[case("sv-mhguide.json", json!({"startNumber": {"value": 12345677}, "endNumber": {"value": 12345678}}))]
+    #[case("sv_del-mhguide.json", json!({"startNumber": {"value": 12345669}, "endNumber": {"value": 12345678}}))]
+    fn test_should_create_phenopacket(
+        #[case] file: &str,
+        #[case] expected_interval: serde_json::Value,
+    ) {
+        let mhguide = read_mhguide(file);
+        let general = &mhguide.general;
+
+        let simple_variant_records = mhguide
+            .all_variants()
+            .iter()
+            .map(|variant| {
+                SimpleVariantRecord::from_variant(
+                    &general.patient_identifier.h_number,
+                    &general.ref_genome_version,
+                    variant,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let actual = phenopacket(general, &simple_variant_records, &[], None);
+
+        assert_eq!(actual["subject"]["id"], "PID0123456");
+        assert_eq!(actual["diseases"][0]["term"]["id"], "MESH:D010190");
+
+        let variant_interpretation = &actual["interpretations"][0]["diagnosis"]["genomicInterpretations"]
+            [0]["variantInterpretation"];
+        assert_eq!(
+            variant_interpretation["acmgPathogenicityClassification"],
+            "LIKELY_BENIGN"
+        );
+        let allele = &variant_interpretation["variationDescriptor"]["variation"]["allele"];
+        assert_eq!(
+            allele["sequenceLocation"]["sequenceId"],
+            "refseq:NC_000001.10"
+        );
+        assert_eq!(
+            allele["sequenceLocation"]["sequenceInterval"],
+            expected_interval
+        );
+        assert_valid(&actual);
+    }
+
+    #[test]
+    fn test_should_omit_disease_without_mesh_id() {
+        let mut mhguide = read_mhguide("cnv-mhguide.json");
+        mhguide.general.indication_mesh_id = None;
+        let record = CopyNumberRecord {
+            gene: "BRAF".to_string(),
+            ..CopyNumberRecord::default()
+        };
+
+        let actual = phenopacket(&mhguide.general, &[], &[record], None);
+
+        assert!(actual.get("diseases").is_none());
+        assert!(
+            actual["biosamples"][0]
+                .get("histologicalDiagnosis")
+                .is_none()
+        );
+        assert!(
+            actual["interpretations"][0]["diagnosis"]
+                .get("disease")
+                .is_none()
+        );
+        let variation_descriptor = &actual["interpretations"][0]["diagnosis"]["genomicInterpretations"]
+            [0]["variantInterpretation"]["variationDescriptor"];
+        assert!(variation_descriptor.get("structuralType").is_none());
+        for resource in actual["metaData"]["resources"].as_array().unwrap() {
+            for field in [
+                "id",
+                "name",
+                "url",
+                "version",
+                "namespacePrefix",
+                "iriPrefix",
+            ] {
+                assert!(resource.get(field).is_some());
+            }
+        }
+        assert_valid(&actual);
+    }
+    #[test]
+    fn test_should_write_provenance_into_metadata() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = crate::read(&content).unwrap();
+        let records = crate::records(&mhguide, crate::VariantSelection::All);
+
+        let actual = crate::phenopacket_json(&mhguide.general, &records).unwrap();
+        let actual = serde_json::from_str::<Value>(&actual).unwrap();
+
+        let meta_data = &actual["metaData"];
+        assert_eq!(
+            meta_data["resources"][1]["version"],
+            crate::hgnc::GENE_LIST_DATE
+        );
+        assert_eq!(
+            meta_data["externalReferences"][0],
+            json!({ "id": crate::provenance::sha256(&content), "description": "inputSha256" })
+        );
+        assert_eq!(meta_data["created"], records.provenance.unwrap().created);
+    }
+}
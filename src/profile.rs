@@ -0,0 +1,156 @@
+use crate::export_record::{ExportRecord, FieldValue, ValueFormat};
+use crate::messages::{Language, translate};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Column in a profile, either the CSV headline or the headline with a new header text
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ColumnEntry {
+    Name(String),
+    Renamed { column: String, header: String },
+}
+
+/// Selection, order and header text of the exported columns for each record type.
+///
+/// Record types not contained in the profile are exported with all columns.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub(crate) simple_variants: Option<Vec<ColumnEntry>>,
+    pub(crate) copy_number_variants: Option<Vec<ColumnEntry>>,
+    pub(crate) fusions: Option<Vec<ColumnEntry>>,
+    pub(crate) biomarkers: Option<Vec<ColumnEntry>>,
+}
+
+/// Exported column with the index of the value in `ExportRecord::values()`
+pub(crate) struct Column {
+    pub(crate) index: usize,
+    pub(crate) header: String,
+}
+
+impl Profile {
+    /// Reads the profile from a JSON file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid profile.
+    pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str::<Profile>(&content)?)
+    }
+
+    /// Returns the exported columns of the record type.
+    ///
+    /// Headers not renamed in the profile are translated into the language.
+    pub(crate) fn columns<T: ExportRecord>(
+        &self,
+        language: Language,
+    ) -> Result<Vec<Column>, Box<dyn std::error::Error>> {
+        let headlines = T::csv_headlines();
+
+        let Some(entries) = T::profile_columns(self) else {
+            return Ok(headlines
+                .into_iter()
+                .enumerate()
+                .map(|(index, headline)| Column {
+                    index,
+                    header: translate(language, &headline),
+                })
+                .collect());
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                let (column, header) = match entry {
+                    ColumnEntry::Name(column) => (column, translate(language, column)),
+                    ColumnEntry::Renamed { column, header } => (column, header.clone()),
+                };
+                match headlines.iter().position(|headline| headline == column) {
+                    Some(index) => Ok(Column { index, header }),
+                    None => Err(format!(
+                        "Unknown column '{column}' for '{}' in profile",
+                        T::RECORD_TYPE
+                    )
+                    .into()),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Selects the values of the columns from all values of a record
+pub(crate) fn selected_values<'a>(
+    values: &'a [FieldValue<'a>],
+    columns: &[Column],
+) -> Vec<&'a FieldValue<'a>> {
+    columns.iter().map(|column| &values[column.index]).collect()
+}
+
+/// Formats the values of the columns of a record
+pub(crate) fn formatted_values<T: ExportRecord>(
+    record: &T,
+    columns: &[Column],
+    value_format: ValueFormat,
+) -> Vec<String> {
+    let values = record.values();
+    selected_values(&values, columns)
+        .into_iter()
+        .map(|value| value_format.format(value))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{
+        BiomarkerRecord, CopyNumberRecord, ExportRecord, FusionRecord, ValueFormat,
+    };
+    use crate::messages::Language;
+    use crate::mhguide::RefGenomeVersion;
+    use crate::profile::{Profile, formatted_values};
+
+    #[test]
+    fn test_should_select_rename_and_order_columns() {
+        let profile = serde_json::from_str::<Profile>(
+            r#"{
+                "biomarkers": [
+                    { "column": "TMB - Tumor Mutational Burden", "header": "TMB" },
+                    "H-Nummer"
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let columns = profile.columns::<BiomarkerRecord>(Language::En).unwrap();
+        let headers = columns
+            .iter()
+            .map(|column| column.header.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(headers, vec!["TMB", "H-Number"]);
+
+        let record = BiomarkerRecord::from_tmb("H/2025/12345", &RefGenomeVersion::Hg19, 5.2);
+        assert_eq!(
+            formatted_values(&record, &columns, ValueFormat::default()),
+            vec!["5,20", "H/2025/12345"]
+        );
+
+        // Record types not in the profile keep all columns
+        assert_eq!(
+            profile
+                .columns::<CopyNumberRecord>(Language::De)
+                .unwrap()
+                .len(),
+            CopyNumberRecord::csv_headlines().len()
+        );
+    }
+
+    #[test]
+    fn test_should_fail_on_unknown_column() {
+        let profile = serde_json::from_str::<Profile>(r#"{ "fusions": ["Unbekannt"] }"#).unwrap();
+
+        assert!(profile.columns::<FusionRecord>(Language::De).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::VariantSelection;
+use crate::hgnc::{GENE_LIST, GENE_LIST_DATE};
+use crate::mhguide::General;
+use chrono::{SecondsFormat, Utc};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+static HGNC_SHA256: LazyLock<String> = LazyLock::new(|| sha256(GENE_LIST.as_bytes()));
+
+/// Writes the bytes as lowercase hexadecimal digits
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Returns the SHA-256 of the content as hexadecimal digits
+pub(crate) fn sha256(content: &[u8]) -> String {
+    hex(&Sha256::digest(content))
+}
+
+/// Origin of exported records, written into the exported files to trace which file, tool version
+/// and variant selection produced them
+#[derive(Clone, Debug)]
+pub struct Provenance {
+    /// SHA-256 of the read file
+    pub input_sha256: Option<String>,
+    /// `CASE_UUID` of the MH Guide file
+    pub case_uuid: Option<String>,
+    /// `REPORT_VERSION` of the MH Guide file
+    pub report_version: Option<u32>,
+    /// Selected variants, not known for imported records
+    pub variant_selection: Option<VariantSelection>,
+    /// Version of mhguide-to-csv
+    pub version: &'static str,
+    /// Download date of the included HGNC gene list
+    pub hgnc_date: &'static str,
+    /// SHA-256 of the included HGNC gene list
+    pub hgnc_sha256: String,
+    /// Time of the conversion in RFC 3339 format
+    pub created: String,
+}
+
+impl Provenance {
+    /// Provenance of records converted from the case data of an input source
+    #[must_use]
+    pub fn new(general: &General, variant_selection: VariantSelection) -> Self {
+        Provenance {
+            input_sha256: general.input_sha256.clone(),
+            case_uuid: general.case_uuid.clone(),
+            report_version: general.report_version,
+            variant_selection: Some(variant_selection),
+            ..Self::from_content(&[])
+        }
+    }
+
+    /// Provenance of records read from another file, e.g. an imported CSV or DNPM JSON file
+    #[must_use]
+    pub fn from_content(content: &[u8]) -> Self {
+        Provenance {
+            input_sha256: (!content.is_empty()).then(|| sha256(content)),
+            case_uuid: None,
+            report_version: None,
+            variant_selection: None,
+            version: env!("CARGO_PKG_VERSION"),
+            hgnc_date: GENE_LIST_DATE,
+            hgnc_sha256: HGNC_SHA256.clone(),
+            created: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }
+    }
+
+    /// Names and values of all known entries
+    #[must_use]
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let variant_selection = self.variant_selection.map(|selection| match selection {
+            VariantSelection::Relevant => "relevant",
+            VariantSelection::RelevantWithoutArtifacts => "no-artifacts",
+            VariantSelection::Oncogenic => "oncogenic",
+            VariantSelection::All => "all-variants",
+        });
+        [
+            ("inputSha256", self.input_sha256.clone()),
+            ("caseUuid", self.case_uuid.clone()),
+            (
+                "reportVersion",
+                self.report_version.map(|version| version.to_string()),
+            ),
+            ("mhguideToCsvVersion", Some(self.version.to_string())),
+            ("hgncDate", Some(self.hgnc_date.to_string())),
+            ("hgncSha256", Some(self.hgnc_sha256.clone())),
+            ("variantSelection", variant_selection.map(str::to_string)),
+            ("created", Some(self.created.clone())),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::provenance::{Provenance, sha256};
+    use crate::{VariantSelection, read};
+
+    #[test]
+    fn test_should_create_provenance_of_mhguide_file() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+
+        let provenance = Provenance::new(&mhguide.general, VariantSelection::Oncogenic);
+        let entries = provenance.entries();
+
+        assert_eq!(entries[0], ("inputSha256", sha256(&content)));
+        assert_eq!(
+            entries[1],
+            (
+                "caseUuid",
+                "00000000-0001-9999-ffff-000000000001".to_string()
+            )
+        );
+        assert_eq!(entries[2], ("reportVersion", "1".to_string()));
+        assert_eq!(entries[4], ("hgncDate", "2026-04-02".to_string()));
+        assert_eq!(entries[6], ("variantSelection", "oncogenic".to_string()));
+    }
+
+    #[test]
+    fn test_should_hash_content() {
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use crate::VariantSelection;
+use crate::export_record::Records;
+use crate::input::InputSource;
+use crate::mhguide::{Fusion, General, PatientIdentifier, Variant};
+use crate::provenance::hex;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Environment variable containing the key used if no key file is given
+pub const KEY_VARIABLE: &str = "MHGUIDE_TO_CSV_PSEUDONYM_KEY";
+
+/// Minimal length of the key in bytes
+const MIN_KEY_LENGTH: usize = 16;
+
+/// Number of bytes of the HMAC used as pseudonym, written as hexadecimal digits
+const PSEUDONYM_LENGTH: usize = 16;
+
+/// Replaces H-numbers and PIDs by pseudonyms
+pub enum Pseudonymizer {
+    /// Pseudonyms are the first bytes of the HMAC-SHA256 of the identifier with a secret key
+    Hmac(Vec<u8>),
+    /// Pseudonyms are looked up in a mapping of identifiers to pseudonyms
+    Mapping(HashMap<String, String>),
+}
+
+impl Pseudonymizer {
+    /// Creates a pseudonymizer with the secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is shorter than 16 bytes.
+    pub fn with_key(key: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let key = key.trim_ascii();
+        if key.len() < MIN_KEY_LENGTH {
+            return Err(format!("Pseudonym key must have at least {MIN_KEY_LENGTH} bytes").into());
+        }
+        Ok(Pseudonymizer::Hmac(key.to_vec()))
+    }
+
+    /// Creates a pseudonymizer with the secret key read from the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or the key is too short.
+    pub fn read_key_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_key(&std::fs::read(path)?)
+    }
+
+    /// Creates a pseudonymizer with the secret key in the environment variable
+    /// `MHGUIDE_TO_CSV_PSEUDONYM_KEY`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variable is not set or the key is too short.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let key = std::env::var(KEY_VARIABLE)
+            .map_err(|_| format!("Environment variable {KEY_VARIABLE} is not set"))?;
+        Self::with_key(key.as_bytes())
+    }
+
+    /// Reads a CSV mapping file with a header line and the columns identifier and pseudonym,
+    /// separated by a semicolon. H-numbers and PIDs can be given in the same file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or contains an invalid or duplicate entry.
+    pub fn read_mapping(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut mapping = HashMap::new();
+        let mut reader = csv::ReaderBuilder::new().delimiter(b';').from_path(path)?;
+        for (index, record) in reader.records().enumerate() {
+            let record = record?;
+            let (Some(identifier), Some(pseudonym), 2) =
+                (record.get(0), record.get(1), record.len())
+            else {
+                return Err(format!("Invalid mapping in line {}", index + 2).into());
+            };
+            let (identifier, pseudonym) = (identifier.trim(), pseudonym.trim());
+            if identifier.is_empty() || pseudonym.is_empty() {
+                return Err(format!("Invalid mapping in line {}", index + 2).into());
+            }
+            if mapping
+                .insert(identifier.to_string(), pseudonym.to_string())
+                .is_some()
+            {
+                return Err(format!("Duplicate mapping in line {}", index + 2).into());
+            }
+        }
+        Ok(Pseudonymizer::Mapping(mapping))
+    }
+
+    /// Returns the pseudonym of the identifier, empty identifiers stay empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the identifier is not contained in the mapping.
+    pub fn pseudonym(&self, identifier: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if identifier.is_empty() {
+            return Ok(String::new());
+        }
+        match self {
+            Pseudonymizer::Hmac(key) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key)?;
+                mac.update(identifier.as_bytes());
+                Ok(hex(&mac.finalize().into_bytes()[..PSEUDONYM_LENGTH]))
+            }
+            Pseudonymizer::Mapping(mapping) => mapping
+                .get(identifier)
+                .cloned()
+                .ok_or_else(|| format!("No pseudonym for '{identifier}' in mapping file").into()),
+        }
+    }
+
+    /// Wraps the input source so that all exports contain pseudonyms instead of the H-number
+    /// and PID. The free text report is removed, since it may contain names or dates of birth,
+    /// and so are the `CASE_UUID` linking to the case in MH Guide and the SHA-256 of the input
+    /// file, which identifies the original file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no pseudonym is found for the H-number or PID.
+    pub fn pseudonymize(
+        &self,
+        source: Box<dyn InputSource>,
+    ) -> Result<Box<dyn InputSource>, Box<dyn std::error::Error>> {
+        let general = source.general();
+        let general = General {
+            order_date: general.order_date.clone(),
+            ref_genome_version: general.ref_genome_version.clone(),
+            patient_identifier: PatientIdentifier {
+                h_number: self.pseudonym(&general.patient_identifier.h_number)?,
+                pid: self.pseudonym(&general.patient_identifier.pid)?,
+            },
+            disease_name: general.disease_name.clone(),
+            indication_mesh_id: general.indication_mesh_id.clone(),
+            report_version: general.report_version,
+            case_uuid: None,
+            input_sha256: None,
+        };
+        Ok(Box::new(Pseudonymized { source, general }))
+    }
+
+    /// Replaces the H-numbers of all records, e.g. of imported files, and removes the SHA-256 of
+    /// the input file from the provenance
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no pseudonym is found for an H-number.
+    pub fn pseudonymize_records(
+        &self,
+        records: &mut Records,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let h_numbers = records
+            .simple_variants
+            .iter_mut()
+            .map(|record| &mut record.h_nummer)
+            .chain(
+                records
+                    .copy_numbers
+                    .iter_mut()
+                    .map(|record| &mut record.h_nummer),
+            )
+            .chain(
+                records
+                    .fusions
+                    .iter_mut()
+                    .map(|record| &mut record.h_nummer),
+            )
+            .chain(
+                records
+                    .biomarkers
+                    .iter_mut()
+                    .map(|record| &mut record.h_nummer),
+            );
+        for h_number in h_numbers {
+            *h_number = self.pseudonym(h_number)?;
+        }
+        if let Some(provenance) = &mut records.provenance {
+            provenance.input_sha256 = None;
+        }
+        Ok(())
+    }
+}
+
+/// Input source with pseudonymized general data
+struct Pseudonymized {
+    source: Box<dyn InputSource>,
+    general: General,
+}
+
+impl InputSource for Pseudonymized {
+    fn general(&self) -> &General {
+        &self.general
+    }
+
+    fn variants(&self, selection: VariantSelection) -> Vec<&Variant> {
+        self.source.variants(selection)
+    }
+
+    fn fusions(&self) -> Vec<Fusion> {
+        self.source.fusions()
+    }
+
+    fn hrd_score(&self) -> Option<f32> {
+        self.source.hrd_score()
+    }
+
+    fn msi_score(&self) -> Option<f32> {
+        self.source.msi_score()
+    }
+
+    fn tmb_value(&self) -> Option<f32> {
+        self.source.tmb_value()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::pseudonym::Pseudonymizer;
+    use crate::{VariantSelection, read, records};
+
+    const KEY: &[u8] = b"0123456789abcdef0123456789abcdef\n";
+
+    #[test]
+    fn test_should_pseudonymize_with_key() {
+        let pseudonymizer = Pseudonymizer::with_key(KEY).unwrap();
+
+        let pseudonym = pseudonymizer.pseudonym("H12345-25").unwrap();
+
+        assert_eq!(pseudonym.len(), 32);
+        assert_eq!(pseudonym, pseudonymizer.pseudonym("H12345-25").unwrap());
+        assert_ne!(pseudonym, pseudonymizer.pseudonym("H12346-25").unwrap());
+    }
+
+    #[test]
+    fn test_should_reject_short_key() {
+        assert!(Pseudonymizer::with_key(b"secret").is_err());
+    }
+
+    #[test]
+    fn test_should_pseudonymize_all_records() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let h_number = mhguide.general.patient_identifier.h_number.clone();
+        let pid = mhguide.general.patient_identifier.pid.clone();
+        let pseudonymizer = Pseudonymizer::with_key(KEY).unwrap();
+
+        let source = pseudonymizer.pseudonymize(Box::new(mhguide)).unwrap();
+        let records = records(source.as_ref(), VariantSelection::All);
+
+        assert_eq!(
+            source.general().patient_identifier.h_number,
+            pseudonymizer.pseudonym(&h_number).unwrap()
+        );
+        assert_ne!(source.general().patient_identifier.pid, pid);
+        assert_eq!(source.report_narrative(), "");
+        assert_eq!(source.general().input_sha256, None);
+        assert_eq!(records.provenance.unwrap().input_sha256, None);
+        assert!(
+            records
+                .simple_variants
+                .iter()
+                .all(|record| record.h_nummer == source.general().patient_identifier.h_number)
+        );
+    }
+
+    #[test]
+    fn test_should_reject_unmapped_identifier() {
+        let pseudonymizer = Pseudonymizer::Mapping(
+            [("H12345-25".to_string(), "P001".to_string())]
+                .into_iter()
+                .collect(),
+        );
+
+        assert_eq!(pseudonymizer.pseudonym("H12345-25").unwrap(), "P001");
+        assert!(pseudonymizer.pseudonym("H12346-25").is_err());
+    }
+}
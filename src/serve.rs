@@ -0,0 +1,313 @@
+use itertools::Itertools;
+use mhguide_to_csv::{Exporter, InputSource, OutputTarget, exporter, exporters};
+use std::io::Read;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Selects the exporter by the query parameter `format` or else by the `Accept` header.
+///
+/// Media types of the `Accept` header are tried in order of their quality value `q`, media types
+/// with the same quality in the given order. The default format is used if neither is given or
+/// any format is accepted. Only formats written into a single file can be selected.
+fn select_exporter(format: Option<&str>, accept: Option<&str>) -> Option<&'static dyn Exporter> {
+    if let Some(format) = format {
+        return exporter(format).filter(|exporter| exporter.target() == OutputTarget::File);
+    }
+
+    let file_exporters = || {
+        exporters()
+            .iter()
+            .filter(|exporter| exporter.target() == OutputTarget::File)
+    };
+    let default = file_exporters().next().copied();
+    let Some(accept) = accept else {
+        return default;
+    };
+    accept_media_types(accept)
+        .into_iter()
+        .find_map(|media_type| match media_type {
+            "*/*" => default,
+            _ => {
+                file_exporters()
+                    .find(|exporter| {
+                        let content_type = exporter
+                            .content_type()
+                            .split(';')
+                            .next()
+                            .unwrap_or_default();
+                        match media_type.strip_suffix("/*") {
+                            Some(main_type) => content_type.split_once('/').is_some_and(
+                                |(content_main_type, _)| {
+                                    content_main_type.eq_ignore_ascii_case(main_type)
+                                },
+                            ),
+                            None => content_type.eq_ignore_ascii_case(media_type),
+                        }
+                    })
+                    .copied()
+            }
+        })
+}
+
+/// Returns the media types of the `Accept` header sorted by their quality value `q`, media types
+/// with `q=0` or an invalid quality value are not acceptable and left out
+fn accept_media_types(accept: &str) -> Vec<&str> {
+    accept
+        .split(',')
+        .filter_map(|media_range| {
+            let mut parts = media_range.split(';');
+            let media_type = parts.next()?.trim();
+            let quality = match parts
+                .filter_map(|parameter| parameter.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+            {
+                Some((_, quality)) => quality.trim().parse::<f32>().ok()?,
+                None => 1.0,
+            };
+            (quality > 0.0).then_some((media_type, quality))
+        })
+        // Stable sort keeps the given order of media types with the same quality
+        .sorted_by(|(_, a), (_, b)| b.total_cmp(a))
+        .map(|(media_type, _)| media_type)
+        .collect()
+}
+
+/// Response to a request with status code, content type and body
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn text(status: u16, text: &str) -> Self {
+        Reply {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Decodes a percent-encoded query component, e.g. `fhir%2Bjson`, `+` is decoded as space.
+/// Returns `None` for invalid escapes or if the decoded value is not UTF-8.
+fn percent_decode(component: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut input = component.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Returns the decoded value of the query parameter, values with invalid escapes are returned
+/// as given and thus not matched against format names
+fn query_parameter(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(key, _)| percent_decode(key).as_deref() == Some(name))
+        .map(|(_, value)| percent_decode(value).unwrap_or_else(|| value.to_string()))
+}
+
+/// Handles one request independent of the HTTP server
+fn handle(
+    method: &Method,
+    url: &str,
+    accept: Option<&str>,
+    body: &[u8],
+    convert: &impl Fn(
+        Box<dyn InputSource>,
+        &dyn Exporter,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>>,
+) -> Reply {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    match (method, path) {
+        (Method::Get, "/health") => Reply::text(200, "OK"),
+        (Method::Post, "/convert") => {
+            let Some(exporter) =
+                select_exporter(query_parameter(query, "format").as_deref(), accept)
+            else {
+                let names = exporters()
+                    .iter()
+                    .filter(|exporter| exporter.target() == OutputTarget::File)
+                    .map(|exporter| exporter.name())
+                    .join(", ");
+                return Reply::text(406, &format!("Supported formats are {names}"));
+            };
+            let source = match mhguide_to_csv::read_source(body) {
+                Ok(source) => source,
+                Err(err) => return Reply::text(400, &format!("Invalid input file: {err}")),
+            };
+            match convert(source, exporter) {
+                Ok(content) => Reply {
+                    status: 200,
+                    content_type: exporter.content_type(),
+                    body: content,
+                },
+                Err(err) => Reply::text(500, &format!("Conversion failed: {err}")),
+            }
+        }
+        (_, "/health" | "/convert") => Reply::text(405, "Method not allowed"),
+        _ => Reply::text(404, "Not found"),
+    }
+}
+
+/// Reads the request body, returns `None` if it is larger than `max_size` bytes
+fn read_body(request: &mut Request, max_size: usize) -> Result<Option<Vec<u8>>, std::io::Error> {
+    if request
+        .body_length()
+        .is_some_and(|length| length > max_size)
+    {
+        return Ok(None);
+    }
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut body)?;
+    if body.len() > max_size {
+        return Ok(None);
+    }
+    Ok(Some(body))
+}
+
+/// Starts the HTTP server and converts all uploaded files with `convert`.
+///
+/// * `GET /health` - Returns `OK` if the service is running
+/// * `POST /convert` - Converts the uploaded JSON, ZIP or VCF file into one of the registered formats
+pub(crate) fn serve(
+    address: &str,
+    max_size: usize,
+    convert: impl Fn(Box<dyn InputSource>, &dyn Exporter) -> Result<Vec<u8>, Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::http(address).map_err(|err| err.to_string())?;
+    println!("Warte auf Anfragen unter http://{address}");
+
+    for mut request in server.incoming_requests() {
+        let accept = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Accept"))
+            .map(|header| header.value.to_string());
+
+        let body = match read_body(&mut request, max_size) {
+            Ok(body) => body,
+            Err(err) => {
+                eprintln!("Fehler beim Lesen der Anfrage: {err}");
+                continue;
+            }
+        };
+        let reply = match body {
+            Some(body) => handle(
+                request.method(),
+                request.url(),
+                accept.as_deref(),
+                &body,
+                &convert,
+            ),
+            None => Reply::text(413, "Request body too large"),
+        };
+
+        eprintln!("{} {} {}", request.method(), request.url(), reply.status);
+
+        let content_type = Header::from_bytes("Content-Type", reply.content_type)
+            .map_err(|()| "Invalid content type")?;
+        let response = Response::from_data(reply.body)
+            .with_status_code(reply.status)
+            .with_header(content_type);
+        if let Err(err) = request.respond(response) {
+            eprintln!("Fehler beim Senden der Antwort: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::serve::{handle, percent_decode, select_exporter};
+    use mhguide_to_csv::Exporter;
+    use rstest::rstest;
+    use tiny_http::Method;
+
+    #[rstest]
+    #[case(None, None, Some("csv"))]
+    #[case(Some("xlsx"), Some("text/csv"), Some("xlsx"))]
+    #[case(Some("pdf"), None, None)]
+    #[case(None, Some("application/json;q=0.9, */*;q=0.1"), Some("json"))]
+    #[case(None, Some("application/fhir+json"), Some("fhir"))]
+    #[case(None, Some("image/png"), None)]
+    #[case(Some("parquet"), None, None)]
+    #[case(None, Some("application/vnd.sqlite3"), None)]
+    #[case(None, Some("text/csv;q=0.5, application/json"), Some("json"))]
+    #[case(None, Some("application/json;q=0, */*;q=0.1"), Some("csv"))]
+    #[case(None, Some("image/png, text/*;q=0.8"), Some("csv"))]
+    #[case(None, Some("TEXT/HTML"), Some("html"))]
+    fn test_should_select_exporter(
+        #[case] format: Option<&str>,
+        #[case] accept: Option<&str>,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(
+            select_exporter(format, accept).map(Exporter::name),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(Method::Get, "/health", 200)]
+    #[case(Method::Post, "/convert?format=json", 200)]
+    #[case(Method::Post, "/convert?format=pdf", 406)]
+    #[case(Method::Post, "/convert?format=%6Aso%6E", 200)]
+    #[case(Method::Post, "/convert?format=js%ZZon", 406)]
+    #[case(Method::Get, "/convert", 405)]
+    #[case(Method::Get, "/", 404)]
+    fn test_should_handle_request(
+        #[case] method: Method,
+        #[case] url: &str,
+        #[case] expected_status: u16,
+    ) {
+        let body = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let reply = handle(&method, url, None, &body, &|source, exporter| {
+            assert_eq!(exporter.name(), "json");
+            Ok(source.general().patient_identifier.pid.as_bytes().to_vec())
+        });
+
+        assert_eq!(reply.status, expected_status);
+        if expected_status == 200 && url.starts_with("/convert") {
+            assert_eq!(reply.content_type, "application/json");
+            assert_eq!(reply.body, b"PID0123456");
+        }
+    }
+
+    #[rstest]
+    #[case("json", Some("json"))]
+    #[case("fhir%2Bjson", Some("fhir+json"))]
+    #[case("a+b", Some("a b"))]
+    #[case("%4", None)]
+    #[case("%G1", None)]
+    #[case("%FF", None)]
+    fn test_should_percent_decode(#[case] component: &str, #[case] expected: Option<&str>) {
+        assert_eq!(percent_decode(component).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_should_reject_invalid_upload() {
+        let reply = handle(&Method::Post, "/convert", None, b"invalid", &|_, _| {
+            Ok(vec![])
+        });
+        assert_eq!(reply.status, 400);
+    }
+}
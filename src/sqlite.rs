@@ -0,0 +1,383 @@
+use crate::export_record::{Records, integer};
+use crate::mhguide::General;
+use crate::provenance::Provenance;
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS cases (
+        h_nummer TEXT PRIMARY KEY NOT NULL,
+        report_version INTEGER,
+        pid TEXT NOT NULL,
+        order_date TEXT NOT NULL,
+        ref_genome TEXT NOT NULL,
+        disease_name TEXT,
+        indication_mesh_id TEXT
+    );
+    CREATE TABLE IF NOT EXISTS simple_variants (
+        h_nummer TEXT NOT NULL REFERENCES cases (h_nummer),
+        ref_genome TEXT NOT NULL,
+        ergebnis TEXT NOT NULL,
+        gene TEXT NOT NULL,
+        genomic_position TEXT NOT NULL,
+        cdna TEXT NOT NULL,
+        protein_orig TEXT NOT NULL,
+        protein TEXT NOT NULL,
+        chromosome TEXT NOT NULL,
+        ensembl_id TEXT NOT NULL,
+        hgnc_id TEXT NOT NULL,
+        hgnc_name TEXT NOT NULL,
+        start INTEGER,
+        end INTEGER,
+        alt_allele TEXT NOT NULL,
+        ref_allele TEXT NOT NULL,
+        read_depth INTEGER,
+        allelic_frequency REAL,
+        dbsnp TEXT NOT NULL,
+        classification TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS copy_number_variants (
+        h_nummer TEXT NOT NULL REFERENCES cases (h_nummer),
+        ref_genome TEXT NOT NULL,
+        ergebnis TEXT NOT NULL,
+        cnv_type TEXT NOT NULL,
+        gene TEXT NOT NULL,
+        chromosome TEXT NOT NULL,
+        ensembl_id TEXT NOT NULL,
+        hgnc_id TEXT NOT NULL,
+        hgnc_name TEXT NOT NULL,
+        total_copy_number REAL,
+        classification TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS fusions (
+        h_nummer TEXT NOT NULL REFERENCES cases (h_nummer),
+        ref_genome TEXT NOT NULL,
+        ergebnis TEXT NOT NULL,
+        gene TEXT NOT NULL,
+        fusion_gene TEXT NOT NULL,
+        ensembl_id_5 TEXT NOT NULL,
+        hgnc_id_5 TEXT NOT NULL,
+        hgnc_name_5 TEXT NOT NULL,
+        transcript_id_5 TEXT NOT NULL,
+        exon_id_5 TEXT NOT NULL,
+        transcript_position_5 INTEGER,
+        strand_5 TEXT NOT NULL,
+        ensembl_id_3 TEXT NOT NULL,
+        hgnc_id_3 TEXT NOT NULL,
+        hgnc_name_3 TEXT NOT NULL,
+        transcript_id_3 TEXT NOT NULL,
+        exon_id_3 TEXT NOT NULL,
+        transcript_position_3 INTEGER,
+        strand_3 TEXT NOT NULL,
+        number_reported_reads INTEGER,
+        classification TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS biomarkers (
+        h_nummer TEXT NOT NULL REFERENCES cases (h_nummer),
+        ref_genome TEXT NOT NULL,
+        ergebnis TEXT NOT NULL,
+        hrd REAL,
+        msi REAL,
+        tmb REAL
+    );
+    CREATE TABLE IF NOT EXISTS provenance (
+        h_nummer TEXT NOT NULL REFERENCES cases (h_nummer),
+        name TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (h_nummer, name)
+    );
+";
+
+/// Opens the database file and creates all tables, if they do not exist yet.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be opened or the tables cannot be created.
+pub(crate) fn open_database(path: &Path) -> Result<Connection, Box<dyn std::error::Error>> {
+    let connection = Connection::open(path)?;
+    connection.pragma_update(None, "foreign_keys", true)?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+/// Inserts or replaces the case with all of its records and their provenance.
+///
+/// The case is keyed by its H-number. An existing case is replaced, if the report version
+/// is the same or newer, otherwise the database is left untouched and `false` is returned.
+/// A missing report version is stored as `NULL` and considered older than any known version.
+///
+/// # Errors
+///
+/// Returns an error if the case cannot be written into the database.
+pub(crate) fn upsert_case(
+    connection: &mut Connection,
+    general: &General,
+    records: &Records,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let h_number = &general.patient_identifier.h_number;
+    let report_version = general.report_version;
+
+    let transaction = connection.transaction()?;
+
+    let existing_version = transaction
+        .query_row(
+            "SELECT report_version FROM cases WHERE h_nummer = ?1",
+            params![h_number],
+            |row| row.get::<_, Option<u32>>(0),
+        )
+        .optional()?;
+    if existing_version.is_some_and(|version| version > report_version) {
+        return Ok(false);
+    }
+
+    for table in [
+        "simple_variants",
+        "copy_number_variants",
+        "fusions",
+        "biomarkers",
+        "provenance",
+        "cases",
+    ] {
+        transaction.execute(
+            &format!("DELETE FROM {table} WHERE h_nummer = ?1"),
+            params![h_number],
+        )?;
+    }
+
+    transaction.execute(
+        "INSERT INTO cases VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            h_number,
+            report_version,
+            general.patient_identifier.pid,
+            general.order_date,
+            general.ref_genome_version.to_string(),
+            general.disease_name,
+            general.indication_mesh_id,
+        ],
+    )?;
+
+    insert_records(&transaction, records)?;
+
+    for (name, value) in records
+        .provenance
+        .as_ref()
+        .map(Provenance::entries)
+        .unwrap_or_default()
+    {
+        transaction.execute(
+            "INSERT INTO provenance VALUES (?1, ?2, ?3)",
+            params![h_number, name, value],
+        )?;
+    }
+
+    transaction.commit()?;
+    Ok(true)
+}
+
+fn insert_records(
+    transaction: &Transaction,
+    records: &Records,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for record in &records.simple_variants {
+        transaction.execute(
+            "INSERT INTO simple_variants VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+            params![
+                record.h_nummer,
+                record.ref_genome,
+                record.ergebnis,
+                record.gene,
+                record.genomic_position,
+                record.cdna,
+                record.protein_orig,
+                record.protein,
+                record.chromosome,
+                record.ensembl_id,
+                record.hgnc_id,
+                record.hgnc_name,
+                integer(&record.start),
+                integer(&record.end),
+                record.alt_allele,
+                record.ref_allele,
+                record.read_depth,
+                record.allelic_frequency.map(f64::from),
+                record.dbsnp,
+                record.classification,
+            ],
+        )?;
+    }
+
+    for record in &records.copy_numbers {
+        transaction.execute(
+            "INSERT INTO copy_number_variants VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                record.h_nummer,
+                record.ref_genome,
+                record.ergebnis,
+                record.cnv_type,
+                record.gene,
+                record.chromosome,
+                record.ensembl_id,
+                record.hgnc_id,
+                record.hgnc_name,
+                record.total_copy_number.map(f64::from),
+                record.classification,
+            ],
+        )?;
+    }
+
+    for record in &records.fusions {
+        transaction.execute(
+            "INSERT INTO fusions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+            params![
+                record.h_nummer,
+                record.ref_genome,
+                record.ergebnis,
+                record.gene,
+                record.fusion_gene,
+                record.ensembl_id_5,
+                record.hgnc_id_5,
+                record.hgnc_name_5,
+                record.transcript_id_5,
+                record.exon_id_5,
+                integer(&record.transcript_position_5),
+                record.strand_5,
+                record.ensembl_id_3,
+                record.hgnc_id_3,
+                record.hgnc_name_3,
+                record.transcript_id_3,
+                record.exon_id_3,
+                integer(&record.transcript_position_3),
+                record.strand_3,
+                integer(&record.number_reported_reads),
+                record.classification,
+            ],
+        )?;
+    }
+
+    for record in &records.biomarkers {
+        transaction.execute(
+            "INSERT INTO biomarkers VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                record.h_nummer,
+                record.ref_genome,
+                record.ergebnis,
+                record.hrd.map(f64::from),
+                record.msi.map(f64::from),
+                record.tmb.map(f64::from),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{BiomarkerRecord, Records};
+    use crate::mhguide::MhGuide;
+    use crate::provenance::{Provenance, sha256};
+    use crate::sqlite::{open_database, upsert_case};
+
+    fn records(mhguide: &MhGuide, tmb: f32) -> Records {
+        Records {
+            simple_variants: vec![],
+            copy_numbers: vec![],
+            fusions: vec![],
+            biomarkers: vec![BiomarkerRecord::from_tmb(
+                &mhguide.general.patient_identifier.h_number,
+                &mhguide.general.ref_genome_version,
+                tmb,
+            )],
+            provenance: Some(Provenance::from_content(b"mhguide")),
+        }
+    }
+
+    #[test]
+    fn test_should_upsert_case_by_report_version() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mhguide.db");
+
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mut mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+
+        let mut connection = open_database(&path).unwrap();
+
+        // Re-importing the same report version is idempotent
+        assert!(upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 1.0)).unwrap());
+        assert!(upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 1.0)).unwrap());
+
+        // Newer report version replaces the case
+        mhguide.general.report_version = Some(2);
+        assert!(upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 2.0)).unwrap());
+
+        // Older report version is ignored
+        mhguide.general.report_version = Some(1);
+        assert!(!upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 3.0)).unwrap());
+
+        // Missing report version does not replace a known report version
+        mhguide.general.report_version = None;
+        assert!(!upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 4.0)).unwrap());
+
+        let (count, tmb) = connection
+            .query_row("SELECT count(*), max(tmb) FROM biomarkers", [], |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, f64>(1)?))
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!((tmb - 2.0).abs() < f64::EPSILON);
+
+        let report_version = connection
+            .query_row("SELECT report_version FROM cases", [], |row| {
+                row.get::<_, u32>(0)
+            })
+            .unwrap();
+        assert_eq!(report_version, 2);
+
+        let input_sha256 = connection
+            .query_row(
+                "SELECT value FROM provenance WHERE name = 'inputSha256'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .unwrap();
+        assert_eq!(input_sha256, sha256(b"mhguide"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_should_store_missing_report_version_and_enforce_foreign_keys() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mhguide.db");
+
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mut mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        mhguide.general.report_version = None;
+
+        let mut connection = open_database(&path).unwrap();
+        assert!(upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 1.0)).unwrap());
+
+        let report_version = connection
+            .query_row("SELECT report_version FROM cases", [], |row| {
+                row.get::<_, Option<u32>>(0)
+            })
+            .unwrap();
+        assert_eq!(report_version, None);
+
+        // Known report version replaces the case without one
+        mhguide.general.report_version = Some(1);
+        assert!(upsert_case(&mut connection, &mhguide.general, &records(&mhguide, 2.0)).unwrap());
+
+        let result = connection.execute(
+            "INSERT INTO biomarkers VALUES ('H00000-00', 'HG38', 'Biomarker', NULL, NULL, 1.0)",
+            [],
+        );
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
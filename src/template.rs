@@ -0,0 +1,142 @@
+use crate::export_record::{ExportRecord, Records, ValueFormat};
+use crate::input::InputSource;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tera::{Context, Tera};
+
+#[derive(Serialize)]
+struct GeneralContext<'a> {
+    h_number: &'a str,
+    pid: &'a str,
+    order_date: &'a str,
+    ref_genome: String,
+    disease_name: Option<&'a str>,
+    indication_mesh_id: Option<&'a str>,
+    report_version: Option<u32>,
+}
+
+/// The full context passed to the template.
+///
+/// Records use the same keys as the CSV column headlines, e.g. `variant["HGNC ID"]`.
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    general: GeneralContext<'a>,
+    simple_variants: Vec<HashMap<String, String>>,
+    copy_number_variants: Vec<HashMap<String, String>>,
+    fusions: Vec<HashMap<String, String>>,
+    biomarkers: Vec<HashMap<String, String>>,
+    report_narrative: &'a str,
+}
+
+/// Maps each record to its formatted values keyed by the CSV column headlines
+fn record_values<T: ExportRecord>(records: &[T]) -> Vec<HashMap<String, String>> {
+    let value_format = ValueFormat::default();
+    records
+        .iter()
+        .map(|record| {
+            T::csv_headlines()
+                .into_iter()
+                .zip(record.formatted_values(value_format))
+                .collect()
+        })
+        .collect()
+}
+
+/// Renders the Tera template with the full context of the case
+pub(crate) fn render(
+    template: &str,
+    source: &dyn InputSource,
+    records: &Records,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let general = source.general();
+    let context = TemplateContext {
+        general: GeneralContext {
+            h_number: &general.patient_identifier.h_number,
+            pid: &general.patient_identifier.pid,
+            order_date: &general.order_date,
+            ref_genome: general.ref_genome_version.to_string(),
+            disease_name: general.disease_name.as_deref(),
+            indication_mesh_id: general.indication_mesh_id.as_deref(),
+            report_version: general.report_version,
+        },
+        simple_variants: record_values(&records.simple_variants),
+        copy_number_variants: record_values(&records.copy_numbers),
+        fusions: record_values(&records.fusions),
+        biomarkers: record_values(&records.biomarkers),
+        report_narrative: source.report_narrative(),
+    };
+
+    Tera::one_off(template, &Context::from_serialize(&context)?, false).map_err(Into::into)
+}
+
+/// Returns the extension of the output file based on the template file name,
+/// e.g. `befund.md` for `befund.md.tera` or `befund.md`.
+///
+/// The template name is kept as part of the extension, so the output file of a template like
+/// `befund.json.tera` never replaces the MH Guide input file.
+pub(crate) fn output_extension(template_path: &Path) -> String {
+    let file_name = template_path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file_name = file_name.strip_suffix(".tera").unwrap_or(&file_name);
+    match Path::new(file_name).extension() {
+        Some(_) => file_name.to_string(),
+        None => format!("{file_name}.txt"),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::export_record::{BiomarkerRecord, Records};
+    use crate::mhguide::MhGuide;
+    use crate::template::{output_extension, render};
+    use rstest::rstest;
+    use std::path::Path;
+
+    #[rstest]
+    #[case("befund.md.tera", "befund.md")]
+    #[case("befund.csv", "befund.csv")]
+    #[case("befund.tera", "befund.txt")]
+    #[case("befund.json.tera", "befund.json")]
+    #[case("befund.zip.tera", "befund.zip")]
+    #[case("vorlagen/befund.md.tera", "befund.md")]
+    fn test_should_use_output_extension(#[case] template_path: &str, #[case] expected: &str) {
+        assert_eq!(output_extension(Path::new(template_path)), expected);
+    }
+
+    #[test]
+    fn test_should_not_write_json_template_output_into_input_file() {
+        let mut output_file = Path::new("H12345678.json").to_path_buf();
+        output_file.set_extension(output_extension(Path::new("befund.json.tera")));
+        assert_eq!(output_file, Path::new("H12345678.befund.json"));
+    }
+
+    #[test]
+    fn test_should_render_template() {
+        let content = std::fs::read_to_string("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = serde_json::from_str::<MhGuide>(&content).unwrap();
+        let records = Records {
+            simple_variants: vec![],
+            copy_numbers: vec![],
+            fusions: vec![],
+            biomarkers: vec![BiomarkerRecord::from_tmb(
+                &mhguide.general.patient_identifier.h_number,
+                &mhguide.general.ref_genome_version,
+                5.2,
+            )],
+            provenance: None,
+        };
+
+        let actual = render(
+            "# {{ general.pid }} ({{ general.order_date }})\n{% for biomarker in biomarkers %}TMB: {{ biomarker[\"TMB - Tumor Mutational Burden\"] }}{% endfor %}",
+            &mhguide,
+            &records,
+        )
+        .unwrap();
+
+        assert_eq!(actual, "# PID0123456 (2026-02-11)\nTMB: 5,20");
+    }
+}
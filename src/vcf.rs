@@ -0,0 +1,614 @@
+use crate::VariantSelection;
+use crate::input::InputSource;
+use crate::mhguide::{
+    General, PatientIdentifier, RefGenomeVersion, ResultType, Variant, VariantEffect,
+    one_letter_protein_modification,
+};
+use crate::provenance::sha256;
+use flate2::read::MultiGzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Names of the used fields in VEP (`CSQ`) and `SnpEff` (`ANN`) annotations
+const ALLELE_FIELDS: [&str; 1] = ["Allele"];
+const GENE_FIELDS: [&str; 2] = ["SYMBOL", "Gene_Name"];
+const CONSEQUENCE_FIELDS: [&str; 2] = ["Consequence", "Annotation"];
+const CDNA_FIELDS: [&str; 2] = ["HGVSc", "HGVS.c"];
+const PROTEIN_FIELDS: [&str; 2] = ["HGVSp", "HGVS.p"];
+const EXISTING_VARIATION_FIELDS: [&str; 1] = ["Existing_variation"];
+
+/// Annotated VCF file of one tumor sample with VEP (`CSQ`) or `SnpEff` (`ANN`) annotations.
+///
+/// VCF files contain no oncogenicity classification and no `REPORT_NARRATIVE`, so the relevant
+/// variants are those with `FILTER` `PASS`, other variants are only used if all variants are
+/// selected and no variant is selected as oncogenic.
+/// Plain or gzip/bgzip compressed content is read.
+/// Biomarkers can be given as header lines `##TMB=`, `##MSI=` and `##HRD=`.
+/// VCF records have no stable variant ID like `DETECTED_VAR_ID`, so all variant IDs are 0.
+pub struct Vcf {
+    general: General,
+    variants: Vec<VcfVariant>,
+    tmb_value: Option<f32>,
+    msi_score: Option<f32>,
+    hrd_score: Option<f32>,
+}
+
+struct VcfVariant {
+    variant: Variant,
+    passed: bool,
+}
+
+/// Key and field names of the annotation in the INFO column
+struct AnnotationFormat {
+    key: String,
+    fields: Vec<String>,
+}
+
+impl AnnotationFormat {
+    /// Parses `##INFO=<ID=CSQ,...,Description="... Format: Allele|Consequence|...">` or the
+    /// `SnpEff` variant `Description="Functional annotations: 'Allele | Annotation | ...' "`
+    fn from_header(line: &str) -> Option<Self> {
+        let definition = line.strip_prefix("##INFO=<ID=")?;
+        let (key, _) = definition.split_once(',')?;
+        if key != "CSQ" && key != "ANN" {
+            return None;
+        }
+        let (_, description) = definition.split_once("Description=\"")?;
+        let format = match description.split_once("Format:") {
+            Some((_, format)) => format,
+            None => description.split_once(':')?.1,
+        };
+        let fields = format
+            .trim_end_matches('>')
+            .trim_end_matches('"')
+            .trim()
+            .trim_matches('\'')
+            .split('|')
+            .map(|field| field.trim().to_string())
+            .collect();
+        Some(AnnotationFormat {
+            key: key.to_string(),
+            fields,
+        })
+    }
+
+    /// Returns the annotation of the alternative allele, preferring the picked or canonical
+    /// transcript of VEP and the first, most severe, annotation of `SnpEff`
+    fn annotation<'a>(
+        &self,
+        info: &HashMap<&str, &'a str>,
+        ref_allele: &str,
+        alt_allele: &str,
+        single_alt_allele: bool,
+    ) -> HashMap<&str, &'a str> {
+        let Some(value) = info.get(self.key.as_str()) else {
+            return HashMap::new();
+        };
+        let allele = annotation_allele(ref_allele, alt_allele);
+        let annotations = value
+            .split(',')
+            .map(|annotation| {
+                self.fields
+                    .iter()
+                    .map(String::as_str)
+                    .zip(annotation.split('|'))
+                    .collect::<HashMap<_, _>>()
+            })
+            .filter(|annotation| {
+                single_alt_allele
+                    || field(annotation, &ALLELE_FIELDS)
+                        .is_some_and(|value| value == alt_allele || value == allele)
+            })
+            .collect::<Vec<_>>();
+
+        let preferred = annotations.iter().position(|annotation| {
+            annotation.get("PICK") == Some(&"1") || annotation.get("CANONICAL") == Some(&"YES")
+        });
+        annotations
+            .into_iter()
+            .nth(preferred.unwrap_or_default())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the first non empty value of the given field names
+fn field<'a>(values: &HashMap<&str, &'a str>, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .filter_map(|name| values.get(name))
+        .find(|value| !value.is_empty())
+        .copied()
+}
+
+/// Returns the allele as written by VEP, without the leading base shared with the reference
+fn annotation_allele(ref_allele: &str, alt_allele: &str) -> String {
+    if ref_allele.len() != alt_allele.len() && ref_allele.get(..1) == alt_allele.get(..1) {
+        match &alt_allele[1..] {
+            "" => "-".to_string(),
+            allele => allele.to_string(),
+        }
+    } else {
+        alt_allele.to_string()
+    }
+}
+
+/// Returns the variant type and the genomic change in HGVS notation as used in MH Guide files
+fn genomic_change(position: u64, ref_allele: &str, alt_allele: &str) -> (&'static str, String) {
+    let shared_first_base = ref_allele.get(..1) == alt_allele.get(..1);
+    match (ref_allele.len(), alt_allele.len()) {
+        (1, 1) => ("SNV", format!("g.{position}{ref_allele}>{alt_allele}")),
+        (1, _) if shared_first_base => (
+            "ins",
+            format!("g.{position}_{}ins{}", position + 1, &alt_allele[1..]),
+        ),
+        (2, 1) if shared_first_base => ("del", format!("g.{}del", position + 1)),
+        (ref_length, 1) if shared_first_base => (
+            "del",
+            format!("g.{}_{}del", position + 1, position + ref_length as u64 - 1),
+        ),
+        (ref_length, _) => (
+            "delins",
+            format!(
+                "g.{position}_{}delins{alt_allele}",
+                position + ref_length as u64 - 1
+            ),
+        ),
+    }
+}
+
+/// Removes the transcript or protein accession like `ENST00000123456.1:` from HGVS notations
+fn without_accession(hgvs: &str) -> &str {
+    hgvs.split_once(':').map_or(hgvs, |(_, change)| change)
+}
+
+fn ref_genome_version(line: &str) -> Option<RefGenomeVersion> {
+    let line = line.to_ascii_lowercase();
+    if line.contains("grch38") || line.contains("hg38") {
+        Some(RefGenomeVersion::Hg38)
+    } else if line.contains("grch37") || line.contains("hg19") {
+        Some(RefGenomeVersion::Hg19)
+    } else {
+        None
+    }
+}
+
+fn patient_identifier(sample: &str) -> PatientIdentifier {
+    match sample.split_once('_') {
+        Some((h_number, pid)) if !pid.contains('_') => PatientIdentifier {
+            h_number: h_number.to_string(),
+            pid: pid.to_string(),
+        },
+        _ => PatientIdentifier {
+            h_number: sample.to_string(),
+            pid: String::new(),
+        },
+    }
+}
+
+/// Returns the allele frequency in percent of the alternative allele from `AF` or `AD`
+#[allow(clippy::cast_possible_truncation)]
+fn allele_frequency(sample: &HashMap<&str, &str>, alt_index: usize) -> Option<f32> {
+    let frequency = if let Some(af) = sample.get("AF") {
+        af.split(',').nth(alt_index)?.parse::<f64>().ok()?
+    } else {
+        let depths = sample
+            .get("AD")?
+            .split(',')
+            .map(|depth| depth.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let total = depths.iter().sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+        f64::from(*depths.get(alt_index + 1)?) / f64::from(total)
+    };
+    Some(((frequency * 10_000.0).round() / 100.0) as f32)
+}
+
+impl Vcf {
+    /// Reads the content of an annotated VCF file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content is not a VCF file with a sample column, the reference
+    /// genome cannot be determined from the header lines or a record is invalid.
+    pub fn read(content: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let decompressed;
+        let content = if is_gzip(content) {
+            decompressed = gunzip(content)?;
+            decompressed.as_str()
+        } else {
+            std::str::from_utf8(content)?
+        };
+        if !content.starts_with("##fileformat=VCF") {
+            return Err("Invalid VCF file: missing fileformat header".into());
+        }
+
+        let mut ref_genome_version = None;
+        let mut order_date = String::new();
+        let mut tumor_sample = None;
+        let mut biomarkers = HashMap::new();
+        let mut annotation_format = None;
+        let mut samples = vec![];
+        let mut variants = vec![];
+
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            if let Some(meta) = line.strip_prefix("##") {
+                if let Some(format) = AnnotationFormat::from_header(line) {
+                    annotation_format = Some(format);
+                } else if let Some(date) = meta.strip_prefix("fileDate=")
+                    && date.len() == 8
+                {
+                    order_date = format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..]);
+                } else if let Some(sample) = meta.strip_prefix("tumor_sample=") {
+                    tumor_sample = Some(sample.to_string());
+                } else if let Some((key, value)) = meta.split_once('=')
+                    && ["TMB", "MSI", "HRD"].contains(&key.to_ascii_uppercase().as_str())
+                {
+                    biomarkers.insert(key.to_ascii_uppercase(), value.trim().parse::<f32>()?);
+                }
+                if ref_genome_version.is_none() {
+                    ref_genome_version = self::ref_genome_version(line);
+                }
+            } else if let Some(header) = line.strip_prefix("#CHROM") {
+                samples = header.split('\t').skip(9).map(str::to_string).collect();
+            } else if samples.is_empty() {
+                return Err("Invalid VCF file: missing header line with sample column".into());
+            } else {
+                let sample_index = tumor_sample
+                    .as_ref()
+                    .and_then(|name| samples.iter().position(|sample| sample == name))
+                    .unwrap_or_default();
+                variants.append(&mut Self::read_record(
+                    line,
+                    sample_index,
+                    annotation_format.as_ref(),
+                )?);
+            }
+        }
+
+        let sample = tumor_sample
+            .or_else(|| samples.first().cloned())
+            .ok_or("Invalid VCF file: missing header line with sample column")?;
+
+        Ok(Vcf {
+            general: General {
+                order_date,
+                ref_genome_version: ref_genome_version
+                    .ok_or("Invalid VCF file: unknown reference genome")?,
+                patient_identifier: patient_identifier(&sample),
+                disease_name: None,
+                indication_mesh_id: None,
+                report_version: None,
+                case_uuid: None,
+                input_sha256: Some(sha256(content.as_bytes())),
+            },
+            variants,
+            tmb_value: biomarkers.get("TMB").copied(),
+            msi_score: biomarkers.get("MSI").copied(),
+            hrd_score: biomarkers.get("HRD").copied(),
+        })
+    }
+
+    /// Reads all alternative alleles of one record as variants
+    fn read_record(
+        line: &str,
+        sample_index: usize,
+        annotation_format: Option<&AnnotationFormat>,
+    ) -> Result<Vec<VcfVariant>, Box<dyn std::error::Error>> {
+        let columns = line.split('\t').collect::<Vec<_>>();
+        if columns.len() < 8 {
+            return Err(format!("Invalid VCF record: {line}").into());
+        }
+        let chromosome = if columns[0].starts_with("chr") {
+            columns[0].to_string()
+        } else {
+            format!("chr{}", columns[0])
+        };
+        let position = columns[1].parse::<u64>()?;
+        let ref_allele = columns[3];
+        let alt_alleles = columns[4].split(',').collect::<Vec<_>>();
+        let passed = columns[6] == "PASS" || columns[6] == ".";
+        let info = columns[7]
+            .split(';')
+            .map(|entry| entry.split_once('=').unwrap_or((entry, "")))
+            .collect::<HashMap<_, _>>();
+        let sample = match (columns.get(8), columns.get(9 + sample_index)) {
+            (Some(format), Some(values)) => format.split(':').zip(values.split(':')).collect(),
+            _ => HashMap::new(),
+        };
+        let db_snp = columns[2]
+            .split(';')
+            .find(|id| id.starts_with("rs"))
+            .map(str::to_string);
+
+        let mut variants = vec![];
+        for (alt_index, alt_allele) in alt_alleles.iter().enumerate() {
+            if *alt_allele == "." || *alt_allele == "*" {
+                continue;
+            }
+            let annotation = annotation_format
+                .map(|format| {
+                    format.annotation(&info, ref_allele, alt_allele, alt_alleles.len() == 1)
+                })
+                .unwrap_or_default();
+            let gene_symbol = field(&annotation, &GENE_FIELDS)
+                .or_else(|| info.get("GENE").copied())
+                .map(str::to_string);
+
+            let variant = if alt_allele.starts_with('<') {
+                let Some(variant) = Self::copy_number_variant(
+                    &info,
+                    &sample,
+                    alt_allele,
+                    gene_symbol,
+                    chromosome.clone(),
+                ) else {
+                    continue;
+                };
+                variant
+            } else {
+                let (variant_type, chromosome_modification) =
+                    genomic_change(position, ref_allele, alt_allele);
+                Variant {
+                    id: 0,
+                    gene_symbol,
+                    protein_modification: field(&annotation, &PROTEIN_FIELDS)
+                        .map(|hgvsp| one_letter_protein_modification(without_accession(hgvsp))),
+                    protein_variant_type: None,
+                    display_variant_type: Some(ResultType::SimpleVariant(variant_type.to_string())),
+                    chromosome: Some(chromosome.clone()),
+                    chromosome_modification: Some(chromosome_modification),
+                    transcript_hgvs_modified_object: field(&annotation, &CDNA_FIELDS)
+                        .map(|hgvsc| without_accession(hgvsc).to_string()),
+                    total_reads_in_tumor: sample
+                        .get("DP")
+                        .or_else(|| info.get("DP"))
+                        .and_then(|depth| depth.parse().ok()),
+                    variant_allele_frequency_in_tumor: allele_frequency(&sample, alt_index),
+                    db_snp: db_snp.clone().or_else(|| {
+                        field(&annotation, &EXISTING_VARIATION_FIELDS).and_then(|variation| {
+                            variation
+                                .split('&')
+                                .find(|id| id.starts_with("rs"))
+                                .map(str::to_string)
+                        })
+                    }),
+                    copy_number: None,
+                    classification_name: None,
+                    oncogenic_classification_name: None,
+                    variant_effect: field(&annotation, &CONSEQUENCE_FIELDS).map(|consequence| {
+                        VariantEffect::Other(
+                            consequence
+                                .split('&')
+                                .next()
+                                .unwrap_or_default()
+                                .to_string(),
+                        )
+                    }),
+                }
+            };
+
+            variants.push(VcfVariant { variant, passed });
+        }
+        Ok(variants)
+    }
+
+    /// Creates a copy number variant of symbolic alleles `<CNV>`, `<DEL>` or `<DUP>` with the
+    /// total copy number in `CN`
+    fn copy_number_variant(
+        info: &HashMap<&str, &str>,
+        sample: &HashMap<&str, &str>,
+        alt_allele: &str,
+        gene_symbol: Option<String>,
+        chromosome: String,
+    ) -> Option<Variant> {
+        let copy_number = sample
+            .get("CN")
+            .or_else(|| info.get("CN"))
+            .and_then(|copy_number| copy_number.parse::<f32>().ok());
+        let sv_type = info
+            .get("SVTYPE")
+            .copied()
+            .unwrap_or(alt_allele.trim_matches(['<', '>']));
+        let gain = match (sv_type, copy_number) {
+            ("DUP", _) => true,
+            ("DEL", _) => false,
+            ("CNV", Some(copy_number)) if copy_number > 2.0 => true,
+            ("CNV", Some(copy_number)) if copy_number < 2.0 => false,
+            _ => return None,
+        };
+
+        Some(Variant {
+            id: 0,
+            gene_symbol,
+            protein_modification: Some(
+                if gain {
+                    "Copy number gain"
+                } else {
+                    "Copy number loss"
+                }
+                .to_string(),
+            ),
+            protein_variant_type: None,
+            display_variant_type: Some(ResultType::CopyNumberVariant),
+            chromosome: Some(chromosome),
+            chromosome_modification: None,
+            transcript_hgvs_modified_object: None,
+            total_reads_in_tumor: None,
+            variant_allele_frequency_in_tumor: None,
+            db_snp: None,
+            copy_number,
+            classification_name: None,
+            oncogenic_classification_name: None,
+            variant_effect: Some(if gain {
+                VariantEffect::CopyGain
+            } else {
+                VariantEffect::CopyLoss
+            }),
+        })
+    }
+}
+
+/// Returns true if the content starts with the gzip magic number, also used by bgzip
+pub(crate) fn is_gzip(content: &[u8]) -> bool {
+    content.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses all gzip members, bgzip files consist of many of them
+fn gunzip(content: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    let mut decompressed = String::new();
+    MultiGzDecoder::new(content).read_to_string(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+impl InputSource for Vcf {
+    fn general(&self) -> &General {
+        &self.general
+    }
+
+    fn variants(&self, selection: VariantSelection) -> Vec<&Variant> {
+        self.variants
+            .iter()
+            .filter(|variant| match selection {
+                VariantSelection::Relevant | VariantSelection::RelevantWithoutArtifacts => {
+                    variant.passed
+                }
+                VariantSelection::Oncogenic => false,
+                VariantSelection::All => true,
+            })
+            .map(|variant| &variant.variant)
+            .collect()
+    }
+
+    fn hrd_score(&self) -> Option<f32> {
+        self.hrd_score
+    }
+
+    fn msi_score(&self) -> Option<f32> {
+        self.msi_score
+    }
+
+    fn tmb_value(&self) -> Option<f32> {
+        self.tmb_value
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::input::InputSource;
+    use crate::mhguide::RefGenomeVersion;
+    use crate::vcf::{Vcf, genomic_change};
+    use crate::{SimpleVariantRecord, VariantSelection, read, records};
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use rstest::rstest;
+    use std::io::Write;
+
+    #[rstest]
+    #[case(12_345_678, "G", "A", "SNV", "g.12345678G>A")]
+    #[case(100, "A", "AGT", "ins", "g.100_101insGT")]
+    #[case(100, "AT", "A", "del", "g.101del")]
+    #[case(100, "ATGC", "A", "del", "g.101_103del")]
+    #[case(100, "AT", "GC", "delins", "g.100_101delinsGC")]
+    fn test_should_create_genomic_change(
+        #[case] position: u64,
+        #[case] ref_allele: &str,
+        #[case] alt_allele: &str,
+        #[case] variant_type: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            genomic_change(position, ref_allele, alt_allele),
+            (variant_type, expected.to_string())
+        );
+    }
+
+    #[test]
+    fn test_should_read_vep_vcf() {
+        let vcf = Vcf::read(include_bytes!("../testfiles/sv-vep.vcf")).unwrap();
+
+        let general = vcf.general();
+        assert_eq!(general.order_date, "2026-02-11");
+        assert_eq!(general.ref_genome_version, RefGenomeVersion::Hg19);
+        assert_eq!(general.patient_identifier.h_number, "H10000-26");
+        assert_eq!(general.patient_identifier.pid, "PID0123456");
+        assert_eq!(vcf.tmb_value(), Some(12.3));
+
+        assert_eq!(vcf.variants(VariantSelection::All).len(), 4);
+        assert!(vcf.variants(VariantSelection::Oncogenic).is_empty());
+        let records = records(&vcf, VariantSelection::Relevant);
+        assert_eq!(records.simple_variants.len(), 2);
+        assert_eq!(records.copy_numbers.len(), 1);
+        assert_eq!(records.copy_numbers[0].cnv_type, "high level gain");
+        assert_eq!(records.biomarkers.len(), 1);
+    }
+
+    #[test]
+    fn test_should_create_same_records_as_mhguide() {
+        let content = std::fs::read("./testfiles/sv-mhguide.json").unwrap();
+        let mhguide = read(&content).unwrap();
+        let vcf = Vcf::read(include_bytes!("../testfiles/sv-vep.vcf")).unwrap();
+
+        let without_classification = |record: &SimpleVariantRecord| {
+            serde_json::to_value(SimpleVariantRecord {
+                classification: String::new(),
+                ..record.clone()
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            without_classification(&records(&vcf, VariantSelection::Relevant).simple_variants[0]),
+            without_classification(&records(&mhguide, VariantSelection::All).simple_variants[0])
+        );
+    }
+
+    #[test]
+    fn test_should_read_snpeff_vcf() {
+        let content = "##fileformat=VCFv4.2\n\
+            ##reference=GRCh38\n\
+            ##INFO=<ID=ANN,Number=.,Type=String,Description=\"Functional annotations: 'Allele | Annotation | Annotation_Impact | Gene_Name | Gene_ID | Feature_Type | Feature_ID | Transcript_BioType | Rank | HGVS.c | HGVS.p | cDNA.pos / cDNA.length | CDS.pos / CDS.length | AA.pos / AA.length | Distance | ERRORS / WARNINGS / INFO' \">\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tH20000-26_PID0654321\n\
+            7\t140753336\t.\tA\tT\t.\tPASS\tANN=T|missense_variant|MODERATE|BRAF|ENSG00000157764|transcript|ENST00000646891.2|protein_coding|15/18|c.1799T>A|p.Val600Glu||||||\tGT:AD:DP\t0/1:60,40:100\n";
+        let vcf = Vcf::read(content.as_bytes()).unwrap();
+
+        assert_eq!(vcf.general().ref_genome_version, RefGenomeVersion::Hg38);
+        let variants = vcf.variants(VariantSelection::Relevant);
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].gene_symbol.as_deref(), Some("BRAF"));
+        assert_eq!(variants[0].chromosome.as_deref(), Some("chr7"));
+        assert_eq!(variants[0].protein_modification.as_deref(), Some("p.V600E"));
+        assert_eq!(
+            variants[0].transcript_hgvs_modified_object.as_deref(),
+            Some("c.1799T>A")
+        );
+        assert_eq!(variants[0].variant_allele_frequency_in_tumor, Some(40.0));
+    }
+
+    #[test]
+    fn test_should_read_gzip_compressed_vcf() {
+        let content = include_bytes!("../testfiles/sv-vep.vcf");
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(
+            Vcf::read(&compressed)
+                .unwrap()
+                .variants(VariantSelection::All)
+                .len(),
+            Vcf::read(content)
+                .unwrap()
+                .variants(VariantSelection::All)
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_should_reject_vcf_without_reference_genome() {
+        let content = "##fileformat=VCFv4.2\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tH20000-26_PID0654321\n";
+        assert!(Vcf::read(content.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use mhguide_to_csv::is_vcf_file;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Size and modification time of a file seen in the watched directory
+type FileState = (u64, Option<SystemTime>);
+
+fn is_input_file(path: &Path) -> bool {
+    path.is_file()
+        && (is_vcf_file(path)
+            || path.extension().is_some_and(|ext| {
+                ["json", "zip"]
+                    .iter()
+                    .any(|input_ext| ext.eq_ignore_ascii_case(input_ext))
+            }))
+}
+
+/// Moves the file into the subfolder of its directory and returns the new path.
+///
+/// If the subfolder already contains a file with the same name, a counter is inserted before
+/// all extensions, e.g. `H12345678-2.json` or `H12345678-2.vcf.gz`, so earlier files and their
+/// outputs are kept and the file type is still recognized.
+fn move_to(path: &Path, folder: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let file_name = path.file_name().ok_or("Invalid file name")?;
+    let mut target = folder.join(file_name);
+    let file_name = file_name.to_string_lossy();
+    // A leading dot of hidden files is not an extension
+    let (stem, extensions) = match file_name.char_indices().skip(1).find(|(_, c)| *c == '.') {
+        Some((index, _)) => file_name.split_at(index),
+        None => (file_name.as_ref(), ""),
+    };
+    let mut counter = 1;
+    while target.exists() {
+        counter += 1;
+        target = folder.join(format!("{stem}-{counter}{extensions}"));
+    }
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+fn file_names(folder: &Path) -> Result<HashSet<PathBuf>, std::io::Error> {
+    fs::read_dir(folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect()
+}
+
+/// Returns all files in the directory that are complete and ready to convert.
+///
+/// A file is complete, if its size and modification time did not change since the previous
+/// call. All other files are remembered in `pending` for the next call.
+fn complete_files(
+    path: &Path,
+    pending: &mut HashMap<PathBuf, FileState>,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut current = HashMap::new();
+    let mut result = vec![];
+
+    for entry in fs::read_dir(path)? {
+        let file = entry?.path();
+        if !is_input_file(&file) {
+            continue;
+        }
+        let metadata = fs::metadata(&file)?;
+        let state = (metadata.len(), metadata.modified().ok());
+        if pending.get(&file) == Some(&state) {
+            result.push(file);
+        } else {
+            current.insert(file, state);
+        }
+    }
+
+    *pending = current;
+    result.sort();
+    Ok(result)
+}
+
+/// Converts one file and moves it into `done/` or, if conversion fails, into `error/`.
+///
+/// Converted files are moved before conversion, so the output is written next to them in `done/`.
+/// If conversion fails, outputs already written into `done/` are removed.
+fn process_file(
+    file: &Path,
+    done: &Path,
+    error: &Path,
+    convert: &impl Fn(&Path) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let existing_files = file_names(done)?;
+    let moved_file = move_to(file, done)?;
+    match convert(&moved_file) {
+        Ok(()) => println!("{}: Konvertiert", file.display()),
+        Err(err) => {
+            move_to(&moved_file, error)?;
+            for output_file in file_names(done)?.difference(&existing_files) {
+                fs::remove_file(output_file)?;
+            }
+            eprintln!("{}: Fehler: {err}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Watches the directory for new or changed JSON, ZIP and VCF files and converts them.
+///
+/// The directory is polled in the given interval, which also works on network shares without
+/// file system notifications. Errors reading the directory are logged and the directory is read
+/// again in the next interval. This function only returns, if the subfolders cannot be created.
+pub(crate) fn watch_directory(
+    path: &Path,
+    interval: Duration,
+    convert: impl Fn(&Path) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let done = path.join("done");
+    let error = path.join("error");
+    fs::create_dir_all(&done)?;
+    fs::create_dir_all(&error)?;
+
+    println!("Überwache {}", path.display());
+
+    let mut pending = HashMap::new();
+    loop {
+        match complete_files(path, &mut pending) {
+            Ok(files) => {
+                for file in files {
+                    if let Err(err) = process_file(&file, &done, &error, &convert) {
+                        eprintln!("{}: Fehler: {err}", file.display());
+                    }
+                }
+            }
+            Err(err) => eprintln!("{}: Fehler: {err}", path.display()),
+        }
+        thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use crate::watch::{complete_files, is_input_file, move_to, process_file};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_should_convert_complete_files_and_move_them() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let done = path.join("done");
+        let error = path.join("error");
+        fs::create_dir_all(&done).unwrap();
+        fs::create_dir_all(&error).unwrap();
+
+        fs::write(path.join("valid.json"), "{}").unwrap();
+        fs::write(path.join("invalid.json"), "{}").unwrap();
+        fs::write(path.join("notes.txt"), "").unwrap();
+
+        let mut pending = HashMap::new();
+
+        // Files are only complete after they did not change between two calls
+        assert!(complete_files(&path, &mut pending).unwrap().is_empty());
+        let files = complete_files(&path, &mut pending).unwrap();
+        assert_eq!(
+            files,
+            vec![path.join("invalid.json"), path.join("valid.json")]
+        );
+
+        for file in files {
+            process_file(&file, &done, &error, &|file| {
+                fs::write(file.with_extension("csv"), "").unwrap();
+                if file.ends_with("valid.json") {
+                    Ok(())
+                } else {
+                    Err("invalid".into())
+                }
+            })
+            .unwrap();
+        }
+
+        assert!(done.join("valid.json").exists());
+        assert!(done.join("valid.csv").exists());
+        assert!(error.join("invalid.json").exists());
+        assert!(!done.join("invalid.csv").exists());
+        assert!(path.join("notes.txt").exists());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_not_replace_files_with_same_name() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let done = path.join("done");
+        fs::create_dir_all(&done).unwrap();
+
+        fs::write(path.join("H12345678.json"), "1").unwrap();
+        let first = move_to(&path.join("H12345678.json"), &done).unwrap();
+        fs::write(path.join("H12345678.json"), "2").unwrap();
+        let second = move_to(&path.join("H12345678.json"), &done).unwrap();
+
+        assert_eq!(first, done.join("H12345678.json"));
+        assert_eq!(second, done.join("H12345678-2.json"));
+        assert_eq!(fs::read_to_string(first).unwrap(), "1");
+        assert_eq!(fs::read_to_string(second).unwrap(), "2");
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_should_keep_compound_extension_of_files_with_same_name() {
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let done = path.join("done");
+        fs::create_dir_all(&done).unwrap();
+
+        fs::write(path.join("H12345678.vcf.gz"), "1").unwrap();
+        move_to(&path.join("H12345678.vcf.gz"), &done).unwrap();
+        fs::write(path.join("H12345678.vcf.gz"), "2").unwrap();
+        let second = move_to(&path.join("H12345678.vcf.gz"), &done).unwrap();
+
+        assert_eq!(second, done.join("H12345678-2.vcf.gz"));
+        assert!(is_input_file(&second));
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}